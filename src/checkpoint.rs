@@ -0,0 +1,258 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::format::TileFormat;
+
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// Number of written tiles between checkpoint commits.
+pub const CHECKPOINT_INTERVAL_TILES: u64 = 10_000;
+
+/// Checkpoint settings passed from the CLI into the prune pipelines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    pub resume: bool,
+    pub style_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputFingerprint {
+    pub path: String,
+    pub format: String,
+    pub size_bytes: u64,
+    pub modified_secs: u64,
+    pub modified_nanos: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputIdentity {
+    pub path: String,
+    pub format: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temporary_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointIdentity {
+    pub input: InputFingerprint,
+    pub output: OutputIdentity,
+    pub style_hash: String,
+    pub options_fingerprint: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowidRangeProgress {
+    pub start: i64,
+    pub end: i64,
+    /// Every rowid in `start..=committed` has been written to the output.
+    pub committed: Option<i64>,
+    /// Rowids above `committed` that were written out of order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub written_above: Vec<i64>,
+}
+
+impl RowidRangeProgress {
+    pub fn is_done(&self, rowid: i64) -> bool {
+        self.committed.is_some_and(|committed| rowid <= committed)
+            || self.written_above.binary_search(&rowid).is_ok()
+    }
+}
+
+/// A tile written in the same transaction as a pending watermark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileMarker {
+    pub zoom: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CheckpointProgress {
+    Rowid {
+        ranges: Vec<RowidRangeProgress>,
+    },
+    TileId {
+        /// Last tile_id written to the temporary data file.
+        committed: Option<u64>,
+        /// Byte length of the temporary data file covered by `committed`.
+        data_bytes: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingProgress {
+    pub progress: CheckpointProgress,
+    pub marker: TileMarker,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    #[serde(flatten)]
+    pub identity: CheckpointIdentity,
+    pub progress: CheckpointProgress,
+    /// Progress saved right before an output commit; adopted on resume only
+    /// if `marker` made it into the output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending: Option<PendingProgress>,
+}
+
+impl Checkpoint {
+    pub fn new(identity: CheckpointIdentity, progress: CheckpointProgress) -> Self {
+        Self {
+            version: CHECKPOINT_VERSION,
+            identity,
+            progress,
+            pending: None,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(path)
+            .with_context(|| format!("failed to read checkpoint: {}", path.display()))?;
+        let checkpoint: Checkpoint = serde_json::from_slice(&data)
+            .with_context(|| format!("failed to parse checkpoint: {}", path.display()))?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            anyhow::bail!(
+                "unsupported checkpoint version {} (expected {CHECKPOINT_VERSION})",
+                checkpoint.version
+            );
+        }
+        Ok(Some(checkpoint))
+    }
+
+    /// Write the checkpoint via a temporary file and rename so a crash never
+    /// leaves a truncated state file behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp_name = path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+        let data = serde_json::to_vec_pretty(self)?;
+        fs::write(&tmp_path, data)
+            .with_context(|| format!("failed to write checkpoint: {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("failed to replace checkpoint: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn ensure_matches(&self, identity: &CheckpointIdentity) -> Result<()> {
+        let mut mismatches = Vec::new();
+        if self.identity.input != identity.input {
+            mismatches.push("input fingerprint");
+        }
+        if self.identity.output.path != identity.output.path
+            || self.identity.output.format != identity.output.format
+        {
+            mismatches.push("output");
+        }
+        if self.identity.style_hash != identity.style_hash {
+            mismatches.push("style hash");
+        }
+        if self.identity.options_fingerprint != identity.options_fingerprint {
+            mismatches.push("options fingerprint");
+        }
+        if !mismatches.is_empty() {
+            anyhow::bail!(
+                "checkpoint does not match this run ({} changed); refusing to resume",
+                mismatches.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn remove_checkpoint(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => {
+            Err(err).with_context(|| format!("failed to remove checkpoint: {}", path.display()))
+        }
+    }
+}
+
+pub fn fingerprint_input(path: &Path, format: TileFormat) -> Result<InputFingerprint> {
+    let meta = fs::metadata(path)
+        .with_context(|| format!("failed to read input metadata: {}", path.display()))?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    Ok(InputFingerprint {
+        path: path.display().to_string(),
+        format: format.extension_str().to_string(),
+        size_bytes: meta.len(),
+        modified_secs: modified.as_secs(),
+        modified_nanos: modified.subsec_nanos(),
+    })
+}
+
+pub fn hash_file(path: &Path) -> Result<String> {
+    let data =
+        fs::read(path).with_context(|| format!("failed to read file: {}", path.display()))?;
+    Ok(format!("{:016x}", fnv1a64(&data)))
+}
+
+/// Fingerprint of the options that change the bytes written for each tile.
+/// Thread and cache settings are deliberately left out so a resume may tune them.
+pub fn prune_options_fingerprint(
+    apply_filters: bool,
+    keep_unknown_filters: bool,
+    drop_empty_tiles: bool,
+    prune_attributes: bool,
+    keep_languages: Option<&[String]>,
+    rules: Option<&crate::rules::AttributeRules>,
+) -> String {
+    let keep_languages = keep_languages.map(|languages| {
+        let mut languages = languages.to_vec();
+        languages.sort_unstable();
+        languages.join(",")
+    });
+    let rules = rules.and_then(|rules| serde_json::to_string(rules).ok());
+    let text = format!(
+        "apply_filters={apply_filters};keep_unknown_filters={keep_unknown_filters};\
+drop_empty_tiles={drop_empty_tiles};prune_attributes={prune_attributes};\
+keep_languages={keep_languages:?};rules={rules:?}"
+    );
+    format!("{:016x}", fnv1a64(text.as_bytes()))
+}
+
+pub fn checkpoint_identity(
+    input: &Path,
+    input_format: TileFormat,
+    output: &Path,
+    output_format: TileFormat,
+    style_hash: &str,
+    options_fingerprint: String,
+) -> Result<CheckpointIdentity> {
+    Ok(CheckpointIdentity {
+        input: fingerprint_input(input, input_format)?,
+        output: OutputIdentity {
+            path: output.display().to_string(),
+            format: output_format.extension_str().to_string(),
+            temporary_path: None,
+        },
+        style_hash: style_hash.to_string(),
+        options_fingerprint,
+    })
+}
+
+fn fnv1a64(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in data {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -44,6 +44,14 @@ pub struct Cli {
     #[arg(short = 't')]
     pub tolerance: Option<f64>,
 
+    /// Checkpoint state file for resumable optimize runs.
+    #[arg(long)]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Resume optimize from --checkpoint when it exists.
+    #[arg(long, default_value_t = false)]
+    pub resume: bool,
+
     /// Log level (error|warn|info|debug|trace)
     #[arg(long, default_value = "info")]
     pub log: String,
@@ -56,6 +64,7 @@ pub enum Command {
     Simplify(SimplifyArgs),
     Copy(CopyArgs),
     Verify(VerifyArgs),
+    Export(ExportArgs),
 }
 
 #[derive(Debug, Args)]
@@ -158,6 +167,10 @@ pub struct InspectArgs {
     /// Tile summary detail level (full or compact).
     #[arg(long, value_enum, default_value_t = TileInfoFormat::Full)]
     pub tile_info_format: TileInfoFormat,
+
+    /// How MBTiles `tile_row` values are numbered (tms per the spec, or xyz).
+    #[arg(long, value_enum, default_value_t = MbtilesSchemeArg::Tms)]
+    pub mbtiles_scheme: MbtilesSchemeArg,
 }
 
 #[derive(Debug, Args)]
@@ -177,8 +190,14 @@ pub struct OptimizeArgs {
     #[arg(long = "report-format", value_enum, default_value_t = ReportFormat::Text)]
     pub report_format: ReportFormat,
 
+    /// Style JSON; repeat to keep everything any of the styles renders.
     #[arg(long)]
-    pub style: Option<PathBuf>,
+    pub style: Vec<PathBuf>,
+
+    /// Style source id whose layers are used; matched against the input's
+    /// file name and metadata when omitted.
+    #[arg(long)]
+    pub source: Option<String>,
 
     #[arg(long, value_enum, default_value_t = StyleMode::LayerFilter)]
     pub style_mode: StyleMode,
@@ -207,11 +226,32 @@ pub struct OptimizeArgs {
     #[arg(long, default_value_t = false)]
     pub drop_empty_tiles: bool,
 
+    /// Drop feature properties that no visible style layer reads in its
+    /// filter, layout or paint.
+    #[arg(long, default_value_t = false)]
+    pub prune_attributes: bool,
+
+    /// Keep only these languages among `name:<lang>` and `name_<lang>`
+    /// properties (comma separated, e.g. `en,ja,de`).
+    #[arg(long, value_delimiter = ',')]
+    pub keep_languages: Vec<String>,
+
+    /// JSON or TOML rules file with per-layer keep/drop/rename/cast of
+    /// feature properties.
+    #[arg(long)]
+    pub rules: Option<PathBuf>,
+
+    /// Checkpoint state file; progress is recorded as output is committed.
     #[arg(long)]
     pub checkpoint: Option<PathBuf>,
 
+    /// Resume from --checkpoint when it exists (refuses on fingerprint mismatch).
     #[arg(long, default_value_t = false)]
     pub resume: bool,
+
+    /// How MBTiles `tile_row` values are numbered when converting to or from PMTiles.
+    #[arg(long, value_enum, default_value_t = MbtilesSchemeArg::Tms)]
+    pub mbtiles_scheme: MbtilesSchemeArg,
 }
 
 #[derive(Debug, Args)]
@@ -221,20 +261,70 @@ pub struct SimplifyArgs {
     #[arg(long)]
     pub output: Option<PathBuf>,
 
+    /// With --x and --y, simplify only this tile; without them the whole
+    /// tileset is simplified.
     #[arg(long)]
-    pub z: u8,
+    pub z: Option<u8>,
 
     #[arg(long)]
-    pub x: u32,
+    pub x: Option<u32>,
 
     #[arg(long)]
-    pub y: u32,
+    pub y: Option<u32>,
 
-    #[arg(long)]
+    /// Layers to keep in the single --z/--x/--y tile.
+    #[arg(long, requires = "z")]
     pub layer: Vec<String>,
 
+    /// Tileset: layers --tolerance applies to; other layers are left as they
+    /// are.
+    #[arg(long = "simplify-layer", conflicts_with = "z")]
+    pub simplify_layers: Vec<String>,
+
     #[arg(long)]
     pub tolerance: Option<f64>,
+
+    /// Tileset tolerance for a layer and/or zoom range, as
+    /// `[LAYER][@ZOOM[-ZOOM]]=TOLERANCE` (repeatable; later rules win).
+    #[arg(long = "tolerance-rule")]
+    pub tolerance_rules: Vec<String>,
+
+    /// Lowest zoom to simplify; other tiles are copied unchanged.
+    #[arg(long)]
+    pub minzoom: Option<u8>,
+
+    /// Highest zoom to simplify; other tiles are copied unchanged.
+    #[arg(long)]
+    pub maxzoom: Option<u8>,
+
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    #[arg(long)]
+    pub readers: Option<usize>,
+
+    #[arg(long, default_value_t = 1_000)]
+    pub io_batch: u32,
+
+    #[arg(long)]
+    pub read_cache_mb: Option<u64>,
+
+    #[arg(long)]
+    pub write_cache_mb: Option<u64>,
+
+    /// Line simplification algorithm. For visvalingam the tolerance is the
+    /// square root of the smallest triangle area to keep.
+    #[arg(long, value_enum, default_value_t = SimplifyAlgorithmArg::DouglasPeucker)]
+    pub algorithm: SimplifyAlgorithmArg,
+
+    /// Simplify edges shared by features of a tile once, and keep polygon
+    /// rings from collapsing or self-intersecting.
+    #[arg(long, default_value_t = false)]
+    pub preserve_topology: bool,
+
+    /// How MBTiles `tile_row` values are numbered (tms per the spec, or xyz).
+    #[arg(long, value_enum, default_value_t = MbtilesSchemeArg::Tms)]
+    pub mbtiles_scheme: MbtilesSchemeArg,
 }
 
 #[derive(Debug, Args)]
@@ -249,11 +339,65 @@ pub struct CopyArgs {
 
     #[arg(long)]
     pub output_format: Option<String>,
+
+    /// How MBTiles `tile_row` values are numbered (tms per the spec, or xyz).
+    #[arg(long, value_enum, default_value_t = MbtilesSchemeArg::Tms)]
+    pub mbtiles_scheme: MbtilesSchemeArg,
+
+    /// PMTiles to PMTiles: directory/metadata compression (defaults to the input's).
+    #[arg(long, value_enum)]
+    pub internal_compression: Option<CompressionArg>,
+
+    /// PMTiles to PMTiles: tile compression (defaults to the input's).
+    #[arg(long, value_enum)]
+    pub tile_compression: Option<CompressionArg>,
 }
 
 #[derive(Debug, Args)]
 pub struct VerifyArgs {
     pub input: PathBuf,
+
+    /// How MBTiles `tile_row` values are numbered (tms per the spec, or xyz).
+    #[arg(long, value_enum, default_value_t = MbtilesSchemeArg::Tms)]
+    pub mbtiles_scheme: MbtilesSchemeArg,
+
+    /// Report format (text/json/ndjson).
+    #[arg(long = "report-format", value_enum, default_value_t = ReportFormat::Text)]
+    pub report_format: ReportFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Path to an MBTiles file, PMTiles file or tile directory.
+    pub input: PathBuf,
+
+    /// Output file (defaults to stdout).
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Export a single tile in z/x/y form.
+    #[arg(long, conflicts_with_all = ["zoom", "bbox"])]
+    pub tile: Option<String>,
+
+    /// Export every tile at this zoom level.
+    #[arg(long, short = 'z')]
+    pub zoom: Option<u8>,
+
+    /// Limit --zoom to tiles intersecting minlon,minlat,maxlon,maxlat.
+    #[arg(long, requires = "zoom", allow_hyphen_values = true)]
+    pub bbox: Option<String>,
+
+    /// Export only these layers (repeatable or comma-separated).
+    #[arg(long, value_delimiter = ',')]
+    pub layer: Vec<String>,
+
+    /// Output format (geojson/geojsonseq).
+    #[arg(long, value_enum, default_value_t = ExportFormatArg::Geojson)]
+    pub format: ExportFormatArg,
+
+    /// How MBTiles `tile_row` values are numbered (tms per the spec, or xyz).
+    #[arg(long, value_enum, default_value_t = MbtilesSchemeArg::Tms)]
+    pub mbtiles_scheme: MbtilesSchemeArg,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
@@ -284,6 +428,32 @@ pub enum TileSortArg {
     Zxy,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MbtilesSchemeArg {
+    Tms,
+    Xyz,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SimplifyAlgorithmArg {
+    DouglasPeucker,
+    Visvalingam,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompressionArg {
+    None,
+    Gzip,
+    Brotli,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormatArg {
+    Geojson,
+    Geojsonseq,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum UnknownFilterMode {
     Keep,
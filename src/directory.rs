@@ -0,0 +1,225 @@
+//! `z/x/y` tile directories with a `metadata.json`, as written by
+//! tippecanoe `--output-to-directory` and served by static hosts.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value};
+
+use crate::mbtiles::{
+    InspectOptions, MbtilesReport, MbtilesScheme, MbtilesSink, TileCoord, encode_tile_payload,
+    inspect_mbtiles_with_options,
+};
+use crate::pmtiles::{decode_tile_payload_pmtiles, json_metadata_to_mbtiles};
+use crate::tiles::{TileRecord, TileSink, TileSource, copy_tiles};
+
+const METADATA_FILE: &str = "metadata.json";
+const TILE_EXTENSIONS: [&str; 6] = ["pbf", "mvt", "png", "jpg", "jpeg", "webp"];
+
+/// Reads tiles from a `z/x/y.<ext>` directory tree. Tiles may be stored gzip
+/// compressed or plain.
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.is_dir() {
+            bail!("tile directory not found: {}", path.display());
+        }
+        Ok(Self {
+            root: path.to_path_buf(),
+        })
+    }
+}
+
+impl TileSource for DirectorySource {
+    fn metadata(&self) -> Result<BTreeMap<String, String>> {
+        let path = self.root.join(METADATA_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let json: Map<String, Value> = serde_json::from_str(&text)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        Ok(json_metadata_to_mbtiles(&json))
+    }
+
+    fn for_each_tile(&mut self, visit: &mut dyn FnMut(TileRecord) -> Result<()>) -> Result<()> {
+        for (zoom, zoom_dir) in numbered_entries(&self.root, true)? {
+            let zoom = u8::try_from(zoom).context("zoom directory out of range")?;
+            for (x, x_dir) in numbered_entries(&zoom_dir, true)? {
+                for (y, tile_path) in numbered_entries(&x_dir, false)? {
+                    let coord = TileCoord { zoom, x, y };
+                    visit(read_tile_file(coord, &tile_path)?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_tile(&mut self, coord: TileCoord) -> Result<Option<TileRecord>> {
+        let x_dir = self
+            .root
+            .join(coord.zoom.to_string())
+            .join(coord.x.to_string());
+        for ext in TILE_EXTENSIONS {
+            let path = x_dir.join(format!("{}.{ext}", coord.y));
+            if path.is_file() {
+                return read_tile_file(coord, &path).map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Numeric children of `dir` sorted by number: subdirectories named `n`, or
+/// tile files named `n.<ext>`. Other entries are ignored.
+fn numbered_entries(dir: &Path, dirs: bool) -> Result<Vec<(u32, PathBuf)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let path = entry.context("read directory entry")?.path();
+        if path.is_dir() != dirs {
+            continue;
+        }
+        let name = if dirs {
+            path.file_name()
+        } else {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) if TILE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) => {
+                    path.file_stem()
+                }
+                _ => None,
+            }
+        };
+        if let Some(number) = name
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<u32>().ok())
+        {
+            entries.push((number, path));
+        }
+    }
+    entries.sort_unstable_by_key(|(number, _)| *number);
+    Ok(entries)
+}
+
+fn read_tile_file(coord: TileCoord, path: &Path) -> Result<TileRecord> {
+    let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let compression = u8::from(data.starts_with(&[0x1f, 0x8b]));
+    Ok(TileRecord {
+        coord,
+        data,
+        compression,
+    })
+}
+
+/// Writes tiles into a new `z/x/y.<ext>` directory tree and its
+/// `metadata.json`. The extension follows the `format` metadata row
+/// (`pbf` by default); brotli and zstd tiles are stored gzip-compressed.
+///
+/// Like tippecanoe, `metadata.json` keeps every value as a string in MBTiles
+/// form (`"minzoom": "0"`, `"bounds": "w,s,e,n"`, `json` nested as text), so
+/// [`DirectorySource`] and tippecanoe-aware readers parse it back unchanged.
+pub struct DirectorySink {
+    root: PathBuf,
+    extension: String,
+}
+
+impl DirectorySink {
+    pub fn create(path: &Path, metadata: BTreeMap<String, String>) -> Result<Self> {
+        if path.exists()
+            && fs::read_dir(path)
+                .with_context(|| format!("failed to read {}", path.display()))?
+                .next()
+                .is_some()
+        {
+            bail!("output directory is not empty: {}", path.display());
+        }
+        fs::create_dir_all(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let extension = match metadata.get("format").map(String::as_str) {
+            Some("png") => "png",
+            Some("jpg" | "jpeg") => "jpg",
+            Some("webp") => "webp",
+            _ => "pbf",
+        }
+        .to_string();
+        let json: Map<String, Value> = metadata
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect();
+        fs::write(
+            path.join(METADATA_FILE),
+            serde_json::to_string_pretty(&json).context("encode metadata.json")?,
+        )
+        .context("write metadata.json")?;
+        Ok(Self {
+            root: path.to_path_buf(),
+            extension,
+        })
+    }
+}
+
+impl TileSink for DirectorySink {
+    fn write_tile(&mut self, tile: &TileRecord) -> Result<()> {
+        let x_dir = self
+            .root
+            .join(tile.coord.zoom.to_string())
+            .join(tile.coord.x.to_string());
+        fs::create_dir_all(&x_dir)
+            .with_context(|| format!("failed to create {}", x_dir.display()))?;
+        let path = x_dir.join(format!("{}.{}", tile.coord.y, self.extension));
+        let data = match tile.compression {
+            0 | 1 => tile.data.clone(),
+            other => encode_tile_payload(&decode_tile_payload_pmtiles(&tile.data, other)?, true)?,
+        };
+        fs::write(&path, data).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Total size of the files under `path`.
+pub fn directory_size(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(path).with_context(|| format!("failed to read {}", path.display()))? {
+        let entry = entry.context("read directory entry")?;
+        let metadata = entry.metadata().context("read file metadata")?;
+        total += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+/// Copy a tile directory into a new MBTiles file (TMS rows) so it can go
+/// through the SQLite-based inspect pass.
+pub fn stage_directory_as_mbtiles(input: &Path, staged: &Path) -> Result<()> {
+    let mut source = DirectorySource::open(input)?;
+    let mut sink = MbtilesSink::create(staged, source.metadata()?, MbtilesScheme::Tms)?;
+    copy_tiles(&mut source, &mut sink)?;
+    Ok(())
+}
+
+/// Inspect a tile directory by staging it as MBTiles first.
+pub fn inspect_directory_with_options(
+    path: &Path,
+    options: InspectOptions,
+) -> Result<MbtilesReport> {
+    let staging = tempfile::tempdir().context("create staging directory")?;
+    let staged = staging.path().join("staged.mbtiles");
+    stage_directory_as_mbtiles(path, &staged)?;
+    inspect_mbtiles_with_options(
+        &staged,
+        InspectOptions {
+            mbtiles_scheme: MbtilesScheme::Tms,
+            ..options
+        },
+    )
+}
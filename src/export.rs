@@ -0,0 +1,339 @@
+//! Decode vector tiles to GeoJSON features in WGS84 for debugging in GIS tools.
+
+use std::f64::consts::PI;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use geo_types::{Coord, Geometry, LineString, Polygon};
+use mvt_reader::Reader;
+use serde_json::{Map, Value, json};
+use tracing::warn;
+
+use crate::format::TileFormat;
+use crate::mbtiles::{MAX_TILE_ZOOM, MbtilesScheme, TileCoord, ring_coords};
+use crate::pmtiles::{GeoBounds, decode_tile_payload_pmtiles};
+use crate::tiles::{TileRecord, open_source};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A single `FeatureCollection` document.
+    GeoJson,
+    /// One feature per line (GeoJSONSeq without record separators).
+    GeoJsonSeq,
+}
+
+/// Which tiles and layers to export. With neither `tile` nor `zoom` set,
+/// every tile is exported.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub tile: Option<TileCoord>,
+    pub zoom: Option<u8>,
+    /// Only tiles at `zoom` that intersect these bounds.
+    pub bbox: Option<GeoBounds>,
+    /// Only these layers; empty exports all layers.
+    pub layers: Vec<String>,
+}
+
+/// Property holding the source-layer name when a feature already has its own
+/// `layer` property.
+pub const SHADOWED_LAYER_KEY: &str = "_layer";
+
+impl ExportOptions {
+    /// Reject option combinations and tile addresses that cannot be exported.
+    pub fn validate(&self) -> Result<()> {
+        if self.bbox.is_some() && self.zoom.is_none() {
+            anyhow::bail!("--bbox requires --zoom");
+        }
+        if let Some(zoom) = self.zoom
+            && i64::from(zoom) > MAX_TILE_ZOOM
+        {
+            anyhow::bail!("--zoom must be at most {MAX_TILE_ZOOM}");
+        }
+        if let Some(coord) = self.tile
+            && !is_valid_tile(coord)
+        {
+            anyhow::bail!(
+                "tile {}/{}/{} is outside the z0-{MAX_TILE_ZOOM} tile grid",
+                coord.zoom,
+                coord.x,
+                coord.y
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportStats {
+    pub tiles: u64,
+    pub features: u64,
+    /// Stored tiles skipped because their address is outside the tile grid.
+    pub skipped_tiles: u64,
+    /// Features whose own `layer` property was kept; their source-layer name
+    /// is written to `SHADOWED_LAYER_KEY` instead.
+    pub shadowed_layer_properties: u64,
+}
+
+/// Write the selected tiles of `input` to `writer` as GeoJSON features with
+/// their properties and a `layer` attribute. Options are validated and the
+/// requested tile is read before anything is written.
+pub fn export_geojson(
+    input: &Path,
+    input_format: TileFormat,
+    scheme: MbtilesScheme,
+    options: &ExportOptions,
+    format: ExportFormat,
+    writer: &mut dyn Write,
+) -> Result<ExportStats> {
+    options.validate()?;
+    let mut source = open_source(input, input_format, scheme)?;
+    let selected =
+        match options.tile {
+            Some(coord) => Some(source.read_tile(coord)?.with_context(|| {
+                format!("tile {}/{}/{} not found", coord.zoom, coord.x, coord.y)
+            })?),
+            None => None,
+        };
+    let mut stats = ExportStats::default();
+    let mut out = FeatureWriter::new(writer, format)?;
+    match selected {
+        Some(tile) => export_tile(&tile, &options.layers, &mut out, &mut stats)?,
+        None => {
+            let range = match (options.zoom, options.bbox) {
+                (Some(zoom), Some(bbox)) => Some(tile_range_for_bounds(zoom, bbox)),
+                _ => None,
+            };
+            source.for_each_tile(&mut |tile| {
+                if !is_valid_tile(tile.coord) {
+                    stats.skipped_tiles += 1;
+                    return Ok(());
+                }
+                let TileCoord { zoom, x, y } = tile.coord;
+                if options.zoom.is_some_and(|selected| selected != zoom) {
+                    return Ok(());
+                }
+                if let Some((min_x, min_y, max_x, max_y)) = range
+                    && !(min_x <= x && x <= max_x && min_y <= y && y <= max_y)
+                {
+                    return Ok(());
+                }
+                export_tile(&tile, &options.layers, &mut out, &mut stats)
+            })?;
+        }
+    }
+    out.finish()?;
+    if stats.skipped_tiles > 0 {
+        warn!(
+            "skipped {} tiles outside the z0-{MAX_TILE_ZOOM} tile grid",
+            stats.skipped_tiles
+        );
+    }
+    if stats.shadowed_layer_properties > 0 {
+        warn!(
+            "{} features have their own `layer` property; their source layer is in `{SHADOWED_LAYER_KEY}`",
+            stats.shadowed_layer_properties
+        );
+    }
+    Ok(stats)
+}
+
+/// Whether `coord` addresses a tile of the z0-`MAX_TILE_ZOOM` grid.
+fn is_valid_tile(coord: TileCoord) -> bool {
+    i64::from(coord.zoom) <= MAX_TILE_ZOOM
+        && coord.x >> coord.zoom == 0
+        && coord.y >> coord.zoom == 0
+}
+
+fn export_tile(
+    tile: &TileRecord,
+    layers_filter: &[String],
+    out: &mut FeatureWriter<'_>,
+    stats: &mut ExportStats,
+) -> Result<()> {
+    let payload = decode_tile_payload_pmtiles(&tile.data, tile.compression)?;
+    let reader =
+        Reader::new(payload).map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+    let layers = reader
+        .get_layer_metadata()
+        .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
+    stats.tiles += 1;
+    for layer in layers {
+        if !layers_filter.is_empty() && !layers_filter.contains(&layer.name) {
+            continue;
+        }
+        let features = reader
+            .get_features(layer.layer_index)
+            .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
+        let project = |coord: &Coord<f32>| tile_to_lon_lat(tile.coord, layer.extent, coord);
+        for feature in features {
+            let mut properties = Map::new();
+            for (key, value) in feature.properties.iter().flatten() {
+                properties.insert(key.clone(), property_to_json(value));
+            }
+            let layer_name = Value::String(layer.name.clone());
+            if properties.contains_key("layer") {
+                properties.insert(SHADOWED_LAYER_KEY.to_string(), layer_name);
+                stats.shadowed_layer_properties += 1;
+            } else {
+                properties.insert("layer".to_string(), layer_name);
+            }
+            let mut object = Map::new();
+            object.insert("type".to_string(), json!("Feature"));
+            if let Some(id) = feature.id {
+                object.insert("id".to_string(), json!(id));
+            }
+            object.insert(
+                "geometry".to_string(),
+                geometry_to_json(feature.get_geometry(), &project),
+            );
+            object.insert("properties".to_string(), Value::Object(properties));
+            out.write_feature(&Value::Object(object))?;
+            stats.features += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Streams features as a `FeatureCollection` or one per line.
+struct FeatureWriter<'a> {
+    writer: &'a mut dyn Write,
+    format: ExportFormat,
+    first: bool,
+}
+
+impl<'a> FeatureWriter<'a> {
+    fn new(writer: &'a mut dyn Write, format: ExportFormat) -> Result<Self> {
+        if format == ExportFormat::GeoJson {
+            writer
+                .write_all(br#"{"type":"FeatureCollection","features":["#)
+                .context("write geojson")?;
+        }
+        Ok(Self {
+            writer,
+            format,
+            first: true,
+        })
+    }
+
+    fn write_feature(&mut self, feature: &Value) -> Result<()> {
+        match self.format {
+            ExportFormat::GeoJson if !self.first => {
+                self.writer.write_all(b",\n").context("write geojson")?
+            }
+            ExportFormat::GeoJson => self.writer.write_all(b"\n").context("write geojson")?,
+            ExportFormat::GeoJsonSeq => {}
+        }
+        serde_json::to_writer(&mut self.writer, feature).context("write geojson")?;
+        if self.format == ExportFormat::GeoJsonSeq {
+            self.writer.write_all(b"\n").context("write geojson")?;
+        }
+        self.first = false;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.format == ExportFormat::GeoJson {
+            self.writer.write_all(b"\n]}\n").context("write geojson")?;
+        }
+        self.writer.flush().context("flush geojson")
+    }
+}
+
+/// Convert a tile-local coordinate to `[longitude, latitude]` in degrees.
+pub fn tile_to_lon_lat(tile: TileCoord, extent: u32, coord: &Coord<f32>) -> [f64; 2] {
+    let scale = 2f64.powi(i32::from(tile.zoom));
+    let extent = f64::from(extent);
+    let world_x = (f64::from(tile.x) + f64::from(coord.x) / extent) / scale;
+    let world_y = (f64::from(tile.y) + f64::from(coord.y) / extent) / scale;
+    let longitude = world_x * 360.0 - 180.0;
+    let latitude = (PI * (1.0 - 2.0 * world_y)).sinh().atan().to_degrees();
+    [longitude, latitude]
+}
+
+/// Inclusive XYZ tile range `(min_x, min_y, max_x, max_y)` covering `bounds`
+/// at `zoom`.
+fn tile_range_for_bounds(zoom: u8, bounds: GeoBounds) -> (u32, u32, u32, u32) {
+    let max_index = (1u32 << zoom) - 1;
+    let scale = f64::from(1u32 << zoom);
+    let tile_x = |longitude: f64| {
+        (((longitude + 180.0) / 360.0 * scale).floor().max(0.0) as u32).min(max_index)
+    };
+    let tile_y = |latitude: f64| {
+        let radians = latitude.clamp(-85.051_128_78, 85.051_128_78).to_radians();
+        let world_y = (1.0 - radians.tan().asinh() / PI) / 2.0;
+        ((world_y * scale).floor().max(0.0) as u32).min(max_index)
+    };
+    (
+        tile_x(bounds.min_longitude),
+        tile_y(bounds.max_latitude),
+        tile_x(bounds.max_longitude),
+        tile_y(bounds.min_latitude),
+    )
+}
+
+fn geometry_to_json(geometry: &Geometry<f32>, project: &dyn Fn(&Coord<f32>) -> [f64; 2]) -> Value {
+    let line = |line: &LineString<f32>| -> Vec<[f64; 2]> { line.0.iter().map(project).collect() };
+    let ring = |ring: &LineString<f32>| -> Vec<[f64; 2]> {
+        let mut coords: Vec<[f64; 2]> = ring_coords(ring).iter().map(project).collect();
+        if let Some(first) = coords.first().copied() {
+            coords.push(first);
+        }
+        coords
+    };
+    let polygon = |polygon: &Polygon<f32>| -> Vec<Vec<[f64; 2]>> {
+        std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .map(ring)
+            .collect()
+    };
+    match geometry {
+        Geometry::Point(point) => json!({"type": "Point", "coordinates": project(&point.0)}),
+        Geometry::MultiPoint(points) => json!({
+            "type": "MultiPoint",
+            "coordinates": points.iter().map(|point| project(&point.0)).collect::<Vec<_>>(),
+        }),
+        Geometry::Line(segment) => json!({
+            "type": "LineString",
+            "coordinates": [project(&segment.start), project(&segment.end)],
+        }),
+        Geometry::LineString(linestring) => {
+            json!({"type": "LineString", "coordinates": line(linestring)})
+        }
+        Geometry::MultiLineString(lines) => json!({
+            "type": "MultiLineString",
+            "coordinates": lines.iter().map(line).collect::<Vec<_>>(),
+        }),
+        Geometry::Polygon(shape) => json!({"type": "Polygon", "coordinates": polygon(shape)}),
+        Geometry::MultiPolygon(shapes) => json!({
+            "type": "MultiPolygon",
+            "coordinates": shapes.iter().map(polygon).collect::<Vec<_>>(),
+        }),
+        Geometry::Rect(rect) => {
+            json!({"type": "Polygon", "coordinates": polygon(&rect.to_polygon())})
+        }
+        Geometry::Triangle(triangle) => {
+            json!({"type": "Polygon", "coordinates": polygon(&triangle.to_polygon())})
+        }
+        Geometry::GeometryCollection(collection) => json!({
+            "type": "GeometryCollection",
+            "geometries": collection
+                .iter()
+                .map(|geometry| geometry_to_json(geometry, project))
+                .collect::<Vec<_>>(),
+        }),
+    }
+}
+
+fn property_to_json(value: &mvt_reader::feature::Value) -> Value {
+    match value {
+        mvt_reader::feature::Value::String(text) => Value::String(text.clone()),
+        mvt_reader::feature::Value::Float(val) => json!(val),
+        mvt_reader::feature::Value::Double(val) => json!(val),
+        mvt_reader::feature::Value::Int(val) => json!(val),
+        mvt_reader::feature::Value::UInt(val) => json!(val),
+        mvt_reader::feature::Value::SInt(val) => json!(val),
+        mvt_reader::feature::Value::Bool(val) => json!(val),
+        mvt_reader::feature::Value::Null => Value::Null,
+    }
+}
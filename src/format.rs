@@ -6,6 +6,8 @@ use anyhow::{Result, bail};
 pub enum TileFormat {
     Mbtiles,
     Pmtiles,
+    /// A `z/x/y` tile directory with a `metadata.json`.
+    Directory,
 }
 
 impl TileFormat {
@@ -18,11 +20,22 @@ impl TileFormat {
         }
     }
 
+    /// Like [`TileFormat::from_extension`], but a trailing slash or an
+    /// existing directory selects [`TileFormat::Directory`].
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let text = path.as_os_str().to_string_lossy();
+        if text.ends_with('/') || text.ends_with(std::path::MAIN_SEPARATOR) || path.is_dir() {
+            return Some(TileFormat::Directory);
+        }
+        Self::from_extension(path)
+    }
+
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(name: &str) -> Option<Self> {
         match name.to_ascii_lowercase().as_str() {
             "mbtiles" => Some(TileFormat::Mbtiles),
             "pmtiles" => Some(TileFormat::Pmtiles),
+            "directory" | "dir" => Some(TileFormat::Directory),
             _ => None,
         }
     }
@@ -31,6 +44,7 @@ impl TileFormat {
         match self {
             TileFormat::Mbtiles => "mbtiles",
             TileFormat::Pmtiles => "pmtiles",
+            TileFormat::Directory => "directory",
         }
     }
 }
@@ -50,7 +64,7 @@ pub fn decide_formats(
     let input = if let Some(name) = input_format {
         TileFormat::from_str(name).ok_or_else(|| anyhow::anyhow!("unknown input format: {name}"))?
     } else {
-        TileFormat::from_extension(input_path)
+        TileFormat::from_path(input_path)
             .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?
     };
 
@@ -58,7 +72,7 @@ pub fn decide_formats(
         TileFormat::from_str(name)
             .ok_or_else(|| anyhow::anyhow!("unknown output format: {name}"))?
     } else if let Some(path) = output_path {
-        TileFormat::from_extension(path).unwrap_or(input)
+        TileFormat::from_path(path).unwrap_or(input)
     } else {
         input
     };
@@ -67,9 +81,9 @@ pub fn decide_formats(
 }
 
 pub fn require_known_output_extension(path: &Path) -> Result<TileFormat> {
-    TileFormat::from_extension(path).ok_or_else(|| {
+    TileFormat::from_path(path).ok_or_else(|| {
         anyhow::anyhow!(
-            "output path must have .mbtiles or .pmtiles extension when output format is not provided"
+            "output path must have .mbtiles or .pmtiles extension (or end with / for a directory) when output format is not provided"
         )
     })
 }
@@ -86,7 +100,7 @@ pub fn validate_output_format_matches_path(
     let declared = TileFormat::from_str(fmt_name)
         .ok_or_else(|| anyhow::anyhow!("unknown output format: {fmt_name}"))?;
 
-    if let Some(path_fmt) = TileFormat::from_extension(path)
+    if let Some(path_fmt) = TileFormat::from_path(path)
         && path_fmt != declared
     {
         bail!("output format ({fmt_name}) conflicts with output file extension",);
@@ -123,7 +137,10 @@ pub fn default_output_path_pruned(input_path: &Path, output_format: TileFormat)
         .or(file_name)
         .unwrap_or("output");
 
-    let file_name = format!("{stem}.pruned.{}", output_format.extension_str());
+    let file_name = match output_format {
+        TileFormat::Directory => format!("{stem}.pruned"),
+        _ => format!("{stem}.pruned.{}", output_format.extension_str()),
+    };
     let mut out = input_path.parent().map(PathBuf::from).unwrap_or_default();
     out.push(file_name);
     out
@@ -1,6 +1,11 @@
+pub mod checkpoint;
 pub mod cli;
+pub mod directory;
+pub mod export;
 pub mod format;
 pub mod mbtiles;
 pub mod output;
 pub mod pmtiles;
+pub mod rules;
 pub mod style;
+pub mod tiles;
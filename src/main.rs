@@ -5,23 +5,35 @@ use clap::Parser;
 
 use nu_ansi_term::{Color, Style};
 use serde::Serialize;
-use vt_optimizer::cli::{Cli, Command, ReportFormat, TileSortArg};
+use vt_optimizer::checkpoint::{CheckpointConfig, hash_file};
+use vt_optimizer::cli::{
+    Cli, Command, CompressionArg, ExportFormatArg, MbtilesSchemeArg, ReportFormat,
+    SimplifyAlgorithmArg, TileSortArg,
+};
+use vt_optimizer::directory::{directory_size, inspect_directory_with_options};
+use vt_optimizer::export::{ExportFormat, ExportOptions, export_geojson};
 use vt_optimizer::format::{plan_copy, plan_optimize, resolve_output_path};
 use vt_optimizer::mbtiles::{
-    InspectOptions, PruneOptions, PruneStats, TileListOptions, TileSort, copy_mbtiles,
-    inspect_mbtiles_with_options, parse_sample_spec, parse_tile_spec, prune_mbtiles_layer_only,
-    simplify_mbtiles_tile,
+    InspectOptions, MbtilesScheme, PruneOptions, PruneStats, RemovedNames, SimplifyAlgorithm,
+    SimplifyMethod, SimplifyOptions, TileListOptions, TileSort, ToleranceRule, copy_mbtiles,
+    inspect_mbtiles_with_options, parse_sample_spec, parse_tile_spec, parse_tolerance_rule,
+    prune_mbtiles_to_pmtiles, prune_mbtiles_with_checkpoint, simplify_mbtiles,
+    simplify_mbtiles_tile_with_scheme, verify_mbtiles_with_scheme,
 };
 use vt_optimizer::output::{
     format_bytes, format_histogram_table, format_histograms_by_zoom_section,
-    format_metadata_section, format_top_tiles_lines, format_zoom_table, ndjson_lines, pad_left,
-    pad_right, resolve_output_format,
+    format_metadata_section, format_top_tiles_lines, format_verify_issue_lines, format_zoom_table,
+    ndjson_lines, pad_left, pad_right, resolve_output_format, verify_ndjson_lines,
 };
 use vt_optimizer::pmtiles::{
-    inspect_pmtiles_with_options, mbtiles_to_pmtiles, pmtiles_to_mbtiles, prune_pmtiles_layer_only,
-    simplify_pmtiles_tile,
+    PmtilesCopyOptions, inspect_pmtiles_with_options, mbtiles_to_pmtiles_with_scheme,
+    parse_mbtiles_bounds, pmtiles_to_mbtiles_with_scheme, pmtiles_to_pmtiles,
+    prune_pmtiles_to_mbtiles, prune_pmtiles_with_checkpoint, simplify_pmtiles,
+    simplify_pmtiles_tile, verify_pmtiles,
 };
-use vt_optimizer::style::read_style;
+use vt_optimizer::rules::read_rules;
+use vt_optimizer::style::{MapboxStyle, match_style_source, read_style_with_source};
+use vt_optimizer::tiles::{copy_tiles, create_sink, open_source, prune_tiles, simplify_tiles};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -35,54 +47,7 @@ fn main() -> Result<()> {
             run_optimize(args)?;
         }
         Some(Command::Simplify(args)) => {
-            let input_format = vt_optimizer::format::TileFormat::from_extension(&args.input)
-                .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
-            let coord = vt_optimizer::mbtiles::TileCoord {
-                zoom: args.z,
-                x: args.x,
-                y: args.y,
-            };
-            let (output, stats) = match input_format {
-                vt_optimizer::format::TileFormat::Mbtiles => {
-                    let output = args
-                        .output
-                        .clone()
-                        .unwrap_or_else(|| args.input.with_extension("simplified.mbtiles"));
-                    let stats = simplify_mbtiles_tile(
-                        &args.input,
-                        &output,
-                        coord,
-                        &args.layer,
-                        args.tolerance,
-                    )?;
-                    (output, stats)
-                }
-                vt_optimizer::format::TileFormat::Pmtiles => {
-                    let output = args
-                        .output
-                        .clone()
-                        .unwrap_or_else(|| args.input.with_extension("simplified.pmtiles"));
-                    let stats = simplify_pmtiles_tile(
-                        &args.input,
-                        &output,
-                        coord,
-                        &args.layer,
-                        args.tolerance,
-                    )?;
-                    (output, stats)
-                }
-            };
-            println!(
-                "simplify: input={} output={} z={} x={} y={} features={} vertices={}=>{}",
-                args.input.display(),
-                output.display(),
-                args.z,
-                args.x,
-                args.y,
-                stats.feature_count,
-                stats.vertices_before,
-                stats.vertices_after
-            );
+            run_simplify(args)?;
         }
         Some(Command::Copy(args)) => {
             let decision = plan_copy(
@@ -93,6 +58,18 @@ fn main() -> Result<()> {
             )?;
             let _output_path =
                 resolve_output_path(&args.input, args.output.as_deref(), decision.output);
+            let recompress = args.internal_compression.is_some() || args.tile_compression.is_some();
+            if recompress
+                && (decision.input, decision.output)
+                    != (
+                        vt_optimizer::format::TileFormat::Pmtiles,
+                        vt_optimizer::format::TileFormat::Pmtiles,
+                    )
+            {
+                anyhow::bail!(
+                    "--internal-compression/--tile-compression require PMTiles input and output"
+                );
+            }
             match (decision.input, decision.output) {
                 (
                     vt_optimizer::format::TileFormat::Mbtiles,
@@ -104,25 +81,52 @@ fn main() -> Result<()> {
                     vt_optimizer::format::TileFormat::Mbtiles,
                     vt_optimizer::format::TileFormat::Pmtiles,
                 ) => {
-                    mbtiles_to_pmtiles(&args.input, &_output_path)?;
+                    mbtiles_to_pmtiles_with_scheme(
+                        &args.input,
+                        &_output_path,
+                        mbtiles_scheme(args.mbtiles_scheme),
+                    )?;
                 }
                 (
                     vt_optimizer::format::TileFormat::Pmtiles,
                     vt_optimizer::format::TileFormat::Mbtiles,
                 ) => {
-                    pmtiles_to_mbtiles(&args.input, &_output_path)?;
+                    pmtiles_to_mbtiles_with_scheme(
+                        &args.input,
+                        &_output_path,
+                        mbtiles_scheme(args.mbtiles_scheme),
+                    )?;
                 }
                 (
                     vt_optimizer::format::TileFormat::Pmtiles,
                     vt_optimizer::format::TileFormat::Pmtiles,
                 ) => {
-                    anyhow::bail!("v0.0.3 does not support PMTiles to PMTiles copy");
+                    pmtiles_to_pmtiles(
+                        &args.input,
+                        &_output_path,
+                        PmtilesCopyOptions {
+                            internal_compression: args.internal_compression.map(compression_code),
+                            tile_compression: args.tile_compression.map(compression_code),
+                        },
+                    )?;
+                }
+                (input_format, output_format) => {
+                    let scheme = mbtiles_scheme(args.mbtiles_scheme);
+                    let mut source = open_source(&args.input, input_format, scheme)?;
+                    let mut sink =
+                        create_sink(&_output_path, output_format, source.metadata()?, scheme)?;
+                    copy_tiles(source.as_mut(), sink.as_mut())?;
                 }
             }
             println!("copy: input={}", args.input.display());
         }
         Some(Command::Verify(args)) => {
-            println!("verify: input={}", args.input.display());
+            if !run_verify(args)? {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Export(args)) => {
+            run_export(args)?;
         }
         None => {
             let Some(input) = cli.mbtiles.as_ref() else {
@@ -135,7 +139,8 @@ fn main() -> Result<()> {
                     input_format: None,
                     output_format: None,
                     report_format: ReportFormat::Text,
-                    style: cli.style.clone(),
+                    style: cli.style.clone().into_iter().collect(),
+                    source: None,
                     style_mode: vt_optimizer::cli::StyleMode::VtCompat,
                     unknown_filter: vt_optimizer::cli::UnknownFilterMode::Keep,
                     max_tile_bytes: 1_280_000,
@@ -145,8 +150,12 @@ fn main() -> Result<()> {
                     read_cache_mb: None,
                     write_cache_mb: None,
                     drop_empty_tiles: false,
-                    checkpoint: None,
-                    resume: false,
+                    prune_attributes: false,
+                    keep_languages: Vec::new(),
+                    rules: None,
+                    checkpoint: cli.checkpoint.clone(),
+                    resume: cli.resume,
+                    mbtiles_scheme: MbtilesSchemeArg::Tms,
                 };
                 run_optimize(args)?;
                 return Ok(());
@@ -156,61 +165,25 @@ fn main() -> Result<()> {
                     let args = vt_optimizer::cli::SimplifyArgs {
                         input: input.clone(),
                         output: cli.output.clone(),
-                        z,
-                        x,
-                        y,
+                        z: Some(z),
+                        x: Some(x),
+                        y: Some(y),
                         layer: cli.layer.clone(),
+                        simplify_layers: Vec::new(),
                         tolerance: cli.tolerance,
+                        tolerance_rules: Vec::new(),
+                        minzoom: None,
+                        maxzoom: None,
+                        threads: None,
+                        readers: None,
+                        io_batch: 1_000,
+                        read_cache_mb: None,
+                        write_cache_mb: None,
+                        algorithm: SimplifyAlgorithmArg::DouglasPeucker,
+                        preserve_topology: false,
+                        mbtiles_scheme: MbtilesSchemeArg::Tms,
                     };
-                    let input_format = vt_optimizer::format::TileFormat::from_extension(
-                        &args.input,
-                    )
-                    .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
-                    let coord = vt_optimizer::mbtiles::TileCoord {
-                        zoom: args.z,
-                        x: args.x,
-                        y: args.y,
-                    };
-                    let (output, stats) =
-                        match input_format {
-                            vt_optimizer::format::TileFormat::Mbtiles => {
-                                let output = args.output.clone().unwrap_or_else(|| {
-                                    args.input.with_extension("simplified.mbtiles")
-                                });
-                                let stats = simplify_mbtiles_tile(
-                                    &args.input,
-                                    &output,
-                                    coord,
-                                    &args.layer,
-                                    args.tolerance,
-                                )?;
-                                (output, stats)
-                            }
-                            vt_optimizer::format::TileFormat::Pmtiles => {
-                                let output = args.output.clone().unwrap_or_else(|| {
-                                    args.input.with_extension("simplified.pmtiles")
-                                });
-                                let stats = simplify_pmtiles_tile(
-                                    &args.input,
-                                    &output,
-                                    coord,
-                                    &args.layer,
-                                    args.tolerance,
-                                )?;
-                                (output, stats)
-                            }
-                        };
-                    println!(
-                        "simplify: input={} output={} z={} x={} y={} features={} vertices={}=>{}",
-                        args.input.display(),
-                        output.display(),
-                        args.z,
-                        args.x,
-                        args.y,
-                        stats.feature_count,
-                        stats.vertices_before,
-                        stats.vertices_after
-                    );
+                    run_simplify(args)?;
                     return Ok(());
                 }
                 let args = vt_optimizer::cli::InspectArgs {
@@ -239,6 +212,7 @@ fn main() -> Result<()> {
                     ndjson_compact: false,
                     include_layer_list: false,
                     tile_info_format: vt_optimizer::cli::TileInfoFormat::Full,
+                    mbtiles_scheme: MbtilesSchemeArg::Tms,
                 };
                 run_inspect(args)?;
                 return Ok(());
@@ -269,6 +243,7 @@ fn main() -> Result<()> {
                 ndjson_compact: false,
                 include_layer_list: false,
                 tile_info_format: vt_optimizer::cli::TileInfoFormat::Full,
+                mbtiles_scheme: MbtilesSchemeArg::Tms,
             };
             run_inspect(args)?;
         }
@@ -277,6 +252,34 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn mbtiles_scheme(arg: MbtilesSchemeArg) -> MbtilesScheme {
+    match arg {
+        MbtilesSchemeArg::Tms => MbtilesScheme::Tms,
+        MbtilesSchemeArg::Xyz => MbtilesScheme::Xyz,
+    }
+}
+
+fn simplify_method(args: &vt_optimizer::cli::SimplifyArgs) -> SimplifyMethod {
+    let algorithm = match args.algorithm {
+        SimplifyAlgorithmArg::DouglasPeucker => SimplifyAlgorithm::DouglasPeucker,
+        SimplifyAlgorithmArg::Visvalingam => SimplifyAlgorithm::Visvalingam,
+    };
+    SimplifyMethod {
+        algorithm,
+        preserve_topology: args.preserve_topology,
+    }
+}
+
+/// PMTiles compression as numbered by this crate; headers store the spec codes.
+fn compression_code(arg: CompressionArg) -> u8 {
+    match arg {
+        CompressionArg::None => 0,
+        CompressionArg::Gzip => 1,
+        CompressionArg::Brotli => 2,
+        CompressionArg::Zstd => 3,
+    }
+}
+
 fn init_tracing(level: &str) {
     let filter = tracing_subscriber::EnvFilter::try_new(level)
         .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
@@ -364,8 +367,9 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
         } else {
             None
         },
+        mbtiles_scheme: mbtiles_scheme(args.mbtiles_scheme),
     };
-    let input_format = vt_optimizer::format::TileFormat::from_extension(&args.input)
+    let input_format = vt_optimizer::format::TileFormat::from_path(&args.input)
         .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
     let report = match input_format {
         vt_optimizer::format::TileFormat::Mbtiles => {
@@ -374,6 +378,9 @@ fn run_inspect(args: vt_optimizer::cli::InspectArgs) -> Result<()> {
         vt_optimizer::format::TileFormat::Pmtiles => {
             inspect_pmtiles_with_options(&args.input, &options)?
         }
+        vt_optimizer::format::TileFormat::Directory => {
+            inspect_directory_with_options(&args.input, options)?
+        }
     };
     let report = vt_optimizer::output::apply_tile_info_format(report, args.tile_info_format);
     let summary_totals = if args.include_layer_list
@@ -723,10 +730,9 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
         args.output_format.as_deref(),
     )?;
     let output_path = resolve_output_path(&args.input, args.output.as_deref(), decision.output);
-    let style_path = args
-        .style
-        .as_ref()
-        .context("--style is required for optimize")?;
+    if args.style.is_empty() {
+        anyhow::bail!("--style is required for optimize");
+    }
     if args.style_mode != vt_optimizer::cli::StyleMode::Layer
         && args.style_mode != vt_optimizer::cli::StyleMode::LayerFilter
         && args.style_mode != vt_optimizer::cli::StyleMode::VtCompat
@@ -738,37 +744,116 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
         println!("Prune steps");
         println!("- Parsing style file");
     }
-    let style = read_style(style_path)?;
+    let metadata = match args.source {
+        Some(_) => None,
+        None => Some(
+            open_source(
+                &args.input,
+                decision.input,
+                mbtiles_scheme(args.mbtiles_scheme),
+            )?
+            .metadata()?,
+        ),
+    };
+    let mut styles = Vec::with_capacity(args.style.len());
+    let mut style_hashes = Vec::with_capacity(args.style.len());
+    for style_path in &args.style {
+        let source = match (&args.source, &metadata) {
+            (Some(source), _) => Some(source.clone()),
+            (None, Some(metadata)) => match_style_source(style_path, &args.input, metadata)?,
+            (None, None) => None,
+        };
+        if emit_logs && let Some(source) = source.as_deref() {
+            println!("- Using style source {source} of {}", style_path.display());
+        }
+        styles.push(read_style_with_source(style_path, source.as_deref())?);
+        // The selected source changes the output as much as the style does.
+        style_hashes.push(match source.as_deref() {
+            Some(source) => format!("{}#{source}", hash_file(style_path)?),
+            None => hash_file(style_path)?,
+        });
+    }
+    let style = MapboxStyle::union(styles);
+    let rules = match args.rules.as_deref() {
+        Some(path) => {
+            if emit_logs {
+                println!("- Parsing rules file");
+            }
+            Some(read_rules(path)?)
+        }
+        None => None,
+    };
+    let checkpoint = match args.checkpoint.as_ref() {
+        Some(path) => Some(CheckpointConfig {
+            path: path.clone(),
+            resume: args.resume,
+            style_hash: style_hashes.join("+"),
+        }),
+        None if args.resume => anyhow::bail!("--resume requires --checkpoint"),
+        None => None,
+    };
+    let apply_filters = args.style_mode == vt_optimizer::cli::StyleMode::LayerFilter;
+    let threads = args.threads.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let readers = args.readers.unwrap_or(threads);
+    let prune_options = PruneOptions {
+        threads,
+        io_batch: args.io_batch,
+        readers,
+        read_cache_mb: args.read_cache_mb,
+        write_cache_mb: args.write_cache_mb,
+        drop_empty_tiles: args.drop_empty_tiles,
+        keep_unknown_filters: args.unknown_filter == vt_optimizer::cli::UnknownFilterMode::Keep,
+        prune_attributes: args.prune_attributes,
+        keep_languages: (!args.keep_languages.is_empty()).then(|| args.keep_languages.clone()),
+        rules,
+    };
+    let scheme = mbtiles_scheme(args.mbtiles_scheme);
+    let streamed = decision.input == vt_optimizer::format::TileFormat::Directory
+        || decision.output == vt_optimizer::format::TileFormat::Directory;
+    if streamed && checkpoint.is_some() {
+        anyhow::bail!("--checkpoint is not supported for directory input or output");
+    }
     let stats = match (decision.input, decision.output) {
+        // Directories stream through the format-neutral tile source and sink.
+        _ if streamed => {
+            if emit_logs {
+                println!(
+                    "- Processing tiles (threads={threads}, io_batch={})",
+                    args.io_batch,
+                );
+            }
+            let mut source = open_source(&args.input, decision.input, scheme)?;
+            let mut sink = create_sink(&output_path, decision.output, source.metadata()?, scheme)?;
+            let stats = prune_tiles(
+                source.as_mut(),
+                sink.as_mut(),
+                &style,
+                apply_filters,
+                prune_options,
+            )?;
+            if emit_logs {
+                println!("- Writing output file to {}", output_path.display());
+            }
+            stats
+        }
         (vt_optimizer::format::TileFormat::Mbtiles, vt_optimizer::format::TileFormat::Mbtiles) => {
-            let apply_filters = args.style_mode == vt_optimizer::cli::StyleMode::LayerFilter;
-            let threads = args.threads.unwrap_or_else(|| {
-                thread::available_parallelism()
-                    .map(|n| n.get())
-                    .unwrap_or(1)
-            });
-            let readers = args.readers.unwrap_or(threads);
             if emit_logs {
                 println!(
                     "- Processing tiles (threads={threads}, readers={readers}, io_batch={})",
                     args.io_batch,
                 );
             }
-            let stats = prune_mbtiles_layer_only(
+            let stats = prune_mbtiles_with_checkpoint(
                 &args.input,
                 &output_path,
                 &style,
                 apply_filters,
-                PruneOptions {
-                    threads,
-                    io_batch: args.io_batch,
-                    readers,
-                    read_cache_mb: args.read_cache_mb,
-                    write_cache_mb: args.write_cache_mb,
-                    drop_empty_tiles: args.drop_empty_tiles,
-                    keep_unknown_filters: args.unknown_filter
-                        == vt_optimizer::cli::UnknownFilterMode::Keep,
-                },
+                prune_options,
+                checkpoint.as_ref(),
             )?;
             if emit_logs {
                 println!("- Writing output file to {}", output_path.display());
@@ -776,25 +861,76 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
             stats
         }
         (vt_optimizer::format::TileFormat::Pmtiles, vt_optimizer::format::TileFormat::Pmtiles) => {
-            let apply_filters = args.style_mode == vt_optimizer::cli::StyleMode::LayerFilter;
             if emit_logs {
-                println!("- Processing tiles");
+                println!(
+                    "- Processing tiles (threads={threads}, readers={readers}, io_batch={})",
+                    args.io_batch,
+                );
+            }
+            let stats = prune_pmtiles_with_checkpoint(
+                &args.input,
+                &output_path,
+                &style,
+                apply_filters,
+                prune_options,
+                checkpoint.as_ref(),
+            )?;
+            if emit_logs {
+                println!("- Writing output file to {}", output_path.display());
+            }
+            stats
+        }
+        (vt_optimizer::format::TileFormat::Mbtiles, vt_optimizer::format::TileFormat::Pmtiles) => {
+            if checkpoint.is_some() {
+                anyhow::bail!(
+                    "--checkpoint is not supported when optimize converts between formats"
+                );
+            }
+            if emit_logs {
+                println!(
+                    "- Processing tiles (threads={threads}, readers={readers}, io_batch={})",
+                    args.io_batch,
+                );
             }
-            let stats = prune_pmtiles_layer_only(
+            let stats = prune_mbtiles_to_pmtiles(
                 &args.input,
                 &output_path,
                 &style,
                 apply_filters,
-                args.unknown_filter == vt_optimizer::cli::UnknownFilterMode::Keep,
+                prune_options,
+                scheme,
             )?;
             if emit_logs {
                 println!("- Writing output file to {}", output_path.display());
             }
             stats
         }
-        _ => {
-            anyhow::bail!("v0.0.47 only supports matching input/output formats for optimize");
+        (vt_optimizer::format::TileFormat::Pmtiles, vt_optimizer::format::TileFormat::Mbtiles) => {
+            if checkpoint.is_some() {
+                anyhow::bail!(
+                    "--checkpoint is not supported when optimize converts between formats"
+                );
+            }
+            if emit_logs {
+                println!(
+                    "- Processing tiles (threads={threads}, readers={readers}, io_batch={})",
+                    args.io_batch,
+                );
+            }
+            let stats = prune_pmtiles_to_mbtiles(
+                &args.input,
+                &output_path,
+                &style,
+                apply_filters,
+                prune_options,
+                scheme,
+            )?;
+            if emit_logs {
+                println!("- Writing output file to {}", output_path.display());
+            }
+            stats
         }
+        _ => unreachable!("directories are streamed"),
     };
     let output_stats = collect_optimize_io_stats(&output_path, decision.output)?;
     let optimization = build_optimization_summary(&input_stats, &output_stats, &stats);
@@ -826,6 +962,273 @@ fn run_optimize(args: vt_optimizer::cli::OptimizeArgs) -> Result<()> {
     Ok(())
 }
 
+fn run_simplify(args: vt_optimizer::cli::SimplifyArgs) -> Result<()> {
+    let input_format = vt_optimizer::format::TileFormat::from_path(&args.input)
+        .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
+    let output = args.output.clone().unwrap_or_else(|| match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => {
+            args.input.with_extension("simplified.mbtiles")
+        }
+        vt_optimizer::format::TileFormat::Pmtiles => {
+            args.input.with_extension("simplified.pmtiles")
+        }
+        vt_optimizer::format::TileFormat::Directory => {
+            let mut name = args.input.as_os_str().to_owned();
+            name.push(".simplified");
+            name.into()
+        }
+    });
+    let (z, x, y) = match (args.z, args.x, args.y) {
+        (Some(z), Some(x), Some(y)) => (z, x, y),
+        (None, None, None) => return run_simplify_tileset(&args, input_format, &output),
+        _ => anyhow::bail!("--z, --x and --y must be given together"),
+    };
+    if input_format == vt_optimizer::format::TileFormat::Directory {
+        anyhow::bail!("simplify with --z/--x/--y does not support directory input");
+    }
+    let coord = vt_optimizer::mbtiles::TileCoord { zoom: z, x, y };
+    let stats = match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => simplify_mbtiles_tile_with_scheme(
+            &args.input,
+            &output,
+            coord,
+            mbtiles_scheme(args.mbtiles_scheme),
+            &args.layer,
+            args.tolerance,
+            simplify_method(&args),
+        )?,
+        _ => simplify_pmtiles_tile(
+            &args.input,
+            &output,
+            coord,
+            &args.layer,
+            args.tolerance,
+            simplify_method(&args),
+        )?,
+    };
+    println!(
+        "simplify: input={} output={} z={} x={} y={} features={} vertices={}=>{}",
+        args.input.display(),
+        output.display(),
+        z,
+        x,
+        y,
+        stats.feature_count,
+        stats.vertices_before,
+        stats.vertices_after
+    );
+    Ok(())
+}
+
+fn run_simplify_tileset(
+    args: &vt_optimizer::cli::SimplifyArgs,
+    input_format: vt_optimizer::format::TileFormat,
+    output: &std::path::Path,
+) -> Result<()> {
+    if vt_optimizer::format::TileFormat::from_path(output).unwrap_or(input_format) != input_format {
+        anyhow::bail!("simplify writes the same format as its input");
+    }
+    if let (Some(minzoom), Some(maxzoom)) = (args.minzoom, args.maxzoom)
+        && minzoom > maxzoom
+    {
+        anyhow::bail!("--minzoom {minzoom} is greater than --maxzoom {maxzoom}");
+    }
+    // A plain --tolerance applies to every zoom of the --simplify-layer layers
+    // (or all).
+    let mut tolerances = Vec::new();
+    if let Some(tolerance) = args.tolerance {
+        let layers: Vec<Option<String>> = if args.simplify_layers.is_empty() {
+            vec![None]
+        } else {
+            args.simplify_layers.iter().cloned().map(Some).collect()
+        };
+        for layer in layers {
+            tolerances.push(ToleranceRule {
+                layer,
+                minzoom: 0,
+                maxzoom: u8::MAX,
+                tolerance,
+            });
+        }
+    }
+    for rule in &args.tolerance_rules {
+        tolerances.push(parse_tolerance_rule(rule)?);
+    }
+    if tolerances.is_empty() {
+        anyhow::bail!("simplify needs --tolerance or --tolerance-rule for a whole tileset");
+    }
+    let threads = args.threads.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let options = SimplifyOptions {
+        threads,
+        io_batch: args.io_batch,
+        readers: args.readers.unwrap_or(threads),
+        read_cache_mb: args.read_cache_mb,
+        write_cache_mb: args.write_cache_mb,
+        minzoom: args.minzoom,
+        maxzoom: args.maxzoom,
+        tolerances,
+        method: simplify_method(args),
+    };
+    let stats = match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => {
+            simplify_mbtiles(&args.input, output, options)?
+        }
+        vt_optimizer::format::TileFormat::Pmtiles => {
+            simplify_pmtiles(&args.input, output, options)?
+        }
+        vt_optimizer::format::TileFormat::Directory => {
+            let scheme = mbtiles_scheme(args.mbtiles_scheme);
+            let mut source = open_source(&args.input, input_format, scheme)?;
+            let mut sink = create_sink(output, input_format, source.metadata()?, scheme)?;
+            simplify_tiles(source.as_mut(), sink.as_mut(), options)?
+        }
+    };
+    let total = stats.total();
+    println!(
+        "simplify: input={} output={} tiles={} simplified={} features={} vertices={}=>{}",
+        args.input.display(),
+        output.display(),
+        stats.tiles,
+        stats.simplified_tiles,
+        total.feature_count,
+        total.vertices_before,
+        total.vertices_after
+    );
+    if !stats.by_layer.is_empty() {
+        println!(
+            "- {}:",
+            Style::new()
+                .fg(Color::Blue)
+                .paint("Vertex reduction by layer and zoom")
+        );
+        for (layer, by_zoom) in stats.by_layer.iter() {
+            for (zoom, layer_stats) in by_zoom.iter() {
+                println!(
+                    "  {} z{}: features={} vertices={}=>{} (-{:.1}%)",
+                    layer,
+                    zoom,
+                    layer_stats.feature_count,
+                    layer_stats.vertices_before,
+                    layer_stats.vertices_after,
+                    percent_reduction(layer_stats.vertices_before, layer_stats.vertices_after)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_export(args: vt_optimizer::cli::ExportArgs) -> Result<()> {
+    let input_format = vt_optimizer::format::TileFormat::from_path(&args.input)
+        .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
+    let options = ExportOptions {
+        tile: args.tile.as_deref().map(parse_tile_spec).transpose()?,
+        zoom: args.zoom,
+        bbox: args
+            .bbox
+            .as_deref()
+            .map(|value| {
+                parse_mbtiles_bounds(value).context("--bbox must be minlon,minlat,maxlon,maxlat")
+            })
+            .transpose()?,
+        layers: args.layer.clone(),
+    };
+    let format = match args.format {
+        ExportFormatArg::Geojson => ExportFormat::GeoJson,
+        ExportFormatArg::Geojsonseq => ExportFormat::GeoJsonSeq,
+    };
+    options.validate()?;
+    let scheme = mbtiles_scheme(args.mbtiles_scheme);
+    match args.output.as_ref() {
+        Some(path) => {
+            let file = fs::File::create(path)
+                .with_context(|| format!("failed to create output: {}", path.display()))?;
+            let mut writer = std::io::BufWriter::new(file);
+            let stats = export_geojson(
+                &args.input,
+                input_format,
+                scheme,
+                &options,
+                format,
+                &mut writer,
+            )?;
+            println!(
+                "export: input={} output={} tiles={} features={}",
+                args.input.display(),
+                path.display(),
+                stats.tiles,
+                stats.features
+            );
+        }
+        None => {
+            let mut writer = std::io::BufWriter::new(std::io::stdout().lock());
+            export_geojson(
+                &args.input,
+                input_format,
+                scheme,
+                &options,
+                format,
+                &mut writer,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn run_verify(args: vt_optimizer::cli::VerifyArgs) -> Result<bool> {
+    let input_format = vt_optimizer::format::TileFormat::from_extension(&args.input)
+        .ok_or_else(|| anyhow::anyhow!("cannot infer input format from path"))?;
+    let report = match input_format {
+        vt_optimizer::format::TileFormat::Mbtiles => {
+            verify_mbtiles_with_scheme(&args.input, mbtiles_scheme(args.mbtiles_scheme))?
+        }
+        vt_optimizer::format::TileFormat::Pmtiles => verify_pmtiles(&args.input)?,
+        vt_optimizer::format::TileFormat::Directory => {
+            anyhow::bail!("verify does not support directory input");
+        }
+    };
+    match args.report_format {
+        ReportFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        ReportFormat::Ndjson => {
+            for line in verify_ndjson_lines(&report)? {
+                println!("{line}");
+            }
+        }
+        ReportFormat::Text => {
+            println!("{}", emphasize_section_heading("## Verify"));
+            println!("{}", format_summary_label("Path", &report.path));
+            println!("{}", format_summary_label("Format", &report.format));
+            if let Some(schema) = report.schema.as_deref() {
+                println!("{}", format_summary_label("Schema", schema));
+            }
+            println!(
+                "{}",
+                format_summary_label("Metadata", if report.has_metadata { "yes" } else { "no" })
+            );
+            println!("{}", format_summary_label("Tiles", report.tile_count));
+            println!("{}", format_summary_label("Issues", report.issues.len()));
+            println!(
+                "{}",
+                format_summary_label("Result", if report.is_ok() { "ok" } else { "failed" })
+            );
+            if !report.issues.is_empty() {
+                println!();
+                println!("{}", emphasize_section_heading("## Issues"));
+                for line in format_verify_issue_lines(&report.issues) {
+                    println!("{line}");
+                }
+            }
+        }
+    }
+    Ok(report.is_ok())
+}
+
 #[derive(Serialize)]
 struct OptimizeReport {
     input: OptimizeIoStats,
@@ -869,6 +1272,12 @@ struct OptimizeDetails {
     removed_features_by_zoom: BTreeMap<u8, u64>,
     removed_layers_by_zoom: BTreeMap<String, Vec<u8>>,
     unknown_filters_by_layer: BTreeMap<String, u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    kept_features_by_style: BTreeMap<String, BTreeMap<String, u64>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    removed_attribute_bytes_by_key: BTreeMap<String, u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    removed_names_by_language: BTreeMap<String, RemovedNames>,
 }
 
 fn emphasize_section_heading(line: &str) -> String {
@@ -914,9 +1323,12 @@ fn collect_optimize_io_stats(
     path: &std::path::Path,
     format: vt_optimizer::format::TileFormat,
 ) -> Result<OptimizeIoStats> {
-    let file_size_bytes = fs::metadata(path)
-        .with_context(|| format!("failed to read file size: {}", path.display()))?
-        .len();
+    let file_size_bytes = match format {
+        vt_optimizer::format::TileFormat::Directory => directory_size(path)?,
+        _ => fs::metadata(path)
+            .with_context(|| format!("failed to read file size: {}", path.display()))?
+            .len(),
+    };
     let options = InspectOptions {
         no_progress: true,
         include_layer_list: true,
@@ -925,6 +1337,9 @@ fn collect_optimize_io_stats(
     let report = match format {
         vt_optimizer::format::TileFormat::Mbtiles => inspect_mbtiles_with_options(path, options)?,
         vt_optimizer::format::TileFormat::Pmtiles => inspect_pmtiles_with_options(path, &options)?,
+        vt_optimizer::format::TileFormat::Directory => {
+            inspect_directory_with_options(path, options)?
+        }
     };
     let totals = vt_optimizer::output::summarize_file_layers(&report.file_layers);
     Ok(OptimizeIoStats {
@@ -947,6 +1362,9 @@ fn build_optimize_details(stats: &PruneStats) -> OptimizeDetails {
         removed_features_by_zoom: stats.removed_features_by_zoom.clone(),
         removed_layers_by_zoom,
         unknown_filters_by_layer: stats.unknown_filters_by_layer.clone(),
+        kept_features_by_style: stats.kept_features_by_style.clone(),
+        removed_attribute_bytes_by_key: stats.removed_attribute_bytes_by_key.clone(),
+        removed_names_by_language: stats.removed_names_by_language.clone(),
     }
 }
 
@@ -1168,6 +1586,49 @@ fn print_optimize_summary(
             println!("  {}: {}", layer, count);
         }
     }
+    if !details.removed_attribute_bytes_by_key.is_empty() {
+        println!(
+            "- {}:",
+            Style::new()
+                .fg(Color::Blue)
+                .paint("Removed attributes (approx. bytes)")
+        );
+        let mut removed: Vec<_> = details.removed_attribute_bytes_by_key.iter().collect();
+        removed.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (key, bytes) in removed {
+            println!("  {}: {}", key, format_bytes(*bytes));
+        }
+    }
+    if !details.removed_names_by_language.is_empty() {
+        println!(
+            "- {}:",
+            Style::new()
+                .fg(Color::Blue)
+                .paint("Removed names by language")
+        );
+        for (language, removed) in details.removed_names_by_language.iter() {
+            println!(
+                "  {}: {} ({})",
+                language,
+                removed.properties,
+                format_bytes(removed.value_bytes)
+            );
+        }
+    }
+    if !details.kept_features_by_style.is_empty() {
+        println!(
+            "- {}:",
+            Style::new().fg(Color::Blue).paint("Kept features by style")
+        );
+        for (layer, styles) in details.kept_features_by_style.iter() {
+            let styles = styles
+                .iter()
+                .map(|(style, count)| format!("{style}={count}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("  {}: {}", layer, styles);
+        }
+    }
 }
 
 fn signed_count_diff(before: u64, after: u64) -> i64 {
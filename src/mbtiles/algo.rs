@@ -1,11 +1,12 @@
 use anyhow::Result;
-use geo_types::{
-    Coord, Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Polygon,
-};
+use geo_types::{Geometry, Line, LineString, MultiLineString, MultiPoint, MultiPolygon, Polygon};
 use mvt::{GeomData, GeomEncoder, GeomType};
 
 use crate::mbtiles::stats::HistogramBucket;
-use crate::mbtiles::types::SampleSpec;
+use crate::mbtiles::types::{MAX_TILE_ZOOM, SampleSpec};
+
+// Kept at its original path for downstream callers.
+pub use crate::mbtiles::simplify::simplify_geometry;
 
 pub fn histogram_bucket_index(
     value: u64,
@@ -64,6 +65,14 @@ pub fn count_vertices(geometry: &geo_types::Geometry<f32>) -> usize {
     }
 }
 
+pub fn tile_coord_in_range(zoom: i64, x: i64, y: i64) -> bool {
+    if !(0..=MAX_TILE_ZOOM).contains(&zoom) {
+        return false;
+    }
+    let size = 1i64 << zoom;
+    (0..size).contains(&x) && (0..size).contains(&y)
+}
+
 pub fn format_property_value(value: &mvt_reader::feature::Value) -> String {
     match value {
         mvt_reader::feature::Value::String(text) => text.clone(),
@@ -214,171 +223,6 @@ pub fn encode_geometry(geometry: &Geometry<f32>) -> Result<GeomData> {
     }
 }
 
-pub fn simplify_geometry(geometry: &Geometry<f32>, tolerance: f32) -> Geometry<f32> {
-    if tolerance <= 0.0 {
-        return geometry.clone();
-    }
-
-    match geometry {
-        Geometry::LineString(line) => {
-            let simplified = simplify_line(&line.0, tolerance);
-            Geometry::LineString(LineString::from(simplified))
-        }
-        Geometry::MultiLineString(lines) => {
-            let simplified = lines
-                .0
-                .iter()
-                .map(|line| LineString::from(simplify_line(&line.0, tolerance)))
-                .collect::<Vec<_>>();
-            Geometry::MultiLineString(MultiLineString(simplified))
-        }
-        Geometry::Polygon(polygon) => {
-            let exterior = simplify_ring(&polygon.exterior().0, tolerance);
-            let interiors = polygon
-                .interiors()
-                .iter()
-                .map(|ring| simplify_ring(&ring.0, tolerance))
-                .map(LineString::from)
-                .collect::<Vec<_>>();
-            Geometry::Polygon(Polygon::new(LineString::from(exterior), interiors))
-        }
-        Geometry::MultiPolygon(polygons) => {
-            let simplified = polygons
-                .0
-                .iter()
-                .map(|polygon| {
-                    let exterior = simplify_ring(&polygon.exterior().0, tolerance);
-                    let interiors = polygon
-                        .interiors()
-                        .iter()
-                        .map(|ring| simplify_ring(&ring.0, tolerance))
-                        .map(LineString::from)
-                        .collect::<Vec<_>>();
-                    Polygon::new(LineString::from(exterior), interiors)
-                })
-                .collect::<Vec<_>>();
-            Geometry::MultiPolygon(MultiPolygon(simplified))
-        }
-        _ => geometry.clone(),
-    }
-}
-
-fn simplify_ring(points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>> {
-    if points.len() <= 4 {
-        return points.to_vec();
-    }
-
-    let closed = points.first() == points.last();
-    let core = if closed {
-        points[..points.len() - 1].to_vec()
-    } else {
-        points.to_vec()
-    };
-    let simplified = simplify_line(&core, tolerance);
-    if simplified.len() < 3 {
-        return points.to_vec();
-    }
-    let mut out = simplified;
-    if closed {
-        out.push(out[0]);
-    }
-    out
-}
-
-fn simplify_line(points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>> {
-    if points.len() <= 2 {
-        return points.to_vec();
-    }
-    let sq_tolerance = tolerance * tolerance;
-    let mut reduced = simplify_radial_dist(points, sq_tolerance);
-    if reduced.len() <= 2 {
-        return reduced;
-    }
-    reduced = simplify_douglas_peucker(&reduced, sq_tolerance);
-    reduced
-}
-
-fn simplify_radial_dist(points: &[Coord<f32>], sq_tolerance: f32) -> Vec<Coord<f32>> {
-    let mut prev = points[0];
-    let mut out = vec![prev];
-    for point in points.iter().skip(1) {
-        if get_sq_dist(*point, prev) > sq_tolerance {
-            out.push(*point);
-            prev = *point;
-        }
-    }
-    if prev != *points.last().unwrap() {
-        out.push(*points.last().unwrap());
-    }
-    out
-}
-
-// Ramer–Douglas–Peucker algorithm
-fn simplify_douglas_peucker(points: &[Coord<f32>], sq_tolerance: f32) -> Vec<Coord<f32>> {
-    let last = points.len() - 1;
-    let mut simplified = vec![points[0]];
-    simplify_dp_step(points, 0, last, sq_tolerance, &mut simplified);
-    simplified.push(points[last]);
-    simplified
-}
-
-fn simplify_dp_step(
-    points: &[Coord<f32>],
-    first: usize,
-    last: usize,
-    sq_tolerance: f32,
-    simplified: &mut Vec<Coord<f32>>,
-) {
-    let mut max_sq_dist = sq_tolerance;
-    let mut index = None;
-
-    for i in (first + 1)..last {
-        let sq_dist = get_sq_seg_dist(points[i], points[first], points[last]);
-        if sq_dist > max_sq_dist {
-            index = Some(i);
-            max_sq_dist = sq_dist;
-        }
-    }
-
-    if let Some(idx) = index {
-        if idx - first > 1 {
-            simplify_dp_step(points, first, idx, sq_tolerance, simplified);
-        }
-        simplified.push(points[idx]);
-        if last - idx > 1 {
-            simplify_dp_step(points, idx, last, sq_tolerance, simplified);
-        }
-    }
-}
-
-fn get_sq_dist(p1: Coord<f32>, p2: Coord<f32>) -> f32 {
-    let dx = p1.x - p2.x;
-    let dy = p1.y - p2.y;
-    dx * dx + dy * dy
-}
-
-fn get_sq_seg_dist(p: Coord<f32>, p1: Coord<f32>, p2: Coord<f32>) -> f32 {
-    let mut x = p1.x;
-    let mut y = p1.y;
-    let dx = p2.x - x;
-    let dy = p2.y - y;
-
-    if dx != 0.0 || dy != 0.0 {
-        let t = ((p.x - x) * dx + (p.y - y) * dy) / (dx * dx + dy * dy);
-        if t > 1.0 {
-            x = p2.x;
-            y = p2.y;
-        } else if t > 0.0 {
-            x += dx * t;
-            y += dy * t;
-        }
-    }
-
-    let dx = p.x - x;
-    let dy = p.y - y;
-    dx * dx + dy * dy
-}
-
 pub fn include_sample(index: u64, total: u64, spec: Option<&SampleSpec>) -> bool {
     match spec {
         None => true,
@@ -446,11 +290,7 @@ pub fn build_histogram_from_sizes(
         };
         accum_count += counts[i];
         accum_bytes += bytes[i];
-        let running_avg = if accum_count == 0 {
-            0
-        } else {
-            accum_bytes / accum_count
-        };
+        let running_avg = accum_bytes.checked_div(accum_count).unwrap_or(0);
         let pct_tiles = if total_tiles_used == 0 {
             0.0
         } else {
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+use crate::mbtiles::processing::encode_tile_payload;
+use crate::mbtiles::types::{MbtilesScheme, TileCoord};
+use crate::pmtiles::decode_tile_payload_pmtiles;
+use crate::tiles::{TileRecord, TileSink, TileSource};
+
+use super::{
+    ensure_mbtiles_path, fetch_tile_data, read_metadata, tiles_data_expr, tiles_source_clause,
+};
+
+/// Reads tiles from an MBTiles file with either the `tiles` or `map`/`images`
+/// schema.
+pub struct MbtilesSource {
+    conn: Connection,
+    scheme: MbtilesScheme,
+}
+
+impl MbtilesSource {
+    pub fn open(path: &Path, scheme: MbtilesScheme) -> Result<Self> {
+        ensure_mbtiles_path(path)?;
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open input mbtiles: {}", path.display()))?;
+        Ok(Self { conn, scheme })
+    }
+}
+
+impl TileSource for MbtilesSource {
+    fn metadata(&self) -> Result<BTreeMap<String, String>> {
+        read_metadata(&self.conn)
+    }
+
+    fn for_each_tile(&mut self, visit: &mut dyn FnMut(TileRecord) -> Result<()>) -> Result<()> {
+        let source = tiles_source_clause(&self.conn)?;
+        let data_expr = tiles_data_expr(&self.conn)?;
+        let columns = if source == "tiles" {
+            "zoom_level, tile_column, tile_row"
+        } else {
+            "map.zoom_level, map.tile_column, map.tile_row"
+        };
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {columns}, {data_expr} FROM {source} ORDER BY {columns}"
+            ))
+            .context("prepare tiles")?;
+        let mut rows = stmt.query([]).context("query tiles")?;
+        while let Some(row) = rows.next().context("read tile row")? {
+            let zoom: u8 = row.get(0)?;
+            let x: u32 = row.get(1)?;
+            let y = self.scheme.row_to_y(zoom, row.get(2)?);
+            let data: Vec<u8> = row.get(3)?;
+            visit(tile_record(TileCoord { zoom, x, y }, data))?;
+        }
+        Ok(())
+    }
+
+    fn read_tile(&mut self, coord: TileCoord) -> Result<Option<TileRecord>> {
+        Ok(fetch_tile_data(&self.conn, coord, self.scheme)?.map(|data| tile_record(coord, data)))
+    }
+}
+
+fn tile_record(coord: TileCoord, data: Vec<u8>) -> TileRecord {
+    let compression = u8::from(data.starts_with(&[0x1f, 0x8b]));
+    TileRecord {
+        coord,
+        data,
+        compression,
+    }
+}
+
+/// Writes tiles into a new MBTiles file with the plain `tiles` schema. Brotli
+/// and zstd tiles are stored gzip-compressed.
+pub struct MbtilesSink {
+    conn: Connection,
+    scheme: MbtilesScheme,
+}
+
+impl MbtilesSink {
+    pub fn create(
+        path: &Path,
+        metadata: BTreeMap<String, String>,
+        scheme: MbtilesScheme,
+    ) -> Result<Self> {
+        ensure_mbtiles_path(path)?;
+        let conn = create_mbtiles_output(path, metadata)?;
+        conn.execute_batch("BEGIN")
+            .context("begin output transaction")?;
+        Ok(Self { conn, scheme })
+    }
+}
+
+impl TileSink for MbtilesSink {
+    fn write_tile(&mut self, tile: &TileRecord) -> Result<()> {
+        let TileCoord { zoom, x, y } = tile.coord;
+        let data = match tile.compression {
+            0 | 1 => tile.data.clone(),
+            other => encode_tile_payload(&decode_tile_payload_pmtiles(&tile.data, other)?, true)?,
+        };
+        self.conn
+            .prepare_cached(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .context("prepare tile insert")?
+            .execute((zoom, x, self.scheme.y_to_row(zoom, y), data))
+            .context("insert tile")?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.conn.execute_batch("COMMIT").context("commit output")
+    }
+}
+
+/// Create an MBTiles file with the plain `tiles` schema and `metadata` rows.
+pub(crate) fn create_mbtiles_output(
+    output: &Path,
+    metadata: BTreeMap<String, String>,
+) -> Result<Connection> {
+    let conn = Connection::open(output)
+        .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .context("create output schema")?;
+    for (name, value) in metadata {
+        conn.execute(
+            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+            (name, value),
+        )
+        .context("insert metadata")?;
+    }
+    Ok(conn)
+}
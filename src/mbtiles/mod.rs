@@ -5,29 +5,52 @@ use mvt_reader::Reader;
 use rayon::prelude::*;
 use rusqlite::{Connection, OpenFlags, params};
 use std::cmp::Reverse;
-use std::collections::{BTreeMap, BinaryHeap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashSet};
 use std::path::Path;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use tracing::warn;
 
+use crate::checkpoint::{
+    CHECKPOINT_INTERVAL_TILES, Checkpoint, CheckpointConfig, CheckpointProgress, PendingProgress,
+    RowidRangeProgress, TileMarker, checkpoint_identity, prune_options_fingerprint,
+    remove_checkpoint,
+};
+use crate::format::TileFormat;
+use crate::pmtiles::{
+    PmtilesWriter, encode_metadata_json, mbtiles_metadata_to_pmtiles, mbtiles_writer_options,
+    tile_id_from_xyz,
+};
+
 pub mod algo;
+pub mod io;
 pub mod processing;
+pub mod simplify;
 pub mod stats;
 pub mod types;
 
 pub use self::algo::*;
+pub use self::io::*;
 pub use self::processing::*;
+pub use self::simplify::*;
 pub use self::stats::*;
 pub use self::types::*;
 
-fn fetch_tile_data(conn: &Connection, coord: TileCoord) -> Result<Option<Vec<u8>>> {
+fn fetch_tile_data(
+    conn: &Connection,
+    coord: TileCoord,
+    scheme: MbtilesScheme,
+) -> Result<Option<Vec<u8>>> {
     let query = select_tile_data_query(conn)?;
     let mut stmt = conn.prepare(&query).context("prepare tile data")?;
     let mut rows = stmt
-        .query(params![coord.zoom, coord.x, coord.y])
+        .query(params![
+            coord.zoom,
+            coord.x,
+            scheme.y_to_row(coord.zoom, coord.y)
+        ])
         .context("query tile data")?;
     if let Some(row) = rows.next().context("read tile row")? {
         let data: Vec<u8> = row.get(0)?;
@@ -199,13 +222,13 @@ fn build_file_layer_list(
 fn build_tile_summary(
     conn: &Connection,
     coord: TileCoord,
+    scheme: MbtilesScheme,
     layers_filter: &[String],
 ) -> Result<TileSummary> {
     let query = select_tile_data_query(conn)?;
+    let row = scheme.y_to_row(coord.zoom, coord.y);
     let data: Vec<u8> = conn
-        .query_row(&query, params![coord.zoom, coord.x, coord.y], |row| {
-            row.get(0)
-        })
+        .query_row(&query, params![coord.zoom, coord.x, row], |row| row.get(0))
         .context("failed to read tile data")?;
     let tile_bytes = u64::try_from(data.len()).context("tile data size overflow")?;
     let payload = decode_tile_payload(&data)?;
@@ -425,11 +448,7 @@ fn build_histogram(
         };
         accum_count += counts[i];
         accum_bytes += bytes[i];
-        let running_avg = if accum_count == 0 {
-            0
-        } else {
-            accum_bytes / accum_count
-        };
+        let running_avg = accum_bytes.checked_div(accum_count).unwrap_or(0);
         let pct_tiles = if total_tiles_used == 0 {
             0.0
         } else {
@@ -684,11 +703,7 @@ fn build_zoom_histograms(
             };
             accum_count += accum.counts[i];
             accum_bytes += accum.bytes[i];
-            let running_avg = if accum_count == 0 {
-                0
-            } else {
-                accum_bytes / accum_count
-            };
+            let running_avg = accum_bytes.checked_div(accum_count).unwrap_or(0);
             let pct_tiles = if accum.used_tiles == 0 {
                 0.0
             } else {
@@ -766,7 +781,10 @@ fn apply_read_pragmas_with_cache(conn: &Connection, cache_mb: Option<u64>) -> Re
     Ok(())
 }
 
-fn apply_write_pragmas_with_cache(conn: &Connection, cache_mb: Option<u64>) -> Result<()> {
+pub(crate) fn apply_write_pragmas_with_cache(
+    conn: &Connection,
+    cache_mb: Option<u64>,
+) -> Result<()> {
     let cache_kb = cache_mb.unwrap_or(200).saturating_mul(1024);
     conn.execute_batch(&format!(
         "
@@ -879,7 +897,12 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
 
     let tile_summary = if options.summary {
         let coord = options.tile.context("--summary requires --tile z/x/y")?;
-        Some(build_tile_summary(&conn, coord, &options.layers)?)
+        Some(build_tile_summary(
+            &conn,
+            coord,
+            options.mbtiles_scheme,
+            &options.layers,
+        )?)
     } else {
         None
     };
@@ -1034,7 +1057,7 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
             while let Some(row) = rows.next().context("read tile row")? {
                 let _zoom: u8 = row.get(0)?;
                 let x: u32 = row.get(1)?;
-                let y: u32 = row.get(2)?;
+                let y = options.mbtiles_scheme.row_to_y(zoom, row.get(2)?);
                 let length: i64 = row.get(3)?;
                 let length = u64::try_from(length).context("tile length must be non-negative")?;
                 let tile_data: Option<Vec<u8>> = if need_tile_data {
@@ -1297,7 +1320,7 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                 while let Some(row) = rows.next().context("read bucket row")? {
                     let _zoom: u8 = row.get(0)?;
                     let x: u32 = row.get(1)?;
-                    let y: u32 = row.get(2)?;
+                    let y = options.mbtiles_scheme.row_to_y(zoom, row.get(2)?);
                     let length: i64 = row.get(3)?;
                     let length =
                         u64::try_from(length).context("tile length must be non-negative")?;
@@ -1322,9 +1345,9 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                         });
                         if tiles.len() > list_options.limit {
                             if list_options.sort == TileSort::Size {
-                                tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+                                tiles.sort_by_key(|tile| std::cmp::Reverse(tile.bytes));
                             } else {
-                                tiles.sort_by(|a, b| (a.zoom, a.x, a.y).cmp(&(b.zoom, b.x, b.y)));
+                                tiles.sort_by_key(|tile| (tile.zoom, tile.x, tile.y));
                             }
                             tiles.truncate(list_options.limit);
                         }
@@ -1344,9 +1367,9 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         bucket_tiles = bucket_results.into_iter().flatten().collect();
         if bucket_tiles.len() > list_options.limit {
             if list_options.sort == TileSort::Size {
-                bucket_tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+                bucket_tiles.sort_by_key(|tile| std::cmp::Reverse(tile.bytes));
             } else {
-                bucket_tiles.sort_by(|a, b| (a.zoom, a.x, a.y).cmp(&(b.zoom, b.x, b.y)));
+                bucket_tiles.sort_by_key(|tile| (tile.zoom, tile.x, tile.y));
             }
             bucket_tiles.truncate(list_options.limit);
         }
@@ -1397,7 +1420,7 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
         .into_iter()
         .map(|Reverse((bytes, zoom, x, y))| TopTile { zoom, x, y, bytes })
         .collect::<Vec<_>>();
-    top_tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+    top_tiles.sort_by_key(|tile| std::cmp::Reverse(tile.bytes));
 
     let empty_ratio = if used == 0 {
         0.0
@@ -1513,6 +1536,7 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
                         x: tile.x,
                         y: tile.y,
                     },
+                    options.mbtiles_scheme,
                     &[],
                 )
             })
@@ -1543,7 +1567,7 @@ pub fn inspect_mbtiles_with_options(path: &Path, options: InspectOptions) -> Res
     })
 }
 
-fn read_metadata(conn: &Connection) -> Result<BTreeMap<String, String>> {
+pub(crate) fn read_metadata(conn: &Connection) -> Result<BTreeMap<String, String>> {
     let mut metadata = BTreeMap::new();
     let mut stmt = match conn.prepare("SELECT name, value FROM metadata") {
         Ok(stmt) => stmt,
@@ -1795,6 +1819,109 @@ fn select_tile_count_query(conn: &Connection, with_zoom: bool) -> Result<String>
     }
 }
 
+pub fn verify_mbtiles(path: &Path) -> Result<VerifyReport> {
+    verify_mbtiles_with_scheme(path, MbtilesScheme::Tms)
+}
+
+/// Verify an MBTiles file, reading `tile_row` in the given scheme so issues
+/// carry XYZ coordinates.
+pub fn verify_mbtiles_with_scheme(path: &Path, scheme: MbtilesScheme) -> Result<VerifyReport> {
+    ensure_mbtiles_path(path)?;
+    let conn = open_readonly_mbtiles(path)?;
+    apply_read_pragmas(&conn)?;
+
+    let mut report = VerifyReport {
+        path: path.display().to_string(),
+        format: "mbtiles".to_string(),
+        schema: None,
+        has_metadata: has_table(&conn, "metadata")? || has_view(&conn, "metadata")?,
+        tile_count: 0,
+        issues: Vec::new(),
+    };
+    if !report.has_metadata {
+        report.issues.push(VerifyIssue::archive(
+            VerifyIssueKind::Schema,
+            "mbtiles missing metadata table",
+        ));
+    }
+    let mode = match tiles_schema_mode(&conn) {
+        Ok(mode) => mode,
+        Err(err) => {
+            report.issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Schema,
+                err.to_string(),
+            ));
+            return Ok(report);
+        }
+    };
+    let query = match mode {
+        TilesSchemaMode::Tiles => {
+            report.schema = Some("tiles".to_string());
+            "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles"
+        }
+        TilesSchemaMode::MapImages => {
+            report.schema = Some("map/images".to_string());
+            "SELECT map.zoom_level, map.tile_column, map.tile_row, images.tile_data \
+FROM map LEFT JOIN images ON map.tile_id = images.tile_id"
+        }
+    };
+    let mut stmt = conn.prepare(query).context("prepare verify scan")?;
+    let mut rows = stmt.query([]).context("query verify scan")?;
+    while let Some(row) = rows.next().context("read verify row")? {
+        let zoom: i64 = row.get(0)?;
+        let x: i64 = row.get(1)?;
+        let tile_row: i64 = row.get(2)?;
+        let data: Option<Vec<u8>> = row.get(3)?;
+        report.tile_count += 1;
+        // Out-of-range rows cannot be flipped and are reported as stored.
+        let y = if tile_coord_in_range(zoom, x, tile_row) {
+            i64::from(scheme.row_to_y(zoom as u8, tile_row as u32))
+        } else {
+            report.issues.push(VerifyIssue::tile(
+                VerifyIssueKind::Coordinate,
+                zoom,
+                x,
+                tile_row,
+                format!("tile {zoom}/{x} with tile_row {tile_row} is out of range for its zoom"),
+            ));
+            tile_row
+        };
+        let Some(data) = data else {
+            report.issues.push(VerifyIssue::tile(
+                VerifyIssueKind::Decode,
+                zoom,
+                x,
+                y,
+                "missing tile data",
+            ));
+            continue;
+        };
+        let payload = match decode_tile_payload(&data) {
+            Ok(payload) => payload,
+            Err(err) => {
+                report.issues.push(VerifyIssue::tile(
+                    VerifyIssueKind::Decode,
+                    zoom,
+                    x,
+                    y,
+                    format!("{err:#}"),
+                ));
+                continue;
+            }
+        };
+        if let Err(err) = validate_vector_tile(payload) {
+            report.issues.push(VerifyIssue::tile(
+                VerifyIssueKind::Mvt,
+                zoom,
+                x,
+                y,
+                format!("{err:#}"),
+            ));
+        }
+    }
+    Ok(report)
+}
+
 pub fn copy_mbtiles(input: &Path, output: &Path) -> Result<()> {
     ensure_mbtiles_path(input)?;
     ensure_mbtiles_path(output)?;
@@ -1882,38 +2009,301 @@ pub fn prune_mbtiles_layer_only(
     style: &crate::style::MapboxStyle,
     apply_filters: bool,
     options: PruneOptions,
+) -> Result<PruneStats> {
+    prune_mbtiles_with_checkpoint(input, output, style, apply_filters, options, None)
+}
+
+pub fn prune_mbtiles_with_checkpoint(
+    input: &Path,
+    output: &Path,
+    style: &crate::style::MapboxStyle,
+    apply_filters: bool,
+    options: PruneOptions,
+    checkpoint: Option<&CheckpointConfig>,
 ) -> Result<PruneStats> {
     ensure_mbtiles_path(input)?;
     ensure_mbtiles_path(output)?;
 
+    let identity = match checkpoint {
+        Some(config) => Some(checkpoint_identity(
+            input,
+            TileFormat::Mbtiles,
+            output,
+            TileFormat::Mbtiles,
+            &config.style_hash,
+            prune_options_fingerprint(
+                apply_filters,
+                options.keep_unknown_filters,
+                options.drop_empty_tiles,
+                options.prune_attributes,
+                options.keep_languages.as_deref(),
+                options.rules.as_ref(),
+            ),
+        )?),
+        None => None,
+    };
+    let resumed = match (checkpoint, identity.as_ref()) {
+        (Some(config), Some(identity)) if config.resume => match Checkpoint::load(&config.path)? {
+            Some(state) => {
+                state.ensure_matches(identity)?;
+                if !output.exists() {
+                    anyhow::bail!(
+                        "checkpoint found but output is missing: {}",
+                        output.display()
+                    );
+                }
+                Some(state)
+            }
+            None => None,
+        },
+        _ => None,
+    };
+
     let input_conn = Connection::open(input)
         .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
     apply_read_pragmas_with_cache(&input_conn, options.read_cache_mb)?;
-    let mut output_conn = Connection::open(output)
+    let output_conn = Connection::open(output)
         .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
     apply_write_pragmas_with_cache(&output_conn, options.write_cache_mb)?;
     let schema_mode = tiles_schema_mode(&input_conn)?;
-    create_output_schema(&output_conn, schema_mode)?;
+    let rowid_available = match schema_mode {
+        TilesSchemaMode::Tiles => supports_rowid(&input_conn, "tiles")?,
+        TilesSchemaMode::MapImages => supports_rowid(&input_conn, "map")?,
+    };
+    if checkpoint.is_some() && !rowid_available {
+        anyhow::bail!("--checkpoint requires an input tiles table with rowids");
+    }
 
-    let tx = output_conn
-        .transaction()
+    let tracker = match (&resumed, checkpoint) {
+        (Some(state), _) => {
+            let progress = resolve_pending_progress(&output_conn, schema_mode, state)?;
+            let CheckpointProgress::Rowid { ranges } = progress else {
+                anyhow::bail!("checkpoint does not contain MBTiles rowid progress");
+            };
+            Some(Arc::new(RowidTracker::new(ranges)))
+        }
+        (None, Some(_)) => {
+            let table = match schema_mode {
+                TilesSchemaMode::Tiles => "tiles",
+                TilesSchemaMode::MapImages => "map",
+            };
+            let ranges = rowid_ranges(&input_conn, table, options.readers.max(1))?
+                .into_iter()
+                .map(|(start, end)| RowidRangeProgress {
+                    start,
+                    end,
+                    committed: None,
+                    written_above: Vec::new(),
+                })
+                .collect();
+            Some(Arc::new(RowidTracker::new(ranges)))
+        }
+        (None, None) => None,
+    };
+
+    output_conn
+        .execute_batch("BEGIN")
         .context("begin output transaction")?;
+    if resumed.is_none() {
+        create_output_schema(&output_conn, schema_mode)?;
+        let mut meta_stmt = input_conn
+            .prepare("SELECT name, value FROM metadata")
+            .context("prepare metadata read")?;
+        let mut meta_rows = meta_stmt.query([]).context("query metadata")?;
+        while let Some(row) = meta_rows.next().context("read metadata row")? {
+            let name: String = row.get(0)?;
+            let value: String = row.get(1)?;
+            output_conn
+                .execute(
+                    "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+                    (name, value),
+                )
+                .context("insert metadata")?;
+        }
+    }
+    let mut state = match (checkpoint, identity, &tracker) {
+        (Some(config), Some(identity), Some(tracker)) => {
+            let progress = tracker.snapshot();
+            let mut state = Checkpoint::new(identity, progress.clone());
+            commit_with_checkpoint(&output_conn, config, &mut state, progress, None)?;
+            Some((config, state))
+        }
+        _ => None,
+    };
 
-    let mut meta_stmt = input_conn
-        .prepare("SELECT name, value FROM metadata")
-        .context("prepare metadata read")?;
-    let mut meta_rows = meta_stmt.query([]).context("query metadata")?;
-    while let Some(row) = meta_rows.next().context("read metadata row")? {
-        let name: String = row.get(0)?;
-        let value: String = row.get(1)?;
-        tx.execute(
-            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
-            (name, value),
-        )
-        .context("insert metadata")?;
+    let mut since_checkpoint = 0u64;
+    let mut marker = None;
+    let stats = run_mbtiles_tile_pipeline(
+        input,
+        &input_conn,
+        schema_mode,
+        rowid_available,
+        tracker.clone(),
+        StylePrune::new(style, apply_filters, &options),
+        PipelineOptions::from(&options),
+        |output| {
+            let (written, source) = insert_tile_output(&output_conn, output)?;
+            if written.is_some() {
+                marker = written;
+            }
+            if let (Some(tracker), Some(source), Some((config, state))) =
+                (&tracker, source, state.as_mut())
+            {
+                tracker.written(source);
+                since_checkpoint += 1;
+                if since_checkpoint >= CHECKPOINT_INTERVAL_TILES {
+                    let progress = tracker.snapshot();
+                    commit_with_checkpoint(&output_conn, config, state, progress, marker.take())?;
+                    since_checkpoint = 0;
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    // The last batch goes through the pending marker too, so a crash before the
+    // checkpoint is removed does not resume from an older snapshot.
+    if let (Some(tracker), Some((config, state))) = (&tracker, state.as_mut()) {
+        commit_with_checkpoint(&output_conn, config, state, tracker.snapshot(), marker)?;
+    }
+    output_conn
+        .execute_batch("COMMIT")
+        .context("commit output")?;
+    if let Some(config) = checkpoint {
+        remove_checkpoint(&config.path)?;
+    }
+    if apply_filters && stats.unknown_filters > 0 {
+        warn!(
+            count = stats.unknown_filters,
+            "unknown filter expressions encountered"
+        );
+    }
+    Ok(stats)
+}
+
+/// Prune an MBTiles file straight into a PMTiles archive in one pass.
+pub fn prune_mbtiles_to_pmtiles(
+    input: &Path,
+    output: &Path,
+    style: &crate::style::MapboxStyle,
+    apply_filters: bool,
+    options: PruneOptions,
+    scheme: MbtilesScheme,
+) -> Result<PruneStats> {
+    ensure_mbtiles_path(input)?;
+    crate::pmtiles::ensure_pmtiles_path(output)?;
+
+    let input_conn = Connection::open(input)
+        .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
+    apply_read_pragmas_with_cache(&input_conn, options.read_cache_mb)?;
+    let metadata = read_metadata(&input_conn)?;
+    let schema_mode = tiles_schema_mode(&input_conn)?;
+    let rowid_available = match schema_mode {
+        TilesSchemaMode::Tiles => supports_rowid(&input_conn, "tiles")?,
+        TilesSchemaMode::MapImages => supports_rowid(&input_conn, "map")?,
+    };
+
+    let mut writer = PmtilesWriter::new()?;
+    let mut tile_compression = None;
+    let stats = run_mbtiles_tile_pipeline(
+        input,
+        &input_conn,
+        schema_mode,
+        rowid_available,
+        None,
+        StylePrune::new(style, apply_filters, &options),
+        PipelineOptions::from(&options),
+        |output| {
+            let (zoom, x, row, data) = match output {
+                TileOutput::Tiles {
+                    zoom, x, y, data, ..
+                }
+                | TileOutput::MapImages {
+                    zoom, x, y, data, ..
+                } => (zoom, x, y, data),
+                TileOutput::Dropped { .. } => return Ok(()),
+            };
+            tile_compression.get_or_insert(if data.starts_with(&[0x1f, 0x8b]) {
+                1
+            } else {
+                0
+            });
+            writer.add_tile(tile_id_from_xyz(zoom, x, scheme.row_to_y(zoom, row)), &data)
+        },
+    )?;
+
+    writer.finish(
+        output,
+        &encode_metadata_json(&mbtiles_metadata_to_pmtiles(&metadata), 0)?,
+        mbtiles_writer_options(&metadata, tile_compression.unwrap_or(1)),
+    )?;
+    if apply_filters && stats.unknown_filters > 0 {
+        warn!(
+            count = stats.unknown_filters,
+            "unknown filter expressions encountered"
+        );
+    }
+    Ok(stats)
+}
+
+/// Write one pipeline result; returns the tile written, if any, and the input
+/// row it came from.
+fn insert_tile_output(
+    conn: &Connection,
+    output: TileOutput,
+) -> Result<(Option<TileMarker>, Option<RowSource>)> {
+    match output {
+        TileOutput::Tiles {
+            zoom,
+            x,
+            y,
+            data,
+            source,
+        } => {
+            conn.execute(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+                (zoom as i64, x as i64, y as i64, data),
+            )
+            .context("insert tile")?;
+            Ok((Some(TileMarker { zoom, x, y }), source))
+        }
+        TileOutput::MapImages {
+            zoom,
+            x,
+            y,
+            tile_id,
+            data,
+            source,
+        } => {
+            conn.execute(
+                "INSERT INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (?1, ?2, ?3, ?4)",
+                (zoom as i64, x as i64, y as i64, tile_id.clone()),
+            )
+            .context("insert map row")?;
+            conn.execute(
+                "INSERT INTO images (tile_id, tile_data) VALUES (?1, ?2)",
+                (tile_id, data),
+            )
+            .context("insert image row")?;
+            Ok((Some(TileMarker { zoom, x, y }), source))
+        }
+        TileOutput::Dropped { source } => Ok((None, Some(source))),
     }
+}
 
-    let keep_layers = style.source_layers();
+/// Run `transform` over tiles on parallel readers and workers, handing each
+/// result to `sink` in arrival order.
+#[allow(clippy::too_many_arguments)]
+fn run_mbtiles_tile_pipeline<T: TileTransform>(
+    input: &Path,
+    input_conn: &Connection,
+    schema_mode: TilesSchemaMode,
+    rowid_available: bool,
+    tracker: Option<Arc<RowidTracker>>,
+    transform: T,
+    options: PipelineOptions,
+    mut sink: impl FnMut(TileOutput) -> Result<()>,
+) -> Result<T::Stats> {
     let worker_count = options.threads.max(1);
     let reader_count = options.readers.max(1);
     let queue_capacity = options.io_batch.max(1) as usize;
@@ -1925,25 +2315,20 @@ pub fn prune_mbtiles_layer_only(
     for _ in 0..worker_count {
         let rx_in = rx_in.clone();
         let tx_out = tx_out.clone();
-        let keep_layers = keep_layers.clone();
-        let style = style.clone();
+        let transform = transform.clone();
         let drop_empty_tiles = options.drop_empty_tiles;
-        let keep_unknown_filters = options.keep_unknown_filters;
-        worker_handles.push(thread::spawn(move || -> Result<PruneStats> {
-            let mut stats = PruneStats::default();
+        worker_handles.push(thread::spawn(move || -> Result<T::Stats> {
+            let mut stats = T::Stats::default();
             while let Ok(tile) = rx_in.recv() {
                 let is_gzip = tile.data.starts_with(&[0x1f, 0x8b]);
                 let payload = decode_tile_payload(&tile.data)?;
-                let encoded = prune_tile_layers(
-                    &payload,
-                    tile.zoom,
-                    &style,
-                    &keep_layers,
-                    apply_filters,
-                    keep_unknown_filters,
-                    &mut stats,
-                )?;
+                let encoded = transform.transform(&payload, tile.zoom, &mut stats)?;
                 if encoded.empty && drop_empty_tiles {
+                    if let Some(source) = tile.source {
+                        tx_out
+                            .send(TileOutput::Dropped { source })
+                            .context("send dropped tile")?;
+                    }
                     continue;
                 }
                 let tile_data = encode_tile_payload(&encoded.bytes, is_gzip)?;
@@ -1955,6 +2340,7 @@ pub fn prune_mbtiles_layer_only(
                         y: tile.y,
                         tile_id,
                         data: tile_data,
+                        source: tile.source,
                     }
                 } else {
                     TileOutput::Tiles {
@@ -1962,6 +2348,7 @@ pub fn prune_mbtiles_layer_only(
                         x: tile.x,
                         y: tile.y,
                         data: tile_data,
+                        source: tile.source,
                     }
                 };
                 tx_out.send(output).context("send processed tile")?;
@@ -1971,83 +2358,74 @@ pub fn prune_mbtiles_layer_only(
     }
     drop(tx_out);
 
-    let ranges = match schema_mode {
-        TilesSchemaMode::Tiles => rowid_ranges(&input_conn, "tiles", reader_count).ok(),
-        TilesSchemaMode::MapImages => rowid_ranges(&input_conn, "map", reader_count).ok(),
-    };
-    let rowid_available = match schema_mode {
-        TilesSchemaMode::Tiles => supports_rowid(&input_conn, "tiles")?,
-        TilesSchemaMode::MapImages => supports_rowid(&input_conn, "map")?,
-    };
-
     let reader_handles = if rowid_available {
-        let ranges = ranges.unwrap_or_default();
+        let ranges = match &tracker {
+            Some(tracker) => tracker.resume_ranges(),
+            None => {
+                let ranges = match schema_mode {
+                    TilesSchemaMode::Tiles => rowid_ranges(input_conn, "tiles", reader_count).ok(),
+                    TilesSchemaMode::MapImages => {
+                        rowid_ranges(input_conn, "map", reader_count).ok()
+                    }
+                };
+                ranges.unwrap_or_default()
+            }
+        };
         let mut handles = Vec::with_capacity(ranges.len());
-        for (start_rowid, end_rowid) in ranges {
+        for (range_index, (start_rowid, end_rowid)) in ranges.into_iter().enumerate() {
             let tx_in = tx_in.clone();
             let input_path = input.to_path_buf();
             let read_cache_mb = options.read_cache_mb;
+            let tracker = tracker.clone();
             handles.push(thread::spawn(move || -> Result<()> {
                 let input_conn = Connection::open(&input_path).with_context(|| {
                     format!("failed to open input mbtiles: {}", input_path.display())
                 })?;
                 apply_read_pragmas_with_cache(&input_conn, read_cache_mb)?;
-                match schema_mode {
-                    TilesSchemaMode::Tiles => {
-                        let mut stmt = input_conn
-                            .prepare(
-                                "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles WHERE rowid BETWEEN ?1 AND ?2 ORDER BY rowid",
-                            )
-                            .context("prepare tile scan")?;
-                        let mut rows = stmt
-                            .query(params![start_rowid, end_rowid])
-                            .context("query tiles")?;
-                        while let Some(row) = rows.next().context("read tile row")? {
-                            let zoom: u8 = row.get(0)?;
-                            let x: u32 = row.get(1)?;
-                            let y: u32 = row.get(2)?;
-                            let data: Vec<u8> = row.get(3)?;
-                            if tx_in
-                                .send(TileInput {
-                                    zoom,
-                                    x,
-                                    y,
-                                    data,
-                                    map_images: false,
-                                })
-                                .is_err()
-                            {
-                                break;
-                            }
-                        }
-                    }
-                    TilesSchemaMode::MapImages => {
-                        let mut stmt = input_conn
-                            .prepare(
-                                "SELECT map.zoom_level, map.tile_column, map.tile_row, images.tile_data FROM map JOIN images ON map.tile_id = images.tile_id WHERE map.rowid BETWEEN ?1 AND ?2 ORDER BY map.rowid",
-                            )
-                            .context("prepare map/images scan")?;
-                        let mut rows = stmt
-                            .query(params![start_rowid, end_rowid])
-                            .context("query map/images")?;
-                        while let Some(row) = rows.next().context("read map/images row")? {
-                            let zoom: u8 = row.get(0)?;
-                            let x: u32 = row.get(1)?;
-                            let y: u32 = row.get(2)?;
-                            let data: Vec<u8> = row.get(3)?;
-                            if tx_in
-                                .send(TileInput {
-                                    zoom,
-                                    x,
-                                    y,
-                                    data,
-                                    map_images: true,
-                                })
-                                .is_err()
-                            {
-                                break;
+                let (query, map_images) = match schema_mode {
+                    TilesSchemaMode::Tiles => (
+                        "SELECT rowid, zoom_level, tile_column, tile_row, tile_data FROM tiles WHERE rowid BETWEEN ?1 AND ?2 ORDER BY rowid",
+                        false,
+                    ),
+                    TilesSchemaMode::MapImages => (
+                        "SELECT map.rowid, map.zoom_level, map.tile_column, map.tile_row, images.tile_data FROM map JOIN images ON map.tile_id = images.tile_id WHERE map.rowid BETWEEN ?1 AND ?2 ORDER BY map.rowid",
+                        true,
+                    ),
+                };
+                let mut stmt = input_conn.prepare(query).context("prepare tile scan")?;
+                let mut rows = stmt
+                    .query(params![start_rowid, end_rowid])
+                    .context("query tiles")?;
+                while let Some(row) = rows.next().context("read tile row")? {
+                    let rowid: i64 = row.get(0)?;
+                    let source = match tracker.as_deref() {
+                        Some(tracker) => {
+                            if !tracker.dispatch(range_index, rowid) {
+                                continue;
                             }
+                            Some(RowSource {
+                                range: range_index,
+                                rowid,
+                            })
                         }
+                        None => None,
+                    };
+                    let zoom: u8 = row.get(1)?;
+                    let x: u32 = row.get(2)?;
+                    let y: u32 = row.get(3)?;
+                    let data: Vec<u8> = row.get(4)?;
+                    if tx_in
+                        .send(TileInput {
+                            zoom,
+                            x,
+                            y,
+                            data,
+                            map_images,
+                            source,
+                        })
+                        .is_err()
+                    {
+                        break;
                     }
                 }
                 Ok(())
@@ -2055,7 +2433,7 @@ pub fn prune_mbtiles_layer_only(
         }
         handles
     } else {
-        let zoom_groups = zoom_partitions(&input_conn, reader_count)?;
+        let zoom_groups = zoom_partitions(input_conn, reader_count)?;
         let mut handles = Vec::with_capacity(zoom_groups.len());
         for zooms in zoom_groups {
             let tx_in = tx_in.clone();
@@ -2087,6 +2465,7 @@ pub fn prune_mbtiles_layer_only(
                                         y,
                                         data,
                                         map_images: false,
+                                        source: None,
                                     })
                                     .is_err()
                                 {
@@ -2117,6 +2496,7 @@ pub fn prune_mbtiles_layer_only(
                                         y,
                                         data,
                                         map_images: true,
+                                        source: None,
                                     })
                                     .is_err()
                                 {
@@ -2133,35 +2513,8 @@ pub fn prune_mbtiles_layer_only(
     };
     drop(tx_in);
 
-    let mut stats = PruneStats::default();
     for output in rx_out.iter() {
-        match output {
-            TileOutput::Tiles { zoom, x, y, data } => {
-                tx.execute(
-                    "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
-                    (zoom as i64, x as i64, y as i64, data),
-                )
-                .context("insert tile")?;
-            }
-            TileOutput::MapImages {
-                zoom,
-                x,
-                y,
-                tile_id,
-                data,
-            } => {
-                tx.execute(
-                    "INSERT INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (?1, ?2, ?3, ?4)",
-                    (zoom as i64, x as i64, y as i64, tile_id.clone()),
-                )
-                .context("insert map row")?;
-                tx.execute(
-                    "INSERT INTO images (tile_id, tile_data) VALUES (?1, ?2)",
-                    (tile_id, data),
-                )
-                .context("insert image row")?;
-            }
-        }
+        sink(output)?;
     }
 
     for handle in reader_handles {
@@ -2170,21 +2523,157 @@ pub fn prune_mbtiles_layer_only(
             .map_err(|_| anyhow::anyhow!("reader thread panicked"))??;
     }
 
+    let mut stats = T::Stats::default();
     for handle in worker_handles {
         let worker_stats = handle
             .join()
             .map_err(|_| anyhow::anyhow!("worker thread panicked"))??;
-        stats.merge(worker_stats);
+        T::merge(&mut stats, worker_stats);
     }
+    Ok(stats)
+}
 
-    tx.commit().context("commit output")?;
-    if apply_filters && stats.unknown_filters > 0 {
-        warn!(
-            count = stats.unknown_filters,
-            "unknown filter expressions encountered"
-        );
+/// Commit the open output transaction and record `progress` as durable.
+///
+/// The new progress is first stored as pending together with a tile written in
+/// this transaction, so a crash between the commit and the final checkpoint
+/// write can be told apart on resume.
+fn commit_with_checkpoint(
+    conn: &Connection,
+    config: &CheckpointConfig,
+    state: &mut Checkpoint,
+    progress: CheckpointProgress,
+    marker: Option<TileMarker>,
+) -> Result<()> {
+    if let Some(marker) = marker {
+        state.pending = Some(PendingProgress {
+            progress: progress.clone(),
+            marker,
+        });
+        state.save(&config.path)?;
+    }
+    conn.execute_batch("COMMIT; BEGIN")
+        .context("commit output batch")?;
+    state.progress = progress;
+    state.pending = None;
+    state.save(&config.path)
+}
+
+fn resolve_pending_progress(
+    conn: &Connection,
+    mode: TilesSchemaMode,
+    state: &Checkpoint,
+) -> Result<CheckpointProgress> {
+    let Some(pending) = state.pending.as_ref() else {
+        return Ok(state.progress.clone());
+    };
+    let marker = pending.marker;
+    let query = match mode {
+        TilesSchemaMode::Tiles => {
+            "SELECT COUNT(*) FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3"
+        }
+        TilesSchemaMode::MapImages => {
+            "SELECT COUNT(*) FROM map WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3"
+        }
+    };
+    let count: i64 = conn
+        .query_row(query, params![marker.zoom, marker.x, marker.y], |row| {
+            row.get(0)
+        })
+        .context("check checkpoint marker")?;
+    if count > 0 {
+        Ok(pending.progress.clone())
+    } else {
+        Ok(state.progress.clone())
+    }
+}
+
+/// Tracks which input rowids have reached the output so a watermark can be
+/// committed even though workers finish tiles out of order.
+struct RowidTracker {
+    ranges: Mutex<Vec<RowidRangeState>>,
+}
+
+struct RowidRangeState {
+    progress: RowidRangeProgress,
+    in_flight: BTreeSet<i64>,
+    written_above: BTreeSet<i64>,
+    dispatched: Option<i64>,
+}
+
+impl RowidTracker {
+    fn new(ranges: Vec<RowidRangeProgress>) -> Self {
+        let ranges = ranges
+            .into_iter()
+            .map(|progress| RowidRangeState {
+                written_above: progress.written_above.iter().copied().collect(),
+                progress,
+                in_flight: BTreeSet::new(),
+                dispatched: None,
+            })
+            .collect();
+        Self {
+            ranges: Mutex::new(ranges),
+        }
+    }
+
+    fn resume_ranges(&self) -> Vec<(i64, i64)> {
+        let ranges = self.ranges.lock().expect("rowid tracker lock");
+        ranges
+            .iter()
+            .map(|range| {
+                let start = range
+                    .progress
+                    .committed
+                    .map_or(range.progress.start, |committed| committed + 1);
+                (start, range.progress.end)
+            })
+            .collect()
+    }
+
+    /// Returns false when the rowid was already written by an earlier run.
+    fn dispatch(&self, range: usize, rowid: i64) -> bool {
+        let mut ranges = self.ranges.lock().expect("rowid tracker lock");
+        let state = &mut ranges[range];
+        if state.progress.is_done(rowid) {
+            return false;
+        }
+        state.in_flight.insert(rowid);
+        state.dispatched = Some(rowid);
+        true
+    }
+
+    fn written(&self, source: RowSource) {
+        let mut ranges = self.ranges.lock().expect("rowid tracker lock");
+        let state = &mut ranges[source.range];
+        state.in_flight.remove(&source.rowid);
+        state.written_above.insert(source.rowid);
+    }
+
+    fn snapshot(&self) -> CheckpointProgress {
+        let mut ranges = self.ranges.lock().expect("rowid tracker lock");
+        let ranges = ranges
+            .iter_mut()
+            .map(|state| {
+                let upper = match state.in_flight.first() {
+                    Some(first) => Some(first - 1),
+                    None => state.dispatched,
+                };
+                if let Some(upper) = upper
+                    && upper >= state.progress.start
+                    && state.progress.committed.is_none_or(|prev| upper > prev)
+                {
+                    state.progress.committed = Some(upper);
+                }
+                if let Some(committed) = state.progress.committed {
+                    state.written_above = state.written_above.split_off(&(committed + 1));
+                }
+                state.progress.written_above = state.written_above.iter().copied().collect();
+                state.progress.clone()
+            })
+            .collect();
+        CheckpointProgress::Rowid { ranges }
     }
-    Ok(stats)
 }
 
 #[cfg(test)]
@@ -2241,7 +2730,7 @@ fn zoom_partitions(conn: &Connection, readers: usize) -> Result<Vec<Vec<u8>>> {
     if counts.is_empty() {
         return Ok(Vec::new());
     }
-    counts.sort_by(|a, b| b.1.cmp(&a.1));
+    counts.sort_by_key(|entry| std::cmp::Reverse(entry.1));
     let reader_count = readers.max(1);
     let mut groups: Vec<(u64, Vec<u8>)> = (0..reader_count).map(|_| (0u64, Vec::new())).collect();
     for (zoom, count) in counts {
@@ -2256,6 +2745,12 @@ fn zoom_partitions(conn: &Connection, readers: usize) -> Result<Vec<Vec<u8>>> {
     Ok(groups.into_iter().map(|(_, zooms)| zooms).collect())
 }
 
+#[derive(Debug, Clone, Copy)]
+struct RowSource {
+    range: usize,
+    rowid: i64,
+}
+
 #[derive(Debug)]
 struct TileInput {
     zoom: u8,
@@ -2263,6 +2758,7 @@ struct TileInput {
     y: u32,
     data: Vec<u8>,
     map_images: bool,
+    source: Option<RowSource>,
 }
 
 #[derive(Debug)]
@@ -2272,6 +2768,7 @@ enum TileOutput {
         x: u32,
         y: u32,
         data: Vec<u8>,
+        source: Option<RowSource>,
     },
     MapImages {
         zoom: u8,
@@ -2279,6 +2776,10 @@ enum TileOutput {
         y: u32,
         tile_id: String,
         data: Vec<u8>,
+        source: Option<RowSource>,
+    },
+    Dropped {
+        source: RowSource,
     },
 }
 
@@ -2288,6 +2789,27 @@ pub fn simplify_mbtiles_tile(
     coord: TileCoord,
     layers: &[String],
     tolerance: Option<f64>,
+) -> Result<SimplifyStats> {
+    simplify_mbtiles_tile_with_scheme(
+        input,
+        output,
+        coord,
+        MbtilesScheme::Tms,
+        layers,
+        tolerance,
+        SimplifyMethod::default(),
+    )
+}
+
+/// Simplify one tile addressed in XYZ; `scheme` says how the input stores rows.
+pub fn simplify_mbtiles_tile_with_scheme(
+    input: &Path,
+    output: &Path,
+    coord: TileCoord,
+    scheme: MbtilesScheme,
+    layers: &[String],
+    tolerance: Option<f64>,
+    method: SimplifyMethod,
 ) -> Result<SimplifyStats> {
     ensure_mbtiles_path(input)?;
     ensure_mbtiles_path(output)?;
@@ -2315,7 +2837,7 @@ pub fn simplify_mbtiles_tile(
             .context("insert metadata")?;
     }
 
-    let Some(data) = fetch_tile_data(&input_conn, coord)? else {
+    let Some(data) = fetch_tile_data(&input_conn, coord, scheme)? else {
         anyhow::bail!(
             "tile not found: z={} x={} y={}",
             coord.zoom,
@@ -2327,24 +2849,25 @@ pub fn simplify_mbtiles_tile(
     let payload = decode_tile_payload(&data)?;
 
     let keep_layers: HashSet<String> = layers.iter().cloned().collect();
-    let (filtered, stats) = simplify_tile_payload(&payload, &keep_layers, tolerance)?;
+    let (filtered, stats) = simplify_tile_payload(&payload, &keep_layers, tolerance, method)?;
     let encoded = encode_tile_payload(&filtered, is_gzip)?;
+    let row = scheme.y_to_row(coord.zoom, coord.y);
 
     match schema_mode {
         TilesSchemaMode::Tiles => {
             output_conn
                 .execute(
                     "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
-                    (coord.zoom as i64, coord.x as i64, coord.y as i64, encoded),
+                    (coord.zoom as i64, coord.x as i64, row as i64, encoded),
                 )
                 .context("insert tile")?;
         }
         TilesSchemaMode::MapImages => {
-            let tile_id = format!("{}-{}-{}", coord.zoom, coord.x, coord.y);
+            let tile_id = format!("{}-{}-{}", coord.zoom, coord.x, row);
             output_conn
                 .execute(
                     "INSERT INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (?1, ?2, ?3, ?4)",
-                    (coord.zoom as i64, coord.x as i64, coord.y as i64, tile_id.clone()),
+                    (coord.zoom as i64, coord.x as i64, row as i64, tile_id.clone()),
                 )
                 .context("insert map")?;
             output_conn
@@ -2358,3 +2881,54 @@ pub fn simplify_mbtiles_tile(
 
     Ok(stats)
 }
+
+/// Simplify every tile of an MBTiles file with per-layer/zoom tolerances on
+/// the same reader/worker/writer pipeline as `optimize`.
+pub fn simplify_mbtiles(
+    input: &Path,
+    output: &Path,
+    options: SimplifyOptions,
+) -> Result<TilesetSimplifyStats> {
+    ensure_mbtiles_path(input)?;
+    ensure_mbtiles_path(output)?;
+
+    let input_conn = Connection::open(input)
+        .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
+    apply_read_pragmas_with_cache(&input_conn, options.read_cache_mb)?;
+    let output_conn = Connection::open(output)
+        .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
+    apply_write_pragmas_with_cache(&output_conn, options.write_cache_mb)?;
+    let schema_mode = tiles_schema_mode(&input_conn)?;
+    let rowid_available = match schema_mode {
+        TilesSchemaMode::Tiles => supports_rowid(&input_conn, "tiles")?,
+        TilesSchemaMode::MapImages => supports_rowid(&input_conn, "map")?,
+    };
+
+    output_conn
+        .execute_batch("BEGIN")
+        .context("begin output transaction")?;
+    create_output_schema(&output_conn, schema_mode)?;
+    for (name, value) in read_metadata(&input_conn)? {
+        output_conn
+            .execute(
+                "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+                (name, value),
+            )
+            .context("insert metadata")?;
+    }
+    let pipeline = PipelineOptions::from(&options);
+    let stats = run_mbtiles_tile_pipeline(
+        input,
+        &input_conn,
+        schema_mode,
+        rowid_available,
+        None,
+        TilesetSimplify(options),
+        pipeline,
+        |output| insert_tile_output(&output_conn, output).map(|_| ()),
+    )?;
+    output_conn
+        .execute_batch("COMMIT")
+        .context("commit output")?;
+    Ok(stats)
+}
@@ -4,12 +4,13 @@ use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use mvt::Tile;
 use mvt_reader::Reader;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{Read, Write};
 
-use crate::mbtiles::algo::{count_vertices, encode_geometry, simplify_geometry};
-use crate::mbtiles::stats::{PruneStats, SimplifyStats};
-use crate::mbtiles::types::PrunedTile;
+use crate::mbtiles::algo::{count_vertices, encode_geometry};
+use crate::mbtiles::simplify::{SimplifyEngine, SimplifyMethod};
+use crate::mbtiles::stats::{PruneStats, SimplifyStats, TilesetSimplifyStats};
+use crate::mbtiles::types::{PruneOptions, PrunedTile, SimplifyOptions};
 
 pub fn decode_tile_payload(data: &[u8]) -> Result<Vec<u8>> {
     if data.starts_with(&[0x1f, 0x8b]) {
@@ -34,6 +35,83 @@ pub fn encode_tile_payload(data: &[u8], gzip: bool) -> Result<Vec<u8>> {
     Ok(encoded)
 }
 
+pub fn validate_vector_tile(payload: Vec<u8>) -> Result<()> {
+    let reader =
+        Reader::new(payload).map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
+    let layers = reader
+        .get_layer_metadata()
+        .map_err(|err| anyhow::anyhow!("read layer metadata: {err}"))?;
+    for layer in layers {
+        reader
+            .get_features(layer.layer_index)
+            .map_err(|err| anyhow::anyhow!("read layer {} features: {err}", layer.name))?;
+    }
+    Ok(())
+}
+
+/// Properties dropped from one layer by attribute pruning.
+#[derive(Default)]
+struct RemovedAttribute {
+    occurrences: u64,
+    distinct_values: HashSet<String>,
+    value_bytes: u64,
+}
+
+impl RemovedAttribute {
+    fn record(&mut self, value: &mvt_reader::feature::Value) {
+        self.occurrences += 1;
+        if self.distinct_values.insert(format!("{value:?}")) {
+            self.value_bytes += estimated_value_bytes(value);
+        }
+    }
+
+    /// Approximate uncompressed MVT bytes: the key and each distinct value in
+    /// the layer tables plus two tag indices per feature.
+    fn estimated_bytes(&self, key: &str) -> u64 {
+        key.len() as u64 + 2 + self.value_bytes + self.occurrences * 2
+    }
+}
+
+/// Language tag of a `name:<lang>` or `name_<lang>` key, such as `fr` or
+/// `zh-Hant`. Script and fallback variants (`name_int`, `name:latin`,
+/// `name:nonlatin`) and `name:left`/`name:right` are not languages.
+pub fn name_language(key: &str) -> Option<&str> {
+    let tag = key
+        .strip_prefix("name:")
+        .or_else(|| key.strip_prefix("name_"))?;
+    if matches!(tag, "int" | "latin" | "nonlatin" | "left" | "right") {
+        return None;
+    }
+    let base = tag.split('-').next()?;
+    ((2..=3).contains(&base.len()) && base.bytes().all(|b| b.is_ascii_lowercase())).then_some(tag)
+}
+
+/// Whether `tag` is listed in `languages`, directly or by its base language.
+fn keeps_language(languages: &[String], tag: &str) -> bool {
+    let base = tag.split('-').next().unwrap_or(tag);
+    languages
+        .iter()
+        .any(|language| language.eq_ignore_ascii_case(tag) || language.eq_ignore_ascii_case(base))
+}
+
+fn estimated_value_bytes(value: &mvt_reader::feature::Value) -> u64 {
+    let varint_len = |value: u64| u64::from((64 - value.leading_zeros()).max(1).div_ceil(7));
+    // Every value is a length-delimited message holding one field.
+    2 + match value {
+        mvt_reader::feature::Value::String(text) => 2 + text.len() as u64,
+        mvt_reader::feature::Value::Float(_) => 5,
+        mvt_reader::feature::Value::Double(_) => 9,
+        mvt_reader::feature::Value::Int(val) => 1 + varint_len(*val as u64),
+        mvt_reader::feature::Value::UInt(val) => 1 + varint_len(*val),
+        mvt_reader::feature::Value::SInt(val) => {
+            1 + varint_len(((*val << 1) ^ (*val >> 63)) as u64)
+        }
+        mvt_reader::feature::Value::Bool(_) => 2,
+        mvt_reader::feature::Value::Null => 0,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn prune_tile_layers(
     payload: &[u8],
     zoom: u8,
@@ -41,6 +119,9 @@ pub fn prune_tile_layers(
     keep_layers: &HashSet<String>,
     apply_filters: bool,
     keep_unknown_filters: bool,
+    prune_attributes: bool,
+    keep_languages: Option<&[String]>,
+    rules: Option<&crate::rules::AttributeRules>,
     stats: &mut PruneStats,
 ) -> Result<PrunedTile> {
     let reader = Reader::new(payload.to_vec())
@@ -59,31 +140,40 @@ pub fn prune_tile_layers(
 
     let mut tile = Tile::new(extent);
     let mut kept_layers = 0u32;
+    let attribute_styles = style.style_names().len() > 1;
     for layer in layers {
         if !keep_layers.contains(&layer.name) {
             stats.record_removed_layer(&layer.name, zoom);
             stats.record_removed_features(zoom, layer.feature_count as u64);
             continue;
         }
-        if !style.is_layer_visible_on_zoom(&layer.name, zoom) {
+        let Some(visible_style) = style.visible_style(&layer.name, zoom) else {
             stats.record_removed_layer(&layer.name, zoom);
             stats.record_removed_features(zoom, layer.feature_count as u64);
             continue;
-        }
+        };
         let mut layer_builder = tile.create_layer(&layer.name);
         let features = reader
             .get_features(layer.layer_index)
             .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
         let mut kept_features = 0u64;
+        let mut kept_by_style = vec![0u64; style.style_names().len()];
+        let used_keys = if prune_attributes {
+            style.used_property_keys(&layer.name, zoom)
+        } else {
+            None
+        };
+        let layer_rule = rules.and_then(|rules| rules.layer_rule(&layer.name, zoom));
+        let mut removed_attributes: HashMap<String, RemovedAttribute> = HashMap::new();
         for feature in features {
+            let mut keeping_style = visible_style;
             if apply_filters {
-                match style.should_keep_feature(
-                    &layer.name,
-                    zoom,
-                    &feature,
-                    &mut stats.unknown_filters,
-                ) {
-                    crate::style::FilterResult::True => {}
+                let (result, matched_style) =
+                    style.keeping_style(&layer.name, zoom, &feature, &mut stats.unknown_filters);
+                match result {
+                    crate::style::FilterResult::True => {
+                        keeping_style = matched_style.unwrap_or(visible_style);
+                    }
                     crate::style::FilterResult::Unknown => {
                         stats.record_unknown_layer(&layer.name);
                         if !keep_unknown_filters {
@@ -101,7 +191,25 @@ pub fn prune_tile_layers(
                 feature_builder.set_id(id);
             }
             if let Some(props) = feature.properties {
+                // Sorted keys keep re-encoded tiles byte-for-byte reproducible.
+                let mut props: BTreeMap<_, _> = props.into_iter().collect();
+                if let Some(rule) = layer_rule {
+                    props = rule.transform(props);
+                }
                 for (key, value) in props {
+                    if let Some(languages) = keep_languages
+                        && let Some(language) = name_language(&key)
+                        && !keeps_language(languages, language)
+                    {
+                        stats.record_removed_name(language, &value);
+                        continue;
+                    }
+                    if let Some(used_keys) = used_keys.as_ref()
+                        && !used_keys.contains(key.as_str())
+                    {
+                        removed_attributes.entry(key).or_default().record(&value);
+                        continue;
+                    }
                     match value {
                         mvt_reader::feature::Value::String(text) => {
                             feature_builder.add_tag_string(&key, &text);
@@ -130,6 +238,15 @@ pub fn prune_tile_layers(
             }
             layer_builder = feature_builder.into_layer();
             kept_features += 1;
+            kept_by_style[keeping_style] += 1;
+        }
+        for (key, removed) in removed_attributes {
+            stats.record_removed_attribute(&key, removed.estimated_bytes(&key));
+        }
+        if attribute_styles {
+            for (name, count) in style.style_names().iter().zip(kept_by_style) {
+                stats.record_kept_by_style(&layer.name, name, count);
+            }
         }
         let removed_features = (layer.feature_count as u64).saturating_sub(kept_features);
         stats.record_removed_features(zoom, removed_features);
@@ -155,7 +272,27 @@ pub fn simplify_tile_payload(
     payload: &[u8],
     keep_layers: &HashSet<String>,
     tolerance: Option<f64>,
+    method: SimplifyMethod,
 ) -> Result<(Vec<u8>, SimplifyStats)> {
+    let engine = method.engine();
+    let (bytes, by_layer) =
+        simplify_tile_layers(payload, keep_layers, engine.as_ref(), |_| tolerance)?;
+    let mut stats = SimplifyStats::default();
+    for layer_stats in by_layer.values() {
+        stats.add(layer_stats);
+    }
+    Ok((bytes, stats))
+}
+
+/// Simplify each kept layer (every layer when `keep_layers` is empty) with
+/// its own tolerance and return feature/vertex counts per layer. The whole
+/// tile goes through `engine` at once so shared edges can be kept consistent.
+pub fn simplify_tile_layers(
+    payload: &[u8],
+    keep_layers: &HashSet<String>,
+    engine: &dyn SimplifyEngine,
+    tolerance_for: impl Fn(&str) -> Option<f64>,
+) -> Result<(Vec<u8>, BTreeMap<String, SimplifyStats>)> {
     let reader = Reader::new(payload.to_vec())
         .map_err(|err| anyhow::anyhow!("decode vector tile: {err}"))?;
     let layers = reader
@@ -170,28 +307,41 @@ pub fn simplify_tile_payload(
         }
     }
 
-    let mut tile = Tile::new(extent);
-    let mut stats = SimplifyStats {
-        feature_count: 0,
-        vertices_before: 0,
-        vertices_after: 0,
-    };
+    let mut kept = Vec::new();
+    let mut geometries = Vec::new();
+    let mut tolerances = Vec::new();
     for layer in layers {
         if !keep_layers.is_empty() && !keep_layers.contains(&layer.name) {
             continue;
         }
-        let mut layer_builder = tile.create_layer(&layer.name);
+        let tolerance = tolerance_for(&layer.name)
+            .filter(|value| *value > 0.0)
+            .unwrap_or(0.0) as f32;
         let features = reader
             .get_features(layer.layer_index)
             .map_err(|err| anyhow::anyhow!("read layer features: {err}"))?;
+        for feature in features.iter() {
+            geometries.push(feature.get_geometry().clone());
+            tolerances.push(tolerance);
+        }
+        kept.push((layer.name, features));
+    }
+    let mut simplified = engine
+        .simplify_tile(&geometries, &tolerances)
+        .into_iter()
+        .zip(geometries.iter());
+
+    let mut tile = Tile::new(extent);
+    let mut by_layer: BTreeMap<String, SimplifyStats> = BTreeMap::new();
+    for (name, features) in kept {
+        let mut stats = SimplifyStats::default();
+        let mut layer_builder = tile.create_layer(&name);
         for feature in features {
-            let geometry = feature.get_geometry();
+            let (geometry, original) = simplified
+                .next()
+                .context("simplify engine returned too few geometries")?;
             stats.feature_count += 1;
-            stats.vertices_before += count_vertices(geometry) as u64;
-            let geometry = match tolerance {
-                Some(value) if value > 0.0 => simplify_geometry(geometry, value as f32),
-                _ => geometry.clone(),
-            };
+            stats.vertices_before += count_vertices(original) as u64;
             stats.vertices_after += count_vertices(&geometry) as u64;
             let geom_data = encode_geometry(&geometry)?;
             let mut feature_builder = layer_builder.into_feature(geom_data);
@@ -199,6 +349,8 @@ pub fn simplify_tile_payload(
                 feature_builder.set_id(id);
             }
             if let Some(props) = feature.properties {
+                // Sorted keys keep re-encoded tiles byte-for-byte reproducible.
+                let props: BTreeMap<_, _> = props.into_iter().collect();
                 for (key, value) in props {
                     match value {
                         mvt_reader::feature::Value::String(text) => {
@@ -228,11 +380,143 @@ pub fn simplify_tile_payload(
             }
             layer_builder = feature_builder.into_layer();
         }
+        by_layer.entry(name).or_default().add(&stats);
         tile.add_layer(layer_builder)
             .map_err(|err| anyhow::anyhow!("add layer: {err}"))?;
     }
 
     tile.to_bytes()
         .map_err(|err| anyhow::anyhow!("encode vector tile: {err}"))
-        .map(|bytes| (bytes, stats))
+        .map(|bytes| (bytes, by_layer))
+}
+
+/// Reader/worker settings shared by the tile pipelines.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PipelineOptions {
+    pub threads: usize,
+    pub readers: usize,
+    pub io_batch: u32,
+    pub read_cache_mb: Option<u64>,
+    pub drop_empty_tiles: bool,
+}
+
+impl From<&PruneOptions> for PipelineOptions {
+    fn from(options: &PruneOptions) -> Self {
+        Self {
+            threads: options.threads,
+            readers: options.readers,
+            io_batch: options.io_batch,
+            read_cache_mb: options.read_cache_mb,
+            drop_empty_tiles: options.drop_empty_tiles,
+        }
+    }
+}
+
+impl From<&SimplifyOptions> for PipelineOptions {
+    fn from(options: &SimplifyOptions) -> Self {
+        Self {
+            threads: options.threads,
+            readers: options.readers,
+            io_batch: options.io_batch,
+            read_cache_mb: options.read_cache_mb,
+            drop_empty_tiles: false,
+        }
+    }
+}
+
+/// Per-tile work run by the tile pipelines; every worker owns a clone.
+pub(crate) trait TileTransform: Clone + Send + 'static {
+    type Stats: Default + Send + 'static;
+
+    fn transform(&self, payload: &[u8], zoom: u8, stats: &mut Self::Stats) -> Result<PrunedTile>;
+
+    fn merge(stats: &mut Self::Stats, other: Self::Stats);
+}
+
+/// Style-driven pruning done by `optimize`.
+#[derive(Clone)]
+pub(crate) struct StylePrune {
+    style: crate::style::MapboxStyle,
+    keep_layers: HashSet<String>,
+    apply_filters: bool,
+    options: PruneOptions,
+}
+
+impl StylePrune {
+    pub fn new(
+        style: &crate::style::MapboxStyle,
+        apply_filters: bool,
+        options: &PruneOptions,
+    ) -> Self {
+        Self {
+            style: style.clone(),
+            keep_layers: style.source_layers(),
+            apply_filters,
+            options: options.clone(),
+        }
+    }
+}
+
+impl TileTransform for StylePrune {
+    type Stats = PruneStats;
+
+    fn transform(&self, payload: &[u8], zoom: u8, stats: &mut PruneStats) -> Result<PrunedTile> {
+        prune_tile_layers(
+            payload,
+            zoom,
+            &self.style,
+            &self.keep_layers,
+            self.apply_filters,
+            self.options.keep_unknown_filters,
+            self.options.prune_attributes,
+            self.options.keep_languages.as_deref(),
+            self.options.rules.as_ref(),
+            stats,
+        )
+    }
+
+    fn merge(stats: &mut PruneStats, other: PruneStats) {
+        stats.merge(other);
+    }
+}
+
+/// Tileset-wide simplification with per-layer/zoom tolerances.
+#[derive(Clone)]
+pub(crate) struct TilesetSimplify(pub SimplifyOptions);
+
+impl TileTransform for TilesetSimplify {
+    type Stats = TilesetSimplifyStats;
+
+    fn transform(
+        &self,
+        payload: &[u8],
+        zoom: u8,
+        stats: &mut TilesetSimplifyStats,
+    ) -> Result<PrunedTile> {
+        stats.tiles += 1;
+        if !self.0.simplifies_zoom(zoom) {
+            return Ok(PrunedTile {
+                bytes: payload.to_vec(),
+                empty: false,
+            });
+        }
+        stats.simplified_tiles += 1;
+        let tolerance_for = |layer: &str| self.0.tolerance(layer, zoom);
+        let engine = self.0.method.engine();
+        let (bytes, by_layer) =
+            simplify_tile_layers(payload, &HashSet::new(), engine.as_ref(), tolerance_for)?;
+        for (layer, layer_stats) in by_layer.iter() {
+            if tolerance_for(layer).is_some() {
+                stats.record(layer, zoom, layer_stats);
+            }
+        }
+        Ok(PrunedTile {
+            bytes,
+            empty: false,
+        })
+    }
+
+    fn merge(stats: &mut TilesetSimplifyStats, other: TilesetSimplifyStats) {
+        stats.merge(other);
+    }
 }
@@ -0,0 +1,763 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use geo_types::{Coord, Geometry, LineString, MultiLineString, MultiPolygon, Polygon};
+
+/// Line simplification algorithm used by `simplify`.
+pub trait SimplifyEngine: Send + Sync {
+    /// Simplify an open polyline. The first and last points are always kept.
+    fn simplify_line(&self, points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>>;
+
+    /// Simplify all geometries of one tile; `tolerances[i]` applies to
+    /// `geometries[i]` and values `<= 0` leave the geometry unchanged.
+    fn simplify_tile(
+        &self,
+        geometries: &[Geometry<f32>],
+        tolerances: &[f32],
+    ) -> Vec<Geometry<f32>> {
+        geometries
+            .iter()
+            .zip(tolerances)
+            .map(|(geometry, tolerance)| simplify_geometry_with(self, geometry, *tolerance))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimplifyAlgorithm {
+    /// Radial-distance pre-pass followed by Ramer–Douglas–Peucker.
+    #[default]
+    DouglasPeucker,
+    /// Visvalingam–Whyatt; removes points whose effective area is below
+    /// `tolerance²`.
+    Visvalingam,
+}
+
+/// Engine selection for `simplify`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SimplifyMethod {
+    pub algorithm: SimplifyAlgorithm,
+    /// Simplify edges shared within a tile once and keep polygon rings valid.
+    pub preserve_topology: bool,
+}
+
+impl SimplifyMethod {
+    pub fn engine(self) -> Box<dyn SimplifyEngine> {
+        match (self.algorithm, self.preserve_topology) {
+            (SimplifyAlgorithm::DouglasPeucker, false) => Box::new(DouglasPeucker),
+            (SimplifyAlgorithm::DouglasPeucker, true) => {
+                Box::new(TopologyPreserving(DouglasPeucker))
+            }
+            (SimplifyAlgorithm::Visvalingam, false) => Box::new(VisvalingamWhyatt),
+            (SimplifyAlgorithm::Visvalingam, true) => {
+                Box::new(TopologyPreserving(VisvalingamWhyatt))
+            }
+        }
+    }
+}
+
+pub fn simplify_geometry(geometry: &Geometry<f32>, tolerance: f32) -> Geometry<f32> {
+    simplify_geometry_with(&DouglasPeucker, geometry, tolerance)
+}
+
+pub fn simplify_geometry_with<E: SimplifyEngine + ?Sized>(
+    engine: &E,
+    geometry: &Geometry<f32>,
+    tolerance: f32,
+) -> Geometry<f32> {
+    if tolerance <= 0.0 {
+        return geometry.clone();
+    }
+
+    let simplify_polygon = |polygon: &Polygon<f32>| {
+        let exterior = simplify_ring(engine, &polygon.exterior().0, tolerance);
+        let interiors = polygon
+            .interiors()
+            .iter()
+            .map(|ring| simplify_ring(engine, &ring.0, tolerance))
+            .map(LineString::from)
+            .collect::<Vec<_>>();
+        Polygon::new(LineString::from(exterior), interiors)
+    };
+
+    match geometry {
+        Geometry::LineString(line) => {
+            let simplified = engine.simplify_line(&line.0, tolerance);
+            Geometry::LineString(LineString::from(simplified))
+        }
+        Geometry::MultiLineString(lines) => {
+            let simplified = lines
+                .0
+                .iter()
+                .map(|line| LineString::from(engine.simplify_line(&line.0, tolerance)))
+                .collect::<Vec<_>>();
+            Geometry::MultiLineString(MultiLineString(simplified))
+        }
+        Geometry::Polygon(polygon) => Geometry::Polygon(simplify_polygon(polygon)),
+        Geometry::MultiPolygon(polygons) => {
+            let simplified = polygons.0.iter().map(simplify_polygon).collect::<Vec<_>>();
+            Geometry::MultiPolygon(MultiPolygon(simplified))
+        }
+        _ => geometry.clone(),
+    }
+}
+
+fn simplify_ring<E: SimplifyEngine + ?Sized>(
+    engine: &E,
+    points: &[Coord<f32>],
+    tolerance: f32,
+) -> Vec<Coord<f32>> {
+    if points.len() <= 4 {
+        return points.to_vec();
+    }
+
+    let closed = points.first() == points.last();
+    let core = if closed {
+        &points[..points.len() - 1]
+    } else {
+        points
+    };
+    let simplified = engine.simplify_line(core, tolerance);
+    if simplified.len() < 3 {
+        return points.to_vec();
+    }
+    let mut out = simplified;
+    if closed {
+        out.push(out[0]);
+    }
+    out
+}
+
+/// Radial-distance pre-pass followed by Ramer–Douglas–Peucker.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DouglasPeucker;
+
+impl SimplifyEngine for DouglasPeucker {
+    fn simplify_line(&self, points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>> {
+        if points.len() <= 2 {
+            return points.to_vec();
+        }
+        let sq_tolerance = tolerance * tolerance;
+        let reduced = simplify_radial_dist(points, sq_tolerance);
+        if reduced.len() <= 2 {
+            return reduced;
+        }
+        simplify_douglas_peucker(&reduced, sq_tolerance)
+    }
+}
+
+/// Visvalingam–Whyatt: repeatedly drops the point forming the smallest
+/// triangle with its neighbours until every remaining triangle has an area
+/// of at least `tolerance²`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VisvalingamWhyatt;
+
+impl SimplifyEngine for VisvalingamWhyatt {
+    fn simplify_line(&self, points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>> {
+        let len = points.len();
+        if len <= 2 || tolerance <= 0.0 {
+            return points.to_vec();
+        }
+        let min_area = f64::from(tolerance) * f64::from(tolerance);
+        let mut prev: Vec<usize> = (0..len).map(|index| index.saturating_sub(1)).collect();
+        let mut next: Vec<usize> = (1..=len).collect();
+        let mut area = vec![f64::INFINITY; len];
+        let mut removed = vec![false; len];
+        // Areas are non-negative, so their bit patterns sort like the values.
+        let mut heap = BinaryHeap::new();
+        for index in 1..len - 1 {
+            area[index] = triangle_area(points[index - 1], points[index], points[index + 1]);
+            heap.push(Reverse((area[index].to_bits(), index)));
+        }
+
+        while let Some(Reverse((bits, index))) = heap.pop() {
+            if removed[index] || bits != area[index].to_bits() {
+                continue;
+            }
+            let current = area[index];
+            if current >= min_area {
+                break;
+            }
+            removed[index] = true;
+            let (before, after) = (prev[index], next[index]);
+            next[before] = after;
+            prev[after] = before;
+            for neighbour in [before, after] {
+                if neighbour == 0 || neighbour == len - 1 {
+                    continue;
+                }
+                // A point never becomes cheaper to drop than one already dropped.
+                let updated = triangle_area(
+                    points[prev[neighbour]],
+                    points[neighbour],
+                    points[next[neighbour]],
+                )
+                .max(current);
+                area[neighbour] = updated;
+                heap.push(Reverse((updated.to_bits(), neighbour)));
+            }
+        }
+
+        points
+            .iter()
+            .zip(removed)
+            .filter(|(_, removed)| !removed)
+            .map(|(point, _)| *point)
+            .collect()
+    }
+}
+
+/// Wraps another engine so that edges shared by geometries of the same tile
+/// are simplified once, identically, and polygon rings stay valid.
+///
+/// Paths are split into arcs at vertices where they meet; each arc is
+/// simplified with the smallest tolerance of the geometries using it.
+/// Polygons whose rings would collapse, flip orientation or self-intersect
+/// keep their original arcs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TopologyPreserving<E>(pub E);
+
+impl<E: SimplifyEngine> SimplifyEngine for TopologyPreserving<E> {
+    fn simplify_line(&self, points: &[Coord<f32>], tolerance: f32) -> Vec<Coord<f32>> {
+        self.0.simplify_line(points, tolerance)
+    }
+
+    fn simplify_tile(
+        &self,
+        geometries: &[Geometry<f32>],
+        tolerances: &[f32],
+    ) -> Vec<Geometry<f32>> {
+        let mut graph = ArcGraph::default();
+        let ranges = geometries
+            .iter()
+            .zip(tolerances)
+            .map(|(geometry, tolerance)| graph.add_geometry(geometry, *tolerance))
+            .collect::<Vec<_>>();
+        graph.build_arcs();
+
+        let mut simplified = graph
+            .arcs
+            .iter()
+            .map(|arc| arc.simplify(&self.0))
+            .collect::<Vec<_>>();
+        loop {
+            let mut frozen_any = false;
+            for rings in graph.polygons.iter() {
+                if graph.polygon_is_valid(rings, &simplified) {
+                    continue;
+                }
+                for path in rings {
+                    for (arc, _) in graph.paths[*path].arcs.iter() {
+                        if !graph.arcs[*arc].frozen {
+                            graph.arcs[*arc].frozen = true;
+                            simplified[*arc] = graph.arcs[*arc].points.clone();
+                            frozen_any = true;
+                        }
+                    }
+                }
+            }
+            if !frozen_any {
+                break;
+            }
+        }
+
+        let mut outputs = graph
+            .paths
+            .iter()
+            .map(|path| graph.assemble(path, &simplified));
+        geometries
+            .iter()
+            .zip(tolerances)
+            .zip(ranges)
+            .map(|((geometry, tolerance), range)| {
+                let paths = outputs.by_ref().take(range).collect::<Vec<_>>();
+                if *tolerance <= 0.0 {
+                    geometry.clone()
+                } else {
+                    rebuild_geometry(geometry, paths)
+                }
+            })
+            .collect()
+    }
+}
+
+type PointKey = (u32, u32);
+
+fn point_key(point: Coord<f32>) -> PointKey {
+    // `+ 0.0` folds -0.0 into 0.0.
+    ((point.x + 0.0).to_bits(), (point.y + 0.0).to_bits())
+}
+
+struct TilePath {
+    ring: bool,
+    tolerance: f32,
+    /// Input coordinates, returned as is for paths too short to simplify.
+    original: Vec<Coord<f32>>,
+    /// Coordinates without consecutive duplicates (and without the closing
+    /// point for rings).
+    points: Vec<Coord<f32>>,
+    /// Arc indices in path order, with `true` when traversed backwards.
+    arcs: Vec<(usize, bool)>,
+    /// Signed area of the input ring.
+    area: f64,
+}
+
+impl TilePath {
+    fn is_degenerate(&self) -> bool {
+        self.points.len() < if self.ring { 3 } else { 2 }
+    }
+}
+
+struct TileArc {
+    /// Coordinates in canonical orientation; closed arcs repeat the first point.
+    points: Vec<Coord<f32>>,
+    tolerance: f32,
+    frozen: bool,
+}
+
+impl TileArc {
+    fn simplify<E: SimplifyEngine>(&self, engine: &E) -> Vec<Coord<f32>> {
+        if self.tolerance <= 0.0 {
+            return self.points.clone();
+        }
+        let closed = self.points.len() > 2 && self.points.first() == self.points.last();
+        if !closed {
+            return engine.simplify_line(&self.points, self.tolerance);
+        }
+        let mut simplified =
+            engine.simplify_line(&self.points[..self.points.len() - 1], self.tolerance);
+        if simplified.len() < 3 {
+            return self.points.clone();
+        }
+        simplified.push(simplified[0]);
+        simplified
+    }
+}
+
+#[derive(Default)]
+struct ArcGraph {
+    paths: Vec<TilePath>,
+    /// Path indices of each polygon's rings.
+    polygons: Vec<Vec<usize>>,
+    arcs: Vec<TileArc>,
+}
+
+impl ArcGraph {
+    /// Register the lines and rings of `geometry`; returns how many paths
+    /// were added.
+    fn add_geometry(&mut self, geometry: &Geometry<f32>, tolerance: f32) -> usize {
+        let start = self.paths.len();
+        let tolerance = tolerance.max(0.0);
+        match geometry {
+            Geometry::LineString(line) => self.add_path(&line.0, false, tolerance),
+            Geometry::MultiLineString(lines) => {
+                for line in lines.0.iter() {
+                    self.add_path(&line.0, false, tolerance);
+                }
+            }
+            Geometry::Polygon(polygon) => self.add_polygon(polygon, tolerance),
+            Geometry::MultiPolygon(polygons) => {
+                for polygon in polygons.0.iter() {
+                    self.add_polygon(polygon, tolerance);
+                }
+            }
+            _ => {}
+        }
+        self.paths.len() - start
+    }
+
+    fn add_polygon(&mut self, polygon: &Polygon<f32>, tolerance: f32) {
+        let mut rings = vec![self.paths.len()];
+        self.add_path(&polygon.exterior().0, true, tolerance);
+        for ring in polygon.interiors() {
+            rings.push(self.paths.len());
+            self.add_path(&ring.0, true, tolerance);
+        }
+        self.polygons.push(rings);
+    }
+
+    fn add_path(&mut self, coords: &[Coord<f32>], ring: bool, tolerance: f32) {
+        let mut points: Vec<Coord<f32>> = Vec::with_capacity(coords.len());
+        for point in coords {
+            if points
+                .last()
+                .is_none_or(|last| point_key(*last) != point_key(*point))
+            {
+                points.push(*point);
+            }
+        }
+        if ring && points.len() > 1 && point_key(points[0]) == point_key(points[points.len() - 1]) {
+            points.pop();
+        }
+        let area = if ring { signed_area(&points) } else { 0.0 };
+        self.paths.push(TilePath {
+            ring,
+            tolerance,
+            original: coords.to_vec(),
+            points,
+            arcs: Vec::new(),
+            area,
+        });
+    }
+
+    /// Split every path into arcs at junctions: open-line endpoints and
+    /// vertices with other than two distinct neighbours.
+    fn build_arcs(&mut self) {
+        let mut neighbours: HashMap<PointKey, HashSet<PointKey>> = HashMap::new();
+        let mut junctions: HashSet<PointKey> = HashSet::new();
+        for path in self.paths.iter().filter(|path| !path.is_degenerate()) {
+            let len = path.points.len();
+            for index in 0..len {
+                let entry = neighbours.entry(point_key(path.points[index])).or_default();
+                if index > 0 || path.ring {
+                    entry.insert(point_key(path.points[(index + len - 1) % len]));
+                }
+                if index + 1 < len || path.ring {
+                    entry.insert(point_key(path.points[(index + 1) % len]));
+                }
+            }
+            if !path.ring {
+                junctions.insert(point_key(path.points[0]));
+                junctions.insert(point_key(path.points[len - 1]));
+            }
+        }
+        junctions.extend(
+            neighbours
+                .iter()
+                .filter(|(_, adjacent)| adjacent.len() != 2)
+                .map(|(point, _)| *point),
+        );
+
+        let mut arc_index: HashMap<Vec<PointKey>, usize> = HashMap::new();
+        for path_index in 0..self.paths.len() {
+            let path = &self.paths[path_index];
+            if path.is_degenerate() {
+                continue;
+            }
+            let sequence = if path.ring {
+                let len = path.points.len();
+                let start = (0..len)
+                    .find(|index| junctions.contains(&point_key(path.points[*index])))
+                    .unwrap_or_else(|| {
+                        (0..len)
+                            .min_by_key(|index| point_key(path.points[*index]))
+                            .unwrap_or(0)
+                    });
+                let mut rotated = path.points[start..].to_vec();
+                rotated.extend_from_slice(&path.points[..start]);
+                rotated.push(rotated[0]);
+                rotated
+            } else {
+                path.points.clone()
+            };
+            let tolerance = path.tolerance;
+
+            let mut arcs = Vec::new();
+            let mut begin = 0;
+            for end in 1..sequence.len() {
+                if end + 1 < sequence.len() && !junctions.contains(&point_key(sequence[end])) {
+                    continue;
+                }
+                let segment = &sequence[begin..=end];
+                let forward = segment
+                    .iter()
+                    .map(|point| point_key(*point))
+                    .collect::<Vec<_>>();
+                let backward = forward.iter().rev().copied().collect::<Vec<_>>();
+                let reversed = backward < forward;
+                let key = if reversed { backward } else { forward };
+                let index = *arc_index.entry(key).or_insert_with(|| {
+                    let mut points = segment.to_vec();
+                    if reversed {
+                        points.reverse();
+                    }
+                    self.arcs.push(TileArc {
+                        points,
+                        tolerance,
+                        frozen: false,
+                    });
+                    self.arcs.len() - 1
+                });
+                let arc = &mut self.arcs[index];
+                arc.tolerance = arc.tolerance.min(tolerance);
+                arcs.push((index, reversed));
+                begin = end;
+            }
+            self.paths[path_index].arcs = arcs;
+        }
+    }
+
+    fn assemble(&self, path: &TilePath, simplified: &[Vec<Coord<f32>>]) -> Vec<Coord<f32>> {
+        if path.is_degenerate() {
+            return path.original.clone();
+        }
+        let mut out: Vec<Coord<f32>> = Vec::new();
+        for (position, (arc, reversed)) in path.arcs.iter().enumerate() {
+            let mut points = simplified[*arc].clone();
+            if *reversed {
+                points.reverse();
+            }
+            // Consecutive arcs share their junction point.
+            let skip = usize::from(position > 0);
+            out.extend(points.into_iter().skip(skip));
+        }
+        // Rings were split from a junction; start again where the input did.
+        let first = point_key(path.points[0]);
+        if path.ring
+            && let Some(start) = out.iter().position(|point| point_key(*point) == first)
+            && start > 0
+        {
+            out.pop();
+            out.rotate_left(start);
+            out.push(out[0]);
+        }
+        out
+    }
+
+    /// Rings keep at least four points and their orientation, and no two
+    /// edges of the polygon cross or overlap.
+    fn polygon_is_valid(&self, rings: &[usize], simplified: &[Vec<Coord<f32>>]) -> bool {
+        let mut assembled = Vec::with_capacity(rings.len());
+        for ring in rings {
+            let path = &self.paths[*ring];
+            if path.is_degenerate() {
+                continue;
+            }
+            let points = self.assemble(path, simplified);
+            if points.len() < 4 || area_sign(signed_area(&points)) != area_sign(path.area) {
+                return false;
+            }
+            assembled.push(points);
+        }
+        !rings_intersect(&assembled)
+    }
+}
+
+fn rebuild_geometry(geometry: &Geometry<f32>, paths: Vec<Vec<Coord<f32>>>) -> Geometry<f32> {
+    let mut paths = paths.into_iter();
+    let mut next_line = || LineString::from(paths.next().unwrap_or_default());
+    match geometry {
+        Geometry::LineString(_) => Geometry::LineString(next_line()),
+        Geometry::MultiLineString(lines) => Geometry::MultiLineString(MultiLineString(
+            lines.0.iter().map(|_| next_line()).collect(),
+        )),
+        Geometry::Polygon(polygon) => Geometry::Polygon(rebuild_polygon(polygon, &mut next_line)),
+        Geometry::MultiPolygon(polygons) => Geometry::MultiPolygon(MultiPolygon(
+            polygons
+                .0
+                .iter()
+                .map(|polygon| rebuild_polygon(polygon, &mut next_line))
+                .collect(),
+        )),
+        _ => geometry.clone(),
+    }
+}
+
+fn rebuild_polygon(
+    polygon: &Polygon<f32>,
+    next_line: &mut impl FnMut() -> LineString<f32>,
+) -> Polygon<f32> {
+    let exterior = next_line();
+    let interiors = polygon.interiors().iter().map(|_| next_line()).collect();
+    Polygon::new(exterior, interiors)
+}
+
+fn signed_area(points: &[Coord<f32>]) -> f64 {
+    let len = points.len();
+    let mut sum = 0.0;
+    for index in 0..len {
+        let a = points[index];
+        let b = points[(index + 1) % len];
+        sum += f64::from(a.x) * f64::from(b.y) - f64::from(b.x) * f64::from(a.y);
+    }
+    sum / 2.0
+}
+
+fn area_sign(area: f64) -> i8 {
+    if area > 0.0 {
+        1
+    } else if area < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+fn triangle_area(a: Coord<f32>, b: Coord<f32>, c: Coord<f32>) -> f64 {
+    orientation(a, b, c).abs() / 2.0
+}
+
+/// Twice the signed area of the triangle `a b c`.
+fn orientation(a: Coord<f32>, b: Coord<f32>, c: Coord<f32>) -> f64 {
+    let (ax, ay) = (f64::from(a.x), f64::from(a.y));
+    (f64::from(b.x) - ax) * (f64::from(c.y) - ay) - (f64::from(b.y) - ay) * (f64::from(c.x) - ax)
+}
+
+struct RingSegment {
+    ring: usize,
+    index: usize,
+    start: Coord<f32>,
+    end: Coord<f32>,
+    min: Coord<f32>,
+    max: Coord<f32>,
+}
+
+/// Whether edges of closed `rings` cross, overlap or double back. Edges only
+/// touching at a point are allowed.
+fn rings_intersect(rings: &[Vec<Coord<f32>>]) -> bool {
+    let mut segments = Vec::new();
+    for (ring, points) in rings.iter().enumerate() {
+        let edges = points.len() - 1;
+        for index in 0..edges {
+            let (start, end) = (points[index], points[index + 1]);
+            // The edge after the last one is the first edge again.
+            let next = points[if index + 2 <= edges { index + 2 } else { 1 }];
+            if orientation(start, end, next) == 0.0
+                && (f64::from(end.x) - f64::from(start.x)) * (f64::from(next.x) - f64::from(end.x))
+                    + (f64::from(end.y) - f64::from(start.y))
+                        * (f64::from(next.y) - f64::from(end.y))
+                    < 0.0
+            {
+                return true;
+            }
+            segments.push(RingSegment {
+                ring,
+                index,
+                start,
+                end,
+                min: Coord {
+                    x: start.x.min(end.x),
+                    y: start.y.min(end.y),
+                },
+                max: Coord {
+                    x: start.x.max(end.x),
+                    y: start.y.max(end.y),
+                },
+            });
+        }
+    }
+    segments.sort_by(|a, b| a.min.x.total_cmp(&b.min.x));
+
+    for (position, segment) in segments.iter().enumerate() {
+        for other in segments[position + 1..].iter() {
+            if other.min.x > segment.max.x {
+                break;
+            }
+            if other.min.y > segment.max.y || other.max.y < segment.min.y {
+                continue;
+            }
+            if segment.ring == other.ring {
+                let edges = rings[segment.ring].len() - 1;
+                if (segment.index + 1) % edges == other.index
+                    || (other.index + 1) % edges == segment.index
+                {
+                    continue;
+                }
+            }
+            if segments_conflict(segment.start, segment.end, other.start, other.end) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn segments_conflict(p1: Coord<f32>, p2: Coord<f32>, q1: Coord<f32>, q2: Coord<f32>) -> bool {
+    let d1 = orientation(q1, q2, p1);
+    let d2 = orientation(q1, q2, p2);
+    let d3 = orientation(p1, p2, q1);
+    let d4 = orientation(p1, p2, q2);
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+    if d1 != 0.0 || d2 != 0.0 || d3 != 0.0 || d4 != 0.0 {
+        return false;
+    }
+    // Collinear: overlapping by more than a point.
+    let along_x = (p2.x - p1.x).abs() >= (p2.y - p1.y).abs();
+    let axis = |point: Coord<f32>| if along_x { point.x } else { point.y };
+    let overlap_start = axis(p1).min(axis(p2)).max(axis(q1).min(axis(q2)));
+    let overlap_end = axis(p1).max(axis(p2)).min(axis(q1).max(axis(q2)));
+    overlap_end > overlap_start
+}
+
+fn simplify_radial_dist(points: &[Coord<f32>], sq_tolerance: f32) -> Vec<Coord<f32>> {
+    let mut prev = points[0];
+    let mut out = vec![prev];
+    for point in points.iter().skip(1) {
+        if get_sq_dist(*point, prev) > sq_tolerance {
+            out.push(*point);
+            prev = *point;
+        }
+    }
+    if prev != *points.last().unwrap() {
+        out.push(*points.last().unwrap());
+    }
+    out
+}
+
+// Ramer–Douglas–Peucker algorithm
+fn simplify_douglas_peucker(points: &[Coord<f32>], sq_tolerance: f32) -> Vec<Coord<f32>> {
+    let last = points.len() - 1;
+    let mut simplified = vec![points[0]];
+    simplify_dp_step(points, 0, last, sq_tolerance, &mut simplified);
+    simplified.push(points[last]);
+    simplified
+}
+
+fn simplify_dp_step(
+    points: &[Coord<f32>],
+    first: usize,
+    last: usize,
+    sq_tolerance: f32,
+    simplified: &mut Vec<Coord<f32>>,
+) {
+    let mut max_sq_dist = sq_tolerance;
+    let mut index = None;
+
+    for i in (first + 1)..last {
+        let sq_dist = get_sq_seg_dist(points[i], points[first], points[last]);
+        if sq_dist > max_sq_dist {
+            index = Some(i);
+            max_sq_dist = sq_dist;
+        }
+    }
+
+    if let Some(idx) = index {
+        if idx - first > 1 {
+            simplify_dp_step(points, first, idx, sq_tolerance, simplified);
+        }
+        simplified.push(points[idx]);
+        if last - idx > 1 {
+            simplify_dp_step(points, idx, last, sq_tolerance, simplified);
+        }
+    }
+}
+
+fn get_sq_dist(p1: Coord<f32>, p2: Coord<f32>) -> f32 {
+    let dx = p1.x - p2.x;
+    let dy = p1.y - p2.y;
+    dx * dx + dy * dy
+}
+
+fn get_sq_seg_dist(p: Coord<f32>, p1: Coord<f32>, p2: Coord<f32>) -> f32 {
+    let mut x = p1.x;
+    let mut y = p1.y;
+    let dx = p2.x - x;
+    let dy = p2.y - y;
+
+    if dx != 0.0 || dy != 0.0 {
+        let t = ((p.x - x) * dx + (p.y - y) * dy) / (dx * dx + dy * dy);
+        if t > 1.0 {
+            x = p2.x;
+            y = p2.y;
+        } else if t > 0.0 {
+            x += dx * t;
+            y += dy * t;
+        }
+    }
+
+    let dx = p.x - x;
+    let dy = p.y - y;
+    dx * dx + dy * dy
+}
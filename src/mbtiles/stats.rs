@@ -37,13 +37,58 @@ pub struct MbtilesReport {
     pub top_tile_summaries: Vec<TileSummary>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct SimplifyStats {
     pub feature_count: u64,
     pub vertices_before: u64,
     pub vertices_after: u64,
 }
 
+impl SimplifyStats {
+    pub fn add(&mut self, other: &SimplifyStats) {
+        self.feature_count += other.feature_count;
+        self.vertices_before += other.vertices_before;
+        self.vertices_after += other.vertices_after;
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct TilesetSimplifyStats {
+    pub tiles: u64,
+    /// Tiles inside the zoom range with at least one tolerance rule.
+    pub simplified_tiles: u64,
+    /// Feature and vertex counts of simplified layers, per layer and zoom.
+    pub by_layer: BTreeMap<String, BTreeMap<u8, SimplifyStats>>,
+}
+
+impl TilesetSimplifyStats {
+    pub fn record(&mut self, layer: &str, zoom: u8, stats: &SimplifyStats) {
+        let by_zoom = match self.by_layer.get_mut(layer) {
+            Some(by_zoom) => by_zoom,
+            None => self.by_layer.entry(layer.to_string()).or_default(),
+        };
+        by_zoom.entry(zoom).or_default().add(stats);
+    }
+
+    pub fn merge(&mut self, other: TilesetSimplifyStats) {
+        self.tiles += other.tiles;
+        self.simplified_tiles += other.simplified_tiles;
+        for (layer, by_zoom) in other.by_layer.into_iter() {
+            for (zoom, stats) in by_zoom.into_iter() {
+                self.record(&layer, zoom, &stats);
+            }
+        }
+    }
+
+    pub fn total(&self) -> SimplifyStats {
+        let mut total = SimplifyStats::default();
+        for stats in self.by_layer.values().flat_map(|by_zoom| by_zoom.values()) {
+            total.add(stats);
+        }
+        total
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct HistogramBucket {
     pub min_bytes: u64,
@@ -108,11 +153,7 @@ pub struct TileSummary {
 }
 
 pub fn finalize_stats(stats: &mut MbtilesStats) {
-    if stats.tile_count == 0 {
-        stats.avg_bytes = 0;
-    } else {
-        stats.avg_bytes = stats.total_bytes / stats.tile_count;
-    }
+    stats.avg_bytes = stats.total_bytes.checked_div(stats.tile_count).unwrap_or(0);
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -121,6 +162,22 @@ pub struct PruneStats {
     pub removed_layers_by_zoom: BTreeMap<String, BTreeSet<u8>>,
     pub unknown_filters: usize,
     pub unknown_filters_by_layer: BTreeMap<String, u64>,
+    /// Kept features per source-layer, credited to the first style that
+    /// keeps them. Only recorded when several styles are combined.
+    pub kept_features_by_style: BTreeMap<String, BTreeMap<String, u64>>,
+    /// Approximate uncompressed bytes saved per property key by attribute
+    /// pruning.
+    pub removed_attribute_bytes_by_key: BTreeMap<String, u64>,
+    /// `name:<lang>` properties dropped by `--keep-languages`, per language.
+    pub removed_names_by_language: BTreeMap<String, RemovedNames>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RemovedNames {
+    /// Removed key/value pairs.
+    pub properties: u64,
+    /// UTF-8 bytes of the removed string values.
+    pub value_bytes: u64,
 }
 
 impl PruneStats {
@@ -145,6 +202,39 @@ impl PruneStats {
             .or_insert(0) += 1;
     }
 
+    pub fn record_kept_by_style(&mut self, layer: &str, style: &str, count: u64) {
+        if count == 0 {
+            return;
+        }
+        *self
+            .kept_features_by_style
+            .entry(layer.to_string())
+            .or_default()
+            .entry(style.to_string())
+            .or_insert(0) += count;
+    }
+
+    pub fn record_removed_attribute(&mut self, key: &str, bytes: u64) {
+        *self
+            .removed_attribute_bytes_by_key
+            .entry(key.to_string())
+            .or_insert(0) += bytes;
+    }
+
+    pub fn record_removed_name(&mut self, language: &str, value: &mvt_reader::feature::Value) {
+        let removed = match self.removed_names_by_language.get_mut(language) {
+            Some(removed) => removed,
+            None => self
+                .removed_names_by_language
+                .entry(language.to_string())
+                .or_default(),
+        };
+        removed.properties += 1;
+        if let mvt_reader::feature::Value::String(text) = value {
+            removed.value_bytes += text.len() as u64;
+        }
+    }
+
     pub fn merge(&mut self, other: PruneStats) {
         for (zoom, count) in other.removed_features_by_zoom.into_iter() {
             *self.removed_features_by_zoom.entry(zoom).or_insert(0) += count;
@@ -159,5 +249,100 @@ impl PruneStats {
         for (layer, count) in other.unknown_filters_by_layer.into_iter() {
             *self.unknown_filters_by_layer.entry(layer).or_insert(0) += count;
         }
+        for (language, removed) in other.removed_names_by_language.into_iter() {
+            let entry = self.removed_names_by_language.entry(language).or_default();
+            entry.properties += removed.properties;
+            entry.value_bytes += removed.value_bytes;
+        }
+        for (key, bytes) in other.removed_attribute_bytes_by_key.into_iter() {
+            *self.removed_attribute_bytes_by_key.entry(key).or_insert(0) += bytes;
+        }
+        for (layer, styles) in other.kept_features_by_style.into_iter() {
+            let entry = self.kept_features_by_style.entry(layer).or_default();
+            for (style, count) in styles {
+                *entry.entry(style).or_insert(0) += count;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyIssueKind {
+    Schema,
+    Decode,
+    Mvt,
+    Coordinate,
+    Header,
+    Directory,
+}
+
+impl VerifyIssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyIssueKind::Schema => "schema",
+            VerifyIssueKind::Decode => "decode",
+            VerifyIssueKind::Mvt => "mvt",
+            VerifyIssueKind::Coordinate => "coordinate",
+            VerifyIssueKind::Header => "header",
+            VerifyIssueKind::Directory => "directory",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifyIssue {
+    pub kind: VerifyIssueKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zoom: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<i64>,
+    pub message: String,
+}
+
+impl VerifyIssue {
+    pub fn archive(kind: VerifyIssueKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            zoom: None,
+            x: None,
+            y: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn tile(
+        kind: VerifyIssueKind,
+        zoom: i64,
+        x: i64,
+        y: i64,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            zoom: Some(zoom),
+            x: Some(x),
+            y: Some(y),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    pub path: String,
+    pub format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    pub has_metadata: bool,
+    pub tile_count: u64,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
     }
 }
@@ -1,5 +1,7 @@
 use anyhow::{Context, Result};
 
+use crate::mbtiles::simplify::SimplifyMethod;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TileCoord {
     pub zoom: u8,
@@ -7,6 +9,34 @@ pub struct TileCoord {
     pub y: u32,
 }
 
+/// Row numbering of an MBTiles `tile_row` column. The MBTiles spec uses TMS
+/// (row 0 at the south edge); some producers write XYZ rows instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MbtilesScheme {
+    #[default]
+    Tms,
+    Xyz,
+}
+
+impl MbtilesScheme {
+    /// Convert a stored `tile_row` into an XYZ `y`.
+    pub fn row_to_y(self, zoom: u8, row: u32) -> u32 {
+        self.flip(zoom, row)
+    }
+
+    /// Convert an XYZ `y` into the stored `tile_row`.
+    pub fn y_to_row(self, zoom: u8, y: u32) -> u32 {
+        self.flip(zoom, y)
+    }
+
+    fn flip(self, zoom: u8, value: u32) -> u32 {
+        match self {
+            MbtilesScheme::Tms => ((1u64 << zoom) - 1).saturating_sub(u64::from(value)) as u32,
+            MbtilesScheme::Xyz => value,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum SampleSpec {
     Ratio(f64),
@@ -28,6 +58,7 @@ pub struct InspectOptions {
     pub recommend: bool,
     pub include_layer_list: bool,
     pub list_tiles: Option<TileListOptions>,
+    pub mbtiles_scheme: MbtilesScheme,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -47,6 +78,7 @@ impl Default for InspectOptions {
             recommend: false,
             include_layer_list: false,
             list_tiles: None,
+            mbtiles_scheme: MbtilesScheme::Tms,
         }
     }
 }
@@ -63,7 +95,7 @@ pub struct TileListOptions {
     pub sort: TileSort,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct PruneOptions {
     pub threads: usize,
     pub io_batch: u32,
@@ -72,9 +104,95 @@ pub struct PruneOptions {
     pub write_cache_mb: Option<u64>,
     pub drop_empty_tiles: bool,
     pub keep_unknown_filters: bool,
+    /// Drop feature properties that no rendering style layer reads.
+    pub prune_attributes: bool,
+    /// Keep only these languages among `name:<lang>`/`name_<lang>` keys.
+    pub keep_languages: Option<Vec<String>>,
+    /// Per-layer keep/drop/rename/cast rules for feature properties.
+    pub rules: Option<crate::rules::AttributeRules>,
+}
+
+/// Matches the `optimize` CLI defaults with one thread and one reader.
+impl Default for PruneOptions {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            io_batch: 1_000,
+            readers: 1,
+            read_cache_mb: None,
+            write_cache_mb: None,
+            drop_empty_tiles: false,
+            keep_unknown_filters: true,
+            prune_attributes: false,
+            keep_languages: None,
+            rules: None,
+        }
+    }
+}
+
+/// Simplification tolerance for one layer (or every layer) over a zoom range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToleranceRule {
+    pub layer: Option<String>,
+    pub minzoom: u8,
+    pub maxzoom: u8,
+    pub tolerance: f64,
+}
+
+impl ToleranceRule {
+    pub fn matches(&self, layer: &str, zoom: u8) -> bool {
+        (self.minzoom..=self.maxzoom).contains(&zoom)
+            && self.layer.as_deref().is_none_or(|name| name == layer)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimplifyOptions {
+    pub threads: usize,
+    pub io_batch: u32,
+    pub readers: usize,
+    pub read_cache_mb: Option<u64>,
+    pub write_cache_mb: Option<u64>,
+    /// Zoom range to simplify; tiles outside it are copied unchanged.
+    pub minzoom: Option<u8>,
+    pub maxzoom: Option<u8>,
+    /// Tolerances in tile units; the last rule matching a layer and zoom wins.
+    pub tolerances: Vec<ToleranceRule>,
+    pub method: SimplifyMethod,
+}
+
+impl SimplifyOptions {
+    /// Tolerance for `layer` at `zoom`, or `None` to leave the layer as is.
+    pub fn tolerance(&self, layer: &str, zoom: u8) -> Option<f64> {
+        if !self.covers_zoom(zoom) {
+            return None;
+        }
+        self.tolerances
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(layer, zoom))
+            .map(|rule| rule.tolerance)
+            .filter(|tolerance| *tolerance > 0.0)
+    }
+
+    /// Whether any layer may be simplified at `zoom`.
+    pub fn simplifies_zoom(&self, zoom: u8) -> bool {
+        self.covers_zoom(zoom)
+            && self
+                .tolerances
+                .iter()
+                .any(|rule| (rule.minzoom..=rule.maxzoom).contains(&zoom))
+    }
+
+    fn covers_zoom(&self, zoom: u8) -> bool {
+        self.minzoom.is_none_or(|minzoom| zoom >= minzoom)
+            && self.maxzoom.is_none_or(|maxzoom| zoom <= maxzoom)
+    }
 }
 
 pub const EMPTY_TILE_MAX_BYTES: u64 = 50;
+/// Highest zoom whose tile IDs fit the PMTiles Hilbert numbering used here.
+pub const MAX_TILE_ZOOM: i64 = 30;
 
 pub struct PrunedTile {
     pub bytes: Vec<u8>,
@@ -108,3 +226,39 @@ pub fn parse_tile_spec(value: &str) -> Result<TileCoord> {
     let y: u32 = y_str.parse().context("invalid tile y")?;
     Ok(TileCoord { zoom, x, y })
 }
+
+/// Parse `[LAYER][@ZOOM[-ZOOM]]=TOLERANCE`, e.g. `roads@0-8=4`, `@12=0.5`
+/// or a bare `2` for every layer and zoom.
+pub fn parse_tolerance_rule(value: &str) -> Result<ToleranceRule> {
+    let trimmed = value.trim();
+    let (target, tolerance) = match trimmed.rsplit_once('=') {
+        Some((target, tolerance)) => (target.trim(), tolerance),
+        None => ("", trimmed),
+    };
+    let tolerance: f64 = tolerance.trim().parse().context("invalid tolerance")?;
+    if !tolerance.is_finite() || tolerance < 0.0 {
+        anyhow::bail!("tolerance must be zero or greater");
+    }
+    let (layer, zooms) = match target.split_once('@') {
+        Some((layer, zooms)) => (layer, Some(zooms)),
+        None => (target, None),
+    };
+    let (minzoom, maxzoom) = match zooms {
+        Some(zooms) => {
+            let (min, max) = zooms.split_once('-').unwrap_or((zooms, zooms));
+            let minzoom: u8 = min.trim().parse().context("invalid tolerance zoom")?;
+            let maxzoom: u8 = max.trim().parse().context("invalid tolerance zoom")?;
+            if minzoom > maxzoom {
+                anyhow::bail!("tolerance zoom range {minzoom}-{maxzoom} is empty");
+            }
+            (minzoom, maxzoom)
+        }
+        None => (0, u8::MAX),
+    };
+    Ok(ToleranceRule {
+        layer: (!layer.is_empty()).then(|| layer.to_string()),
+        minzoom,
+        maxzoom,
+        tolerance,
+    })
+}
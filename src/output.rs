@@ -7,7 +7,7 @@ use std::collections::BTreeMap;
 
 use crate::mbtiles::{
     FileLayerSummary, HistogramBucket, MbtilesReport, MbtilesZoomStats, TileSummary, TopTile,
-    ZoomHistogram,
+    VerifyIssue, VerifyReport, ZoomHistogram,
 };
 
 use std::collections::BTreeSet;
@@ -498,6 +498,43 @@ pub fn format_top_tiles_lines(tiles: &[TopTile]) -> Vec<String> {
         .collect()
 }
 
+pub fn verify_ndjson_lines(report: &VerifyReport) -> Result<Vec<String>> {
+    let mut lines = Vec::with_capacity(report.issues.len() + 1);
+    lines.push(serde_json::to_string(&json!({
+        "type": "summary",
+        "path": report.path,
+        "format": report.format,
+        "schema": report.schema,
+        "has_metadata": report.has_metadata,
+        "tile_count": report.tile_count,
+        "issue_count": report.issues.len(),
+        "ok": report.is_ok(),
+    }))?);
+    for issue in report.issues.iter() {
+        let mut value = serde_json::to_value(issue)?;
+        if let Some(map) = value.as_object_mut() {
+            map.insert("type".to_string(), json!("issue"));
+        }
+        lines.push(serde_json::to_string(&value)?);
+    }
+    Ok(lines)
+}
+
+pub fn format_verify_issue_lines(issues: &[VerifyIssue]) -> Vec<String> {
+    issues
+        .iter()
+        .map(|issue| {
+            let kind = issue.kind.as_str();
+            match (issue.zoom, issue.x, issue.y) {
+                (Some(z), Some(x), Some(y)) => {
+                    format!("- [{kind}] -z {z} -x {x} -y {y}: {}", issue.message)
+                }
+                _ => format!("- [{kind}] {}", issue.message),
+            }
+        })
+        .collect()
+}
+
 pub fn format_tile_summary_text(summary: &TileSummary) -> Vec<String> {
     let label = |text: &str| Color::Blue.paint(text).to_string();
     vec![
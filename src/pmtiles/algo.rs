@@ -1,6 +1,6 @@
 use crate::pmtiles::{
     Header,
-    types::{Entry, HEADER_SIZE},
+    types::{Entry, GeoBounds, GeoCenter, HEADER_SIZE},
 };
 use anyhow::{Context, Result};
 use hilbert_2d::{Variant, h2xy_discrete, xy2h_discrete};
@@ -56,6 +56,120 @@ pub fn tile_id_to_xyz(tile_id: u64) -> (u8, u32, u32) {
     }
 }
 
+/// Bounds covered by the tile range `min_x..=max_x`, `min_y..=max_y` (XYZ) at `z`.
+pub fn tile_range_bounds(z: u8, min_x: u32, min_y: u32, max_x: u32, max_y: u32) -> GeoBounds {
+    let n = (1u64 << z) as f64;
+    let longitude = |x: u64| x as f64 / n * 360.0 - 180.0;
+    let latitude = |y: u64| {
+        (std::f64::consts::PI * (1.0 - 2.0 * y as f64 / n))
+            .sinh()
+            .atan()
+            .to_degrees()
+    };
+    GeoBounds {
+        min_longitude: longitude(u64::from(min_x)),
+        min_latitude: latitude(u64::from(max_y) + 1),
+        max_longitude: longitude(u64::from(max_x) + 1),
+        max_latitude: latitude(u64::from(min_y)),
+    }
+}
+
+/// PMTiles tile type for an MBTiles `format` value (0 when unknown).
+pub fn tile_type_from_format(format: &str) -> u8 {
+    match format.trim().to_ascii_lowercase().as_str() {
+        "pbf" | "mvt" => 1,
+        "png" => 2,
+        "jpg" | "jpeg" => 3,
+        "webp" => 4,
+        "avif" => 5,
+        _ => 0,
+    }
+}
+
+/// MBTiles `format` value for a PMTiles tile type.
+pub fn format_from_tile_type(tile_type: u8) -> Option<&'static str> {
+    match tile_type {
+        1 => Some("pbf"),
+        2 => Some("png"),
+        3 => Some("jpg"),
+        4 => Some("webp"),
+        5 => Some("avif"),
+        _ => None,
+    }
+}
+
+/// Parse MBTiles `bounds` metadata ("west,south,east,north").
+pub fn parse_mbtiles_bounds(value: &str) -> Option<GeoBounds> {
+    let parts = parse_f64_list(value)?;
+    let [min_longitude, min_latitude, max_longitude, max_latitude] = parts[..] else {
+        return None;
+    };
+    Some(GeoBounds {
+        min_longitude,
+        min_latitude,
+        max_longitude,
+        max_latitude,
+    })
+}
+
+/// Parse MBTiles `center` metadata ("longitude,latitude,zoom").
+pub fn parse_mbtiles_center(value: &str) -> Option<GeoCenter> {
+    let parts = parse_f64_list(value)?;
+    let [longitude, latitude, zoom] = parts[..] else {
+        return None;
+    };
+    if !(0.0..=f64::from(u8::MAX)).contains(&zoom) {
+        return None;
+    }
+    Some(GeoCenter {
+        longitude,
+        latitude,
+        zoom: zoom as u8,
+    })
+}
+
+fn parse_f64_list(value: &str) -> Option<Vec<f64>> {
+    value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>().ok().filter(|v| v.is_finite()))
+        .collect()
+}
+
+pub fn header_bounds(header: &Header) -> GeoBounds {
+    GeoBounds {
+        min_longitude: from_e7(header.min_longitude),
+        min_latitude: from_e7(header.min_latitude),
+        max_longitude: from_e7(header.max_longitude),
+        max_latitude: from_e7(header.max_latitude),
+    }
+}
+
+pub fn header_center(header: &Header) -> GeoCenter {
+    GeoCenter {
+        longitude: from_e7(header.center_longitude),
+        latitude: from_e7(header.center_latitude),
+        zoom: header.center_zoom,
+    }
+}
+
+pub fn set_header_bounds(header: &mut Header, bounds: GeoBounds, center: GeoCenter) {
+    header.min_longitude = to_e7(bounds.min_longitude);
+    header.min_latitude = to_e7(bounds.min_latitude);
+    header.max_longitude = to_e7(bounds.max_longitude);
+    header.max_latitude = to_e7(bounds.max_latitude);
+    header.center_zoom = center.zoom;
+    header.center_longitude = to_e7(center.longitude);
+    header.center_latitude = to_e7(center.latitude);
+}
+
+fn to_e7(degrees: f64) -> i32 {
+    (degrees * 10_000_000.0).round() as i32
+}
+
+fn from_e7(value: i32) -> f64 {
+    f64::from(value) / 10_000_000.0
+}
+
 pub fn pow4(z: u8) -> u64 {
     1u64 << (2 * (z as u64))
 }
@@ -147,19 +261,21 @@ pub fn decode_directory(mut data: &[u8]) -> Result<Vec<Entry>> {
 
 pub fn build_header(
     root_length: u64,
+    leaf_length: u64,
     data_length: u64,
     tile_count: u64,
     min_zoom: u8,
     max_zoom: u8,
 ) -> Header {
+    let leaf_offset = HEADER_SIZE as u64 + root_length;
     Header {
         root_offset: HEADER_SIZE as u64,
         root_length,
         metadata_offset: 0,
         metadata_length: 0,
-        leaf_offset: 0,
-        leaf_length: 0,
-        data_offset: HEADER_SIZE as u64 + root_length,
+        leaf_offset: if leaf_length == 0 { 0 } else { leaf_offset },
+        leaf_length,
+        data_offset: leaf_offset + leaf_length,
         data_length,
         n_addressed_tiles: tile_count,
         n_tile_entries: tile_count,
@@ -184,6 +300,7 @@ pub fn build_header(
 pub fn build_header_with_metadata(
     root_length: u64,
     metadata_length: u64,
+    leaf_length: u64,
     data_length: u64,
     tile_count: u64,
     min_zoom: u8,
@@ -198,19 +315,15 @@ pub fn build_header_with_metadata(
     } else {
         root_offset + root_length
     };
-    let data_offset = if metadata_length == 0 {
-        root_offset + root_length
-    } else {
-        metadata_offset + metadata_length
-    };
+    let leaf_offset = root_offset + root_length + metadata_length;
     Header {
         root_offset,
         root_length,
         metadata_offset,
         metadata_length,
-        leaf_offset: 0,
-        leaf_length: 0,
-        data_offset,
+        leaf_offset: if leaf_length == 0 { 0 } else { leaf_offset },
+        leaf_length,
+        data_offset: leaf_offset + leaf_length,
         data_length,
         n_addressed_tiles: tile_count,
         n_tile_entries: tile_count,
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::mbtiles::TileCoord;
+use crate::pmtiles::algo::{tile_id_from_xyz, tile_id_to_xyz, tile_type_from_format};
+use crate::pmtiles::processing::{
+    collect_tile_entries, decode_tile_payload_pmtiles, encode_metadata_json,
+    encode_tile_payload_pmtiles, ensure_pmtiles_path, mbtiles_metadata_to_pmtiles,
+    mbtiles_writer_options, pmtiles_metadata_to_mbtiles, read_directory_section, read_header,
+    read_metadata_json,
+};
+use crate::pmtiles::types::{Entry, Header};
+use crate::pmtiles::writer::PmtilesWriter;
+use crate::tiles::{TileRecord, TileSink, TileSource};
+
+/// Reads tiles from a PMTiles archive, expanding run-length entries.
+pub struct PmtilesSource {
+    file: File,
+    header: Header,
+    metadata: BTreeMap<String, String>,
+    entries: Vec<Entry>,
+}
+
+impl PmtilesSource {
+    pub fn open(path: &Path) -> Result<Self> {
+        ensure_pmtiles_path(path)?;
+        let file = File::open(path)
+            .with_context(|| format!("failed to open input pmtiles: {}", path.display()))?;
+        let header = read_header(&file).context("read header")?;
+        let metadata = pmtiles_metadata_to_mbtiles(&read_metadata_json(&file, &header)?, &header);
+        let root_entries =
+            read_directory_section(&file, &header, header.root_offset, header.root_length)?;
+        let mut entries = Vec::new();
+        collect_tile_entries(&file, &header, &root_entries, &mut entries)?;
+        entries.sort_unstable_by_key(|entry| entry.tile_id);
+        Ok(Self {
+            file,
+            header,
+            metadata,
+            entries,
+        })
+    }
+
+    fn read_entry(&mut self, entry: &Entry) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; entry.length as usize];
+        self.file
+            .seek(SeekFrom::Start(self.header.data_offset + entry.offset))
+            .context("seek tile")?;
+        self.file.read_exact(&mut data).context("read tile data")?;
+        Ok(data)
+    }
+
+    fn tile_record(&self, tile_id: u64, data: Vec<u8>) -> TileRecord {
+        let (zoom, x, y) = tile_id_to_xyz(tile_id);
+        TileRecord {
+            coord: TileCoord { zoom, x, y },
+            data,
+            compression: self.header.tile_compression,
+        }
+    }
+}
+
+impl TileSource for PmtilesSource {
+    fn metadata(&self) -> Result<BTreeMap<String, String>> {
+        Ok(self.metadata.clone())
+    }
+
+    fn for_each_tile(&mut self, visit: &mut dyn FnMut(TileRecord) -> Result<()>) -> Result<()> {
+        for index in 0..self.entries.len() {
+            let entry = self.entries[index].clone();
+            let data = self.read_entry(&entry)?;
+            for i in 0..entry.run_length.max(1) {
+                visit(self.tile_record(entry.tile_id + u64::from(i), data.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_tile(&mut self, coord: TileCoord) -> Result<Option<TileRecord>> {
+        let tile_id = tile_id_from_xyz(coord.zoom, coord.x, coord.y);
+        let index = self
+            .entries
+            .partition_point(|entry| entry.tile_id <= tile_id);
+        let Some(entry) = index.checked_sub(1).map(|i| self.entries[i].clone()) else {
+            return Ok(None);
+        };
+        if tile_id >= entry.tile_id + u64::from(entry.run_length.max(1)) {
+            return Ok(None);
+        }
+        let data = self.read_entry(&entry)?;
+        Ok(Some(self.tile_record(tile_id, data)))
+    }
+}
+
+/// Writes tiles into a new PMTiles archive. The archive's tile compression
+/// follows the `format` metadata: gzip for `pbf`/`mvt`, none otherwise.
+/// Tiles stored differently are recompressed to match.
+pub struct PmtilesSink {
+    output: PathBuf,
+    metadata: BTreeMap<String, String>,
+    writer: Option<PmtilesWriter>,
+    tile_compression: u8,
+}
+
+impl PmtilesSink {
+    pub fn create(path: &Path, metadata: BTreeMap<String, String>) -> Result<Self> {
+        ensure_pmtiles_path(path)?;
+        // MBTiles stores vector tiles gzip-compressed; other formats as is.
+        let tile_compression = match metadata
+            .get("format")
+            .map(|format| tile_type_from_format(format))
+        {
+            Some(1) => 1,
+            _ => 0,
+        };
+        Ok(Self {
+            output: path.to_path_buf(),
+            metadata,
+            writer: Some(PmtilesWriter::new()?),
+            tile_compression,
+        })
+    }
+}
+
+impl TileSink for PmtilesSink {
+    fn write_tile(&mut self, tile: &TileRecord) -> Result<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .context("pmtiles sink already finished")?;
+        let tile_id = tile_id_from_xyz(tile.coord.zoom, tile.coord.x, tile.coord.y);
+        if tile.compression == self.tile_compression {
+            return writer.add_tile(tile_id, &tile.data);
+        }
+        let payload = decode_tile_payload_pmtiles(&tile.data, tile.compression)?;
+        writer.add_tile(
+            tile_id,
+            &encode_tile_payload_pmtiles(&payload, self.tile_compression)?,
+        )
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let writer = self
+            .writer
+            .take()
+            .context("pmtiles sink already finished")?;
+        writer.finish(
+            &self.output,
+            &encode_metadata_json(&mbtiles_metadata_to_pmtiles(&self.metadata), 0)?,
+            mbtiles_writer_options(&self.metadata, self.tile_compression),
+        )
+    }
+}
@@ -1,9 +1,13 @@
 pub mod algo;
+pub mod io;
 pub mod processing;
 pub mod stats;
 pub mod types;
+pub mod writer;
 
 pub use self::algo::*;
+pub use self::io::*;
 pub use self::processing::*;
 pub use self::stats::*;
 pub use self::types::*;
+pub use self::writer::*;
@@ -1,30 +1,49 @@
+use crate::checkpoint::{
+    CHECKPOINT_INTERVAL_TILES, Checkpoint, CheckpointConfig, CheckpointProgress,
+    checkpoint_identity, prune_options_fingerprint, remove_checkpoint,
+};
+use crate::format::TileFormat;
+use crate::mbtiles::processing::{PipelineOptions, StylePrune, TileTransform, TilesetSimplify};
 use crate::mbtiles::{
-    HistogramBucket, InspectOptions, MbtilesReport, MbtilesZoomStats, PruneStats, TileListOptions,
-    TileSort, TopTile, ZoomHistogram, count_vertices, encode_tile_payload, format_property_value,
-    prune_tile_layers, simplify_tile_payload,
+    HistogramBucket, InspectOptions, MAX_TILE_ZOOM, MbtilesReport, MbtilesScheme, MbtilesSink,
+    MbtilesSource, MbtilesZoomStats, PruneOptions, PruneStats, SimplifyOptions, TileListOptions,
+    TileSort, TilesetSimplifyStats, TopTile, VerifyIssue, VerifyIssueKind, VerifyReport,
+    ZoomHistogram, apply_write_pragmas_with_cache, count_vertices, create_mbtiles_output,
+    encode_tile_payload, format_property_value, simplify_tile_payload, validate_vector_tile,
 };
 use crate::pmtiles::{
     LayerAccum, StatAccum,
     algo::{
-        build_header, decode_directory, encode_directory, histogram_bucket_index_pmtiles,
-        splitmix64, tile_id_from_xyz, tile_id_to_xyz,
+        decode_directory, encode_directory, format_from_tile_type, header_bounds, header_center,
+        histogram_bucket_index_pmtiles, parse_mbtiles_bounds, parse_mbtiles_center, pow4,
+        set_header_bounds, splitmix64, tile_id_from_xyz, tile_id_to_xyz, tile_range_bounds,
+        tile_type_from_format,
     },
     build_header_with_metadata, progress_for_phase,
-    types::{Entry, HEADER_SIZE, Header, MAGIC, ProgressTracker, VERSION},
+    types::{
+        Entry, GeoCenter, HEADER_SIZE, Header, LEAF_DIRECTORY_MIN_ENTRIES, MAGIC,
+        MAX_DIRECTORY_DEPTH, PmtilesCopyOptions, ProgressTracker, ROOT_DIRECTORY_MAX_BYTES,
+        VERSION,
+    },
+    writer::{PmtilesWriter, PmtilesWriterOptions},
 };
+use crate::pmtiles::{PmtilesSink, PmtilesSource};
+use crate::tiles::{TileSource, copy_tiles};
 use anyhow::{Context, Result};
 use brotli::{CompressorWriter, Decompressor};
+use crossbeam_channel::{Receiver, Sender, bounded};
 use flate2::Compression;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use mvt_reader::Reader;
-use rusqlite::Connection;
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::cmp::Reverse;
 use std::collections::{BTreeMap, BinaryHeap, HashSet};
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
 
 pub fn include_sample(index: u64, total: u64, sample: Option<&crate::mbtiles::SampleSpec>) -> bool {
     match sample {
@@ -87,8 +106,8 @@ pub fn read_header(mut x: &File) -> Result<Header> {
 
     let mut rest = cursor;
     let clustered = read_u8(&mut rest)?;
-    let internal_compression = read_u8(&mut rest)?;
-    let tile_compression = read_u8(&mut rest)?;
+    let internal_compression = compression_from_spec(read_u8(&mut rest)?);
+    let tile_compression = compression_from_spec(read_u8(&mut rest)?);
     let tile_type = read_u8(&mut rest)?;
     let min_zoom = read_u8(&mut rest)?;
     let max_zoom = read_u8(&mut rest)?;
@@ -128,6 +147,29 @@ pub fn read_header(mut x: &File) -> Result<Header> {
     })
 }
 
+/// Map a PMTiles v3 header compression code (0 unknown, 1 none, 2 gzip,
+/// 3 brotli, 4 zstd) to this crate's numbering. Unknown is read as none;
+/// gzip data is still detected by its magic bytes.
+fn compression_from_spec(code: u8) -> u8 {
+    match code {
+        0 | 1 => 0,
+        2 => 1,
+        3 => 2,
+        4 => 3,
+        other => other,
+    }
+}
+
+fn compression_to_spec(compression: u8) -> u8 {
+    match compression {
+        0 => 1,
+        1 => 2,
+        2 => 3,
+        3 => 4,
+        other => other,
+    }
+}
+
 pub fn write_header(mut file: &File, header: &Header) -> Result<()> {
     let mut buf = Vec::with_capacity(HEADER_SIZE);
     buf.write_all(MAGIC)?;
@@ -155,8 +197,8 @@ pub fn write_header(mut file: &File, header: &Header) -> Result<()> {
     write_u64(header.n_tile_contents, &mut buf)?;
 
     buf.push(header.clustered);
-    buf.push(header.internal_compression);
-    buf.push(header.tile_compression);
+    buf.push(compression_to_spec(header.internal_compression));
+    buf.push(compression_to_spec(header.tile_compression));
     buf.push(header.tile_type);
     buf.push(header.min_zoom);
     buf.push(header.max_zoom);
@@ -178,9 +220,22 @@ pub fn write_header(mut file: &File, header: &Header) -> Result<()> {
     Ok(())
 }
 
-pub fn read_metadata_section(mut file: &File, header: &Header) -> Result<BTreeMap<String, String>> {
+pub fn read_metadata_section(file: &File, header: &Header) -> Result<BTreeMap<String, String>> {
+    let mut metadata = BTreeMap::new();
+    for (key, value) in read_metadata_json(file, header)? {
+        let text = match value {
+            Value::String(text) => text,
+            other => other.to_string(),
+        };
+        metadata.insert(key, text);
+    }
+    Ok(metadata)
+}
+
+/// Read the metadata section as a JSON object, keeping non-string values intact.
+pub fn read_metadata_json(mut file: &File, header: &Header) -> Result<Map<String, Value>> {
     if header.metadata_length == 0 {
-        return Ok(BTreeMap::new());
+        return Ok(Map::new());
     }
     file.seek(SeekFrom::Start(header.metadata_offset))
         .context("seek metadata")?;
@@ -190,17 +245,100 @@ pub fn read_metadata_section(mut file: &File, header: &Header) -> Result<BTreeMa
     let decoded = decode_internal_bytes(data, header.internal_compression)?;
 
     let value: Value = serde_json::from_slice(&decoded).context("parse metadata json")?;
-    let mut metadata = BTreeMap::new();
-    if let Value::Object(map) = value {
-        for (key, value) in map.into_iter() {
-            let text = match value {
-                Value::String(text) => text,
-                other => other.to_string(),
-            };
-            metadata.insert(key, text);
+    match value {
+        Value::Object(map) => Ok(map),
+        _ => Ok(Map::new()),
+    }
+}
+
+/// Encode a metadata object for the metadata section; an empty object
+/// produces no section at all.
+pub fn encode_metadata_json(
+    metadata: &Map<String, Value>,
+    internal_compression: u8,
+) -> Result<Vec<u8>> {
+    if metadata.is_empty() {
+        return Ok(Vec::new());
+    }
+    let json = Value::Object(metadata.clone()).to_string();
+    encode_internal_bytes(json.as_bytes(), internal_compression)
+}
+
+/// MBTiles keys that live in the nested `json` metadata row.
+const MBTILES_JSON_KEYS: [&str; 2] = ["vector_layers", "tilestats"];
+
+/// Map MBTiles metadata rows to PMTiles JSON metadata, lifting the nested
+/// `json` row (`vector_layers`, `tilestats`) to top-level keys.
+pub fn mbtiles_metadata_to_pmtiles(metadata: &BTreeMap<String, String>) -> Map<String, Value> {
+    let mut json = Map::new();
+    for (name, value) in metadata {
+        if name != "json" {
+            json.insert(name.clone(), Value::String(value.clone()));
         }
     }
-    Ok(metadata)
+    if let Some(nested) = metadata.get("json") {
+        match serde_json::from_str::<Value>(nested) {
+            Ok(Value::Object(nested)) => {
+                for (key, value) in nested {
+                    json.entry(key).or_insert(value);
+                }
+            }
+            _ => {
+                json.insert("json".to_string(), Value::String(nested.clone()));
+            }
+        }
+    }
+    json
+}
+
+/// Map a JSON metadata object to MBTiles metadata rows, nesting
+/// `vector_layers`/`tilestats` under `json` and stringifying other values.
+pub fn json_metadata_to_mbtiles(metadata: &Map<String, Value>) -> BTreeMap<String, String> {
+    let mut rows = BTreeMap::new();
+    let mut nested = Map::new();
+    for (key, value) in metadata {
+        if MBTILES_JSON_KEYS.contains(&key.as_str()) {
+            nested.insert(key.clone(), value.clone());
+            continue;
+        }
+        let text = match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        rows.insert(key.clone(), text);
+    }
+    if !nested.is_empty() {
+        rows.insert("json".to_string(), Value::Object(nested).to_string());
+    }
+    rows
+}
+
+/// Map PMTiles JSON metadata to MBTiles metadata rows, filling `format`, zoom
+/// range, bounds and center from the header when the metadata lacks them.
+pub fn pmtiles_metadata_to_mbtiles(
+    metadata: &Map<String, Value>,
+    header: &Header,
+) -> BTreeMap<String, String> {
+    let mut rows = json_metadata_to_mbtiles(metadata);
+    if let Some(format) = format_from_tile_type(header.tile_type) {
+        rows.entry("format".to_string())
+            .or_insert_with(|| format.to_string());
+    }
+    rows.entry("minzoom".to_string())
+        .or_insert_with(|| header.min_zoom.to_string());
+    rows.entry("maxzoom".to_string())
+        .or_insert_with(|| header.max_zoom.to_string());
+    let bounds = header_bounds(header);
+    rows.entry("bounds".to_string()).or_insert_with(|| {
+        format!(
+            "{},{},{},{}",
+            bounds.min_longitude, bounds.min_latitude, bounds.max_longitude, bounds.max_latitude
+        )
+    });
+    let center = header_center(header);
+    rows.entry("center".to_string())
+        .or_insert_with(|| format!("{},{},{}", center.longitude, center.latitude, center.zoom));
+    rows
 }
 
 pub fn decode_internal_bytes(data: Vec<u8>, internal_compression: u8) -> Result<Vec<u8>> {
@@ -234,6 +372,7 @@ pub fn decode_internal_bytes(data: Vec<u8>, internal_compression: u8) -> Result<
                 .context("decode brotli metadata")?;
             Ok(decoded)
         }
+        3 => zstd::decode_all(data.as_slice()).context("decode zstd metadata"),
         other => anyhow::bail!("unsupported PMTiles metadata compression: {other}"),
     }
 }
@@ -258,10 +397,46 @@ pub fn encode_internal_bytes(data: &[u8], internal_compression: u8) -> Result<Ve
             }
             Ok(compressed)
         }
+        3 => zstd::encode_all(data, 0).context("encode zstd internal data"),
         other => anyhow::bail!("unsupported PMTiles internal compression: {other}"),
     }
 }
 
+/// Encode entries as a root directory, splitting them into leaf directories
+/// when the header plus root would not fit in the first 16 KiB.
+/// Returns the encoded (root, leaves) sections.
+pub fn build_directories(
+    entries: &[Entry],
+    internal_compression: u8,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let root_budget = ROOT_DIRECTORY_MAX_BYTES as usize - HEADER_SIZE;
+    let root = encode_internal_bytes(&encode_directory(entries)?, internal_compression)?;
+    if root.len() <= root_budget {
+        return Ok((root, Vec::new()));
+    }
+
+    let mut leaf_size = LEAF_DIRECTORY_MIN_ENTRIES;
+    loop {
+        let mut root_entries = Vec::new();
+        let mut leaves = Vec::new();
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = encode_internal_bytes(&encode_directory(chunk)?, internal_compression)?;
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: u32::try_from(leaf.len()).context("leaf directory exceeds 4 GiB")?,
+                run_length: 0,
+            });
+            leaves.extend_from_slice(&leaf);
+        }
+        let root = encode_internal_bytes(&encode_directory(&root_entries)?, internal_compression)?;
+        if root.len() <= root_budget {
+            return Ok((root, leaves));
+        }
+        leaf_size *= 2;
+    }
+}
+
 pub fn decode_tile_payload_pmtiles(data: &[u8], tile_compression: u8) -> Result<Vec<u8>> {
     if data.starts_with(&[0x1f, 0x8b]) {
         let mut decoder = GzDecoder::new(data);
@@ -282,6 +457,7 @@ pub fn decode_tile_payload_pmtiles(data: &[u8], tile_compression: u8) -> Result<
                 .context("decode brotli tile data")?;
             Ok(decoded)
         }
+        3 => zstd::decode_all(data).context("decode zstd tile data"),
         other => anyhow::bail!("unsupported PMTiles tile compression: {other}"),
     }
 }
@@ -298,6 +474,7 @@ pub fn encode_tile_payload_pmtiles(data: &[u8], tile_compression: u8) -> Result<
             }
             Ok(compressed)
         }
+        3 => zstd::encode_all(data, 0).context("encode zstd tile data"),
         other => anyhow::bail!("unsupported PMTiles tile compression: {other}"),
     }
 }
@@ -474,11 +651,7 @@ pub fn build_histogram_from_entries(
         };
         accum_count += counts[i];
         accum_bytes += bytes[i];
-        let running_avg = if accum_count == 0 {
-            0
-        } else {
-            accum_bytes / accum_count
-        };
+        let running_avg = accum_bytes.checked_div(accum_count).unwrap_or(0);
         let pct_tiles = if total_tiles_used == 0 {
             0.0
         } else {
@@ -624,11 +797,7 @@ pub fn build_zoom_histograms_from_entries(
             };
             accum_count += accum.counts[i];
             accum_bytes += accum.bytes[i];
-            let running_avg = if accum_count == 0 {
-                0
-            } else {
-                accum_bytes / accum_count
-            };
+            let running_avg = accum_bytes.checked_div(accum_count).unwrap_or(0);
             let pct_tiles = if accum.used_tiles == 0 {
                 0.0
             } else {
@@ -747,10 +916,9 @@ pub fn collect_top_tiles_from_entries(
                     let list_options = list_options.expect("list options");
                     if bucket_tiles.len() > list_options.limit {
                         if list_options.sort == TileSort::Size {
-                            bucket_tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+                            bucket_tiles.sort_by_key(|tile| std::cmp::Reverse(tile.bytes));
                         } else {
-                            bucket_tiles
-                                .sort_by(|a, b| (a.zoom, a.x, a.y).cmp(&(b.zoom, b.x, b.y)));
+                            bucket_tiles.sort_by_key(|tile| (tile.zoom, tile.x, tile.y));
                         }
                         bucket_tiles.truncate(list_options.limit);
                     }
@@ -763,7 +931,7 @@ pub fn collect_top_tiles_from_entries(
         .into_iter()
         .map(|Reverse((bytes, zoom, x, y))| TopTile { zoom, x, y, bytes })
         .collect::<Vec<_>>();
-    top_tiles.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+    top_tiles.sort_by_key(|tile| std::cmp::Reverse(tile.bytes));
 
     Ok((top_tiles, bucket_tiles))
 }
@@ -1107,12 +1275,415 @@ pub fn inspect_pmtiles_with_options(
     })
 }
 
+pub fn verify_pmtiles(path: &Path) -> Result<VerifyReport> {
+    ensure_pmtiles_path(path)?;
+    let mut file = File::open(path)
+        .with_context(|| format!("failed to open input pmtiles: {}", path.display()))?;
+    let file_len = file
+        .metadata()
+        .with_context(|| format!("failed to read file size: {}", path.display()))?
+        .len();
+
+    let mut report = VerifyReport {
+        path: path.display().to_string(),
+        format: "pmtiles".to_string(),
+        schema: None,
+        has_metadata: false,
+        tile_count: 0,
+        issues: Vec::new(),
+    };
+    let header = match read_header(&file) {
+        Ok(header) => header,
+        Err(err) => {
+            report.issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Schema,
+                format!("{err:#}"),
+            ));
+            return Ok(report);
+        }
+    };
+    report.has_metadata = header.metadata_length > 0;
+    let sections_ok = verify_header_sections(&header, file_len, &mut report.issues);
+    if !sections_ok {
+        return Ok(report);
+    }
+    if let Err(err) = read_metadata_section(&file, &header) {
+        report.issues.push(VerifyIssue::archive(
+            VerifyIssueKind::Decode,
+            format!("{err:#}"),
+        ));
+    }
+    let root_entries =
+        match read_directory_section(&file, &header, header.root_offset, header.root_length) {
+            Ok(entries) => entries,
+            Err(err) => {
+                report.issues.push(VerifyIssue::archive(
+                    VerifyIssueKind::Decode,
+                    format!("root directory: {err:#}"),
+                ));
+                return Ok(report);
+            }
+        };
+
+    let mut tile_entries = Vec::new();
+    let mut visited_leaves = HashSet::new();
+    collect_verify_entries(
+        &file,
+        &header,
+        &root_entries,
+        0,
+        &mut visited_leaves,
+        &mut tile_entries,
+        &mut report.issues,
+    );
+    verify_tile_entries(&header, &tile_entries, &mut report.issues);
+
+    let max_tile_id = (pow4(MAX_TILE_ZOOM as u8 + 1) - 1) / 3;
+    let mut checked_contents = HashSet::new();
+    for entry in tile_entries.iter() {
+        let run = u64::from(entry.run_length);
+        report.tile_count += run;
+        let last_tile_id = entry.tile_id.saturating_add(run - 1);
+        if last_tile_id >= max_tile_id {
+            report.issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Coordinate,
+                format!(
+                    "tile_id {} exceeds the maximum supported zoom {MAX_TILE_ZOOM}",
+                    last_tile_id
+                ),
+            ));
+            continue;
+        }
+        let (z, x, y) = tile_id_to_xyz(entry.tile_id);
+        let (z, x, y) = (i64::from(z), i64::from(x), i64::from(y));
+        let end = entry.offset.checked_add(u64::from(entry.length));
+        if end.is_none_or(|end| end > header.data_length) {
+            report.issues.push(VerifyIssue::tile(
+                VerifyIssueKind::Directory,
+                z,
+                x,
+                y,
+                format!(
+                    "tile data offset {} + length {} exceeds data_length {}",
+                    entry.offset, entry.length, header.data_length
+                ),
+            ));
+            continue;
+        }
+        if !checked_contents.insert((entry.offset, entry.length)) {
+            continue;
+        }
+        // Only allocate once the entry is known to lie inside the data
+        // section, which `verify_header_sections` bounded by the file size.
+        let mut data = vec![0u8; entry.length as usize];
+        let read = file
+            .seek(SeekFrom::Start(header.data_offset + entry.offset))
+            .context("seek tile data")
+            .and_then(|_| file.read_exact(&mut data).context("read tile data"));
+        if let Err(err) = read {
+            report.issues.push(VerifyIssue::tile(
+                VerifyIssueKind::Decode,
+                z,
+                x,
+                y,
+                format!("{err:#}"),
+            ));
+            continue;
+        }
+        let payload = match decode_tile_payload_pmtiles(&data, header.tile_compression) {
+            Ok(payload) => payload,
+            Err(err) => {
+                report.issues.push(VerifyIssue::tile(
+                    VerifyIssueKind::Decode,
+                    z,
+                    x,
+                    y,
+                    format!("{err:#}"),
+                ));
+                continue;
+            }
+        };
+        if let Err(err) = validate_vector_tile(payload) {
+            report.issues.push(VerifyIssue::tile(
+                VerifyIssueKind::Mvt,
+                z,
+                x,
+                y,
+                format!("{err:#}"),
+            ));
+        }
+    }
+    Ok(report)
+}
+
+fn verify_header_sections(header: &Header, file_len: u64, issues: &mut Vec<VerifyIssue>) -> bool {
+    let mut ok = true;
+    let sections = [
+        ("root directory", header.root_offset, header.root_length),
+        ("metadata", header.metadata_offset, header.metadata_length),
+        ("leaf directories", header.leaf_offset, header.leaf_length),
+        ("tile data", header.data_offset, header.data_length),
+    ];
+    for (name, offset, length) in sections {
+        let end = offset.checked_add(length);
+        if length > 0 && offset < HEADER_SIZE as u64 {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Header,
+                format!("{name} section starts inside the header (offset {offset})"),
+            ));
+            ok = false;
+        }
+        if end.is_none_or(|end| end > file_len) {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Header,
+                format!(
+                    "{name} section (offset {offset}, length {length}) exceeds file size {file_len}"
+                ),
+            ));
+            ok = false;
+        }
+    }
+    if header.root_offset.saturating_add(header.root_length) > ROOT_DIRECTORY_MAX_BYTES {
+        issues.push(VerifyIssue::archive(
+            VerifyIssueKind::Header,
+            format!(
+                "header and root directory end at byte {}, beyond the first {ROOT_DIRECTORY_MAX_BYTES} bytes",
+                header.root_offset.saturating_add(header.root_length)
+            ),
+        ));
+    }
+    if header.clustered > 1 {
+        issues.push(VerifyIssue::archive(
+            VerifyIssueKind::Header,
+            format!("invalid clustered flag {}", header.clustered),
+        ));
+    }
+    if header.min_zoom > header.max_zoom {
+        issues.push(VerifyIssue::archive(
+            VerifyIssueKind::Header,
+            format!(
+                "min_zoom {} is greater than max_zoom {}",
+                header.min_zoom, header.max_zoom
+            ),
+        ));
+    }
+    ok
+}
+
+fn collect_verify_entries(
+    file: &File,
+    header: &Header,
+    entries: &[Entry],
+    depth: usize,
+    visited_leaves: &mut HashSet<u64>,
+    tile_entries: &mut Vec<Entry>,
+    issues: &mut Vec<VerifyIssue>,
+) {
+    let directory = if depth == 0 { "root" } else { "leaf" };
+    for pair in entries.windows(2) {
+        if pair[1].tile_id <= pair[0].tile_id {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Directory,
+                format!(
+                    "{directory} directory tile_id {} does not increase after {}",
+                    pair[1].tile_id, pair[0].tile_id
+                ),
+            ));
+        }
+    }
+    for entry in entries {
+        if entry.run_length > 0 {
+            tile_entries.push(entry.clone());
+            continue;
+        }
+        if depth >= MAX_DIRECTORY_DEPTH {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Directory,
+                format!("leaf directories nested deeper than {MAX_DIRECTORY_DEPTH} levels"),
+            ));
+            return;
+        }
+        if entry.length == 0 {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Directory,
+                format!("empty leaf directory entry at tile_id {}", entry.tile_id),
+            ));
+            continue;
+        }
+        let end = entry.offset.checked_add(u64::from(entry.length));
+        if end.is_none_or(|end| end > header.leaf_length) {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Directory,
+                format!(
+                    "leaf directory offset {} + length {} exceeds leaf_length {}",
+                    entry.offset, entry.length, header.leaf_length
+                ),
+            ));
+            continue;
+        }
+        let leaf_offset = header.leaf_offset + entry.offset;
+        if !visited_leaves.insert(leaf_offset) {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Directory,
+                format!("leaf directory at offset {leaf_offset} is referenced more than once"),
+            ));
+            continue;
+        }
+        match read_directory_section(file, header, leaf_offset, entry.length as u64) {
+            Ok(leaf_entries) => {
+                if leaf_entries
+                    .first()
+                    .is_some_and(|first| first.tile_id < entry.tile_id)
+                {
+                    issues.push(VerifyIssue::archive(
+                        VerifyIssueKind::Directory,
+                        format!(
+                            "leaf directory at offset {leaf_offset} starts before its root tile_id {}",
+                            entry.tile_id
+                        ),
+                    ));
+                }
+                collect_verify_entries(
+                    file,
+                    header,
+                    &leaf_entries,
+                    depth + 1,
+                    visited_leaves,
+                    tile_entries,
+                    issues,
+                );
+            }
+            Err(err) => issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Decode,
+                format!("leaf directory at offset {leaf_offset}: {err:#}"),
+            )),
+        }
+    }
+}
+
+fn verify_tile_entries(header: &Header, entries: &[Entry], issues: &mut Vec<VerifyIssue>) {
+    for pair in entries.windows(2) {
+        let prev_end = pair[0]
+            .tile_id
+            .saturating_add(u64::from(pair[0].run_length));
+        if pair[1].tile_id < prev_end {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Directory,
+                format!(
+                    "tile_id {} overlaps the run starting at {} (run_length {})",
+                    pair[1].tile_id, pair[0].tile_id, pair[0].run_length
+                ),
+            ));
+        }
+    }
+
+    let addressed = entries
+        .iter()
+        .map(|entry| u64::from(entry.run_length))
+        .sum::<u64>();
+    let contents = entries
+        .iter()
+        .map(|entry| (entry.offset, entry.length))
+        .collect::<HashSet<_>>()
+        .len() as u64;
+    let counters = [
+        ("n_addressed_tiles", header.n_addressed_tiles, addressed),
+        (
+            "n_tile_entries",
+            header.n_tile_entries,
+            entries.len() as u64,
+        ),
+        ("n_tile_contents", header.n_tile_contents, contents),
+    ];
+    for (name, declared, found) in counters {
+        // Zero means "unknown" in the PMTiles v3 header.
+        if declared != 0 && declared != found {
+            issues.push(VerifyIssue::archive(
+                VerifyIssueKind::Header,
+                format!("{name} is {declared} but {found} were found"),
+            ));
+        }
+    }
+
+    let max_tile_id = (pow4(MAX_TILE_ZOOM as u8 + 1) - 1) / 3;
+    let zooms = entries
+        .iter()
+        .map(|entry| {
+            let last = entry
+                .tile_id
+                .saturating_add(u64::from(entry.run_length) - 1);
+            (entry.tile_id, last)
+        })
+        .filter(|(_, last)| *last < max_tile_id)
+        .flat_map(|(first, last)| [tile_id_to_xyz(first).0, tile_id_to_xyz(last).0])
+        .fold(None, |acc: Option<(u8, u8)>, z| {
+            Some(acc.map_or((z, z), |(min, max)| (min.min(z), max.max(z))))
+        });
+    if let Some((min_zoom, max_zoom)) = zooms
+        && (header.min_zoom != min_zoom || header.max_zoom != max_zoom)
+    {
+        issues.push(VerifyIssue::archive(
+            VerifyIssueKind::Header,
+            format!(
+                "zoom range is {}..={} but tiles span {min_zoom}..={max_zoom}",
+                header.min_zoom, header.max_zoom
+            ),
+        ));
+    }
+
+    if header.clustered == 1 {
+        let mut next_offset = 0u64;
+        for entry in entries {
+            if entry.offset == next_offset {
+                next_offset += u64::from(entry.length);
+            } else if entry.offset > next_offset {
+                issues.push(VerifyIssue::archive(
+                    VerifyIssueKind::Header,
+                    format!(
+                        "clustered flag is set but tile_id {} is stored at offset {} (expected {})",
+                        entry.tile_id, entry.offset, next_offset
+                    ),
+                ));
+                break;
+            }
+        }
+    }
+}
+
 pub fn prune_pmtiles_layer_only(
     input: &Path,
     output: &Path,
     style: &crate::style::MapboxStyle,
     apply_filters: bool,
     keep_unknown_filters: bool,
+) -> Result<PruneStats> {
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    prune_pmtiles_with_checkpoint(
+        input,
+        output,
+        style,
+        apply_filters,
+        PruneOptions {
+            threads,
+            readers: threads,
+            keep_unknown_filters,
+            ..PruneOptions::default()
+        },
+        None,
+    )
+}
+
+/// Prune a PMTiles archive with parallel readers and workers.
+/// Output is written in tile_id order regardless of thread counts.
+pub fn prune_pmtiles_with_checkpoint(
+    input: &Path,
+    output: &Path,
+    style: &crate::style::MapboxStyle,
+    apply_filters: bool,
+    options: PruneOptions,
+    checkpoint: Option<&CheckpointConfig>,
 ) -> Result<PruneStats> {
     ensure_pmtiles_path(input)?;
     ensure_pmtiles_path(output)?;
@@ -1123,112 +1694,499 @@ pub fn prune_pmtiles_layer_only(
     let root_entries =
         read_directory_section(&file, &header, header.root_offset, header.root_length)?;
 
-    let metadata = read_metadata_section(&file, &header)?;
-    let keep_layers = style.source_layers();
-    let mut stats = PruneStats::default();
-    let mut tiles: Vec<(u64, Vec<u8>)> = Vec::new();
-    let mut min_zoom = u8::MAX;
-    let mut max_zoom = u8::MIN;
+    let metadata = read_metadata_json(&file, &header)?;
 
-    let mut stack = vec![root_entries];
-    let mut file = file;
-    while let Some(entries) = stack.pop() {
-        for entry in entries {
-            if entry.run_length == 0 {
-                if entry.length == 0 {
-                    continue;
+    let partial_path = {
+        let mut name = output.as_os_str().to_owned();
+        name.push(".partial");
+        std::path::PathBuf::from(name)
+    };
+    let mut state = match checkpoint {
+        Some(config) => {
+            let mut identity = checkpoint_identity(
+                input,
+                TileFormat::Pmtiles,
+                output,
+                TileFormat::Pmtiles,
+                &config.style_hash,
+                prune_options_fingerprint(
+                    apply_filters,
+                    options.keep_unknown_filters,
+                    options.drop_empty_tiles,
+                    options.prune_attributes,
+                    options.keep_languages.as_deref(),
+                    options.rules.as_ref(),
+                ),
+            )?;
+            identity.output.temporary_path = Some(partial_path.display().to_string());
+            let resumed = if config.resume {
+                Checkpoint::load(&config.path)?
+            } else {
+                None
+            };
+            let state = match resumed {
+                Some(state) => {
+                    state.ensure_matches(&identity)?;
+                    if !matches!(state.progress, CheckpointProgress::TileId { .. }) {
+                        anyhow::bail!("checkpoint does not contain PMTiles tile_id progress");
+                    }
+                    state
                 }
-                let leaf_offset = header.leaf_offset + entry.offset;
-                let leaf_entries =
-                    read_directory_section(&file, &header, leaf_offset, entry.length as u64)?;
-                stack.push(leaf_entries);
-                continue;
+                None => Checkpoint::new(
+                    identity,
+                    CheckpointProgress::TileId {
+                        committed: None,
+                        data_bytes: 0,
+                    },
+                ),
+            };
+            Some((config, state))
+        }
+        None => None,
+    };
+    let (mut records, committed) = match state.as_ref() {
+        Some((
+            _,
+            Checkpoint {
+                progress:
+                    CheckpointProgress::TileId {
+                        committed,
+                        data_bytes,
+                    },
+                ..
+            },
+        )) => {
+            let records = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(committed.is_none())
+                .open(&partial_path)
+                .with_context(|| {
+                    format!("failed to open temporary tiles: {}", partial_path.display())
+                })?;
+            let current_len = records
+                .metadata()
+                .context("read temporary tiles size")?
+                .len();
+            if current_len < *data_bytes {
+                anyhow::bail!(
+                    "temporary tiles file is shorter than the checkpoint: {}",
+                    partial_path.display()
+                );
             }
-            let data_offset = header.data_offset + entry.offset;
-            let mut data = vec![0u8; entry.length as usize];
-            file.seek(SeekFrom::Start(data_offset))
-                .context("seek tile")?;
-            file.read_exact(&mut data).context("read tile data")?;
-            let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
-            let run = entry.run_length.max(1);
-            for idx in 0..run {
-                let tile_id = entry.tile_id + idx as u64;
-                let (z, _x, _y) = tile_id_to_xyz(tile_id);
-                min_zoom = min_zoom.min(z);
-                max_zoom = max_zoom.max(z);
-                let encoded = prune_tile_layers(
-                    &payload,
-                    z,
-                    style,
-                    &keep_layers,
-                    apply_filters,
-                    keep_unknown_filters,
-                    &mut stats,
-                )?;
-                let tile_data =
-                    encode_tile_payload_pmtiles(&encoded.bytes, header.tile_compression)?;
-                tiles.push((tile_id, tile_data));
+            records
+                .set_len(*data_bytes)
+                .context("truncate temporary tiles")?;
+            (records, *committed)
+        }
+        _ => (
+            tempfile::tempfile().context("create temporary tiles")?,
+            None,
+        ),
+    };
+    records
+        .seek(SeekFrom::End(0))
+        .context("seek temporary tiles")?;
+    let mut writer = std::io::BufWriter::new(records);
+    let mut since_checkpoint = 0u64;
+
+    let mut tile_entries = Vec::new();
+    collect_tile_entries(&file, &header, &root_entries, &mut tile_entries)?;
+    drop(file);
+    let pending = pending_entries(&header, tile_entries, committed);
+    let stats = run_pmtiles_tile_pipeline(
+        input,
+        pending,
+        StylePrune::new(style, apply_filters, &options),
+        PipelineOptions::from(&options),
+        header.tile_compression,
+        header.tile_compression,
+        |tile_id, tile_data| {
+            write_tile_record(&mut writer, tile_id, tile_data)?;
+            if let Some((config, state)) = state.as_mut() {
+                since_checkpoint += 1;
+                if since_checkpoint >= CHECKPOINT_INTERVAL_TILES {
+                    writer.flush().context("flush temporary tiles")?;
+                    writer
+                        .get_ref()
+                        .sync_data()
+                        .context("sync temporary tiles")?;
+                    let data_bytes = writer
+                        .get_mut()
+                        .stream_position()
+                        .context("read temporary tiles position")?;
+                    state.progress = CheckpointProgress::TileId {
+                        committed: Some(tile_id),
+                        data_bytes,
+                    };
+                    state.save(&config.path)?;
+                    since_checkpoint = 0;
+                }
+            }
+            Ok(())
+        },
+    )?;
+    writer.flush().context("flush temporary tiles")?;
+    let records = writer
+        .into_inner()
+        .map_err(|err| anyhow::anyhow!("flush temporary tiles: {}", err.error()))?;
+    // The tile records double as the writer's spill, so tile data is only
+    // written to disk once before the final archive.
+    let mut scan = records.try_clone().context("reopen temporary tiles")?;
+    let mut pmtiles = PmtilesWriter::from_spill(records)?;
+    for_each_tile_record(&mut scan, |tile_id, offset, data| {
+        pmtiles.add_spilled_tile(tile_id, offset, data)
+    })?;
+    drop(scan);
+
+    let metadata_bytes = encode_metadata_json(&metadata, header.internal_compression)?;
+    pmtiles.finish(
+        output,
+        &metadata_bytes,
+        PmtilesWriterOptions {
+            internal_compression: header.internal_compression,
+            tile_compression: header.tile_compression,
+            tile_type: header.tile_type,
+            min_zoom: None,
+            max_zoom: None,
+            bounds: Some(header_bounds(&header)),
+            center: Some(header_center(&header)),
+        },
+    )?;
+
+    if let Some((config, _)) = state {
+        remove_checkpoint(&config.path)?;
+        match std::fs::remove_file(&partial_path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!(
+                        "failed to remove temporary tiles: {}",
+                        partial_path.display()
+                    )
+                });
             }
         }
     }
 
-    tiles.sort_by(|a, b| a.0.cmp(&b.0));
-    let mut entries = Vec::with_capacity(tiles.len());
-    let mut data_section = Vec::new();
-    for (tile_id, data) in tiles.iter() {
-        let offset = data_section.len() as u64;
-        let length = data.len() as u32;
-        data_section.extend_from_slice(data);
-        entries.push(Entry {
-            tile_id: *tile_id,
-            offset,
-            length,
-            run_length: 1,
-        });
+    Ok(stats)
+}
+
+/// Run `transform` over `pending` entries on parallel readers and workers and
+/// hand each kept tile to `sink` in tile_id order, re-encoded with
+/// `output_tile_compression`.
+fn run_pmtiles_tile_pipeline<T: TileTransform>(
+    input: &Path,
+    pending: Vec<PendingEntry>,
+    transform: T,
+    options: PipelineOptions,
+    tile_compression: u8,
+    output_tile_compression: u8,
+    mut sink: impl FnMut(u64, &[u8]) -> Result<()>,
+) -> Result<T::Stats> {
+    let pending = Arc::new(pending);
+    let worker_count = options.threads.max(1);
+    let reader_count = options.readers.max(1);
+    let queue_capacity = options.io_batch.max(1) as usize;
+    let window = Arc::new(DispatchWindow::new(queue_capacity * reader_count * 2));
+    let _close_window = CloseWindowOnDrop(window.clone());
+
+    let (tx_in, rx_in): (Sender<PmtilesTileInput>, Receiver<PmtilesTileInput>) =
+        bounded(queue_capacity);
+    // Workers and readers report failures through `tx_out` so the writer
+    // stops instead of waiting for a sequence number that never arrives.
+    let (tx_out, rx_out): (
+        Sender<Result<PmtilesTileOutput>>,
+        Receiver<Result<PmtilesTileOutput>>,
+    ) = bounded(queue_capacity);
+
+    let mut worker_handles = Vec::with_capacity(worker_count);
+    for _ in 0..worker_count {
+        let rx_in = rx_in.clone();
+        let tx_out = tx_out.clone();
+        let transform = transform.clone();
+        let drop_empty_tiles = options.drop_empty_tiles;
+        worker_handles.push(thread::spawn(move || -> T::Stats {
+            let mut stats = T::Stats::default();
+            while let Ok(entry) = rx_in.recv() {
+                let output = (|| -> Result<PmtilesTileOutput> {
+                    let payload = decode_tile_payload_pmtiles(&entry.data, tile_compression)
+                        .with_context(|| format!("decode tile {}", entry.first_tile_id))?;
+                    let mut tiles = Vec::new();
+                    for tile_id in entry.first_tile_id..entry.end_tile_id {
+                        let (z, _x, _y) = tile_id_to_xyz(tile_id);
+                        let encoded = transform.transform(&payload, z, &mut stats)?;
+                        if encoded.empty && drop_empty_tiles {
+                            continue;
+                        }
+                        let tile_data =
+                            encode_tile_payload_pmtiles(&encoded.bytes, output_tile_compression)?;
+                        tiles.push((tile_id, tile_data));
+                    }
+                    Ok(PmtilesTileOutput {
+                        seq: entry.seq,
+                        tiles,
+                    })
+                })();
+                let failed = output.is_err();
+                if tx_out.send(output).is_err() || failed {
+                    break;
+                }
+            }
+            stats
+        }));
+    }
+    drop(rx_in);
+
+    // Readers take `io_batch`-sized entry ranges round-robin; the window keeps
+    // them close to the writer so out-of-order results stay bounded.
+    let chunk_count = pending.len().div_ceil(queue_capacity);
+    let mut reader_handles = Vec::with_capacity(reader_count);
+    for reader_index in 0..reader_count.min(chunk_count) {
+        let tx_in = tx_in.clone();
+        let tx_out = tx_out.clone();
+        let pending = pending.clone();
+        let window = window.clone();
+        let input_path = input.to_path_buf();
+        reader_handles.push(thread::spawn(move || {
+            let result = (|| -> Result<()> {
+                let mut file = File::open(&input_path).with_context(|| {
+                    format!("failed to open input pmtiles: {}", input_path.display())
+                })?;
+                for chunk in (reader_index..chunk_count).step_by(reader_count) {
+                    let start = chunk * queue_capacity;
+                    let end = (start + queue_capacity).min(pending.len());
+                    for seq in start..end {
+                        if !window.wait_for(seq) {
+                            return Ok(());
+                        }
+                        let entry = &pending[seq];
+                        let mut data = vec![0u8; entry.length as usize];
+                        file.seek(SeekFrom::Start(entry.offset))
+                            .context("seek tile")?;
+                        file.read_exact(&mut data).context("read tile data")?;
+                        let input = PmtilesTileInput {
+                            seq,
+                            first_tile_id: entry.first_tile_id,
+                            end_tile_id: entry.end_tile_id,
+                            data,
+                        };
+                        if tx_in.send(input).is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+                Ok(())
+            })();
+            if let Err(err) = result {
+                let _ = tx_out.send(Err(err));
+            }
+        }));
+    }
+    drop(tx_in);
+    drop(tx_out);
+
+    let mut next_seq = 0usize;
+    let written = (|| -> Result<()> {
+        let mut buffered = BTreeMap::new();
+        for output in rx_out.iter() {
+            let output = output?;
+            buffered.insert(output.seq, output.tiles);
+            while let Some(tiles) = buffered.remove(&next_seq) {
+                next_seq += 1;
+                for (tile_id, tile_data) in tiles {
+                    sink(tile_id, &tile_data)?;
+                }
+            }
+            window.advance(next_seq);
+        }
+        Ok(())
+    })();
+    // On failure this releases readers waiting for the window, and dropping
+    // the receiver stops workers blocked on sending, so every thread exits.
+    window.close();
+    drop(rx_out);
+
+    let mut panicked = false;
+    for handle in reader_handles {
+        panicked |= handle.join().is_err();
+    }
+    let mut stats = T::Stats::default();
+    for handle in worker_handles {
+        match handle.join() {
+            Ok(worker_stats) => T::merge(&mut stats, worker_stats),
+            Err(_) => panicked = true,
+        }
+    }
+    written?;
+    if panicked {
+        anyhow::bail!("tile pipeline thread panicked");
+    }
+    if next_seq != pending.len() {
+        anyhow::bail!(
+            "tile pipeline stopped after {next_seq} of {} entries",
+            pending.len()
+        );
     }
+    Ok(stats)
+}
 
-    let dir_bytes = encode_directory(&entries)?;
-    let dir_section = encode_internal_bytes(&dir_bytes, header.internal_compression)?;
-    let metadata_bytes = if metadata.is_empty() {
-        Vec::new()
-    } else {
-        let mut map = serde_json::Map::new();
-        for (key, value) in metadata.into_iter() {
-            map.insert(key, Value::String(value));
+/// Directory entries still to be pruned, skipping tiles up to `committed`.
+fn pending_entries(
+    header: &Header,
+    entries: Vec<Entry>,
+    committed: Option<u64>,
+) -> Vec<PendingEntry> {
+    entries
+        .into_iter()
+        .filter_map(|entry| {
+            let end_tile_id = entry.tile_id + u64::from(entry.run_length);
+            let first_tile_id = match committed {
+                Some(committed) if committed >= entry.tile_id => committed + 1,
+                _ => entry.tile_id,
+            };
+            (first_tile_id < end_tile_id).then_some(PendingEntry {
+                first_tile_id,
+                end_tile_id,
+                offset: header.data_offset + entry.offset,
+                length: entry.length,
+            })
+        })
+        .collect()
+}
+
+/// Tile-id span of one directory entry still to be pruned.
+struct PendingEntry {
+    first_tile_id: u64,
+    end_tile_id: u64,
+    offset: u64,
+    length: u32,
+}
+
+struct PmtilesTileInput {
+    seq: usize,
+    first_tile_id: u64,
+    end_tile_id: u64,
+    data: Vec<u8>,
+}
+
+struct PmtilesTileOutput {
+    seq: usize,
+    tiles: Vec<(u64, Vec<u8>)>,
+}
+
+/// Lets readers dispatch entry `seq` only while it is within `size` entries
+/// of the next one the writer needs.
+struct DispatchWindow {
+    state: Mutex<(usize, bool)>,
+    ready: Condvar,
+    size: usize,
+}
+
+impl DispatchWindow {
+    fn new(size: usize) -> Self {
+        Self {
+            state: Mutex::new((0, false)),
+            ready: Condvar::new(),
+            size,
         }
-        let json = Value::Object(map).to_string();
-        encode_internal_bytes(json.as_bytes(), header.internal_compression)?
-    };
-    let header = build_header_with_metadata(
-        dir_section.len() as u64,
-        metadata_bytes.len() as u64,
-        data_section.len() as u64,
-        entries.len() as u64,
-        if min_zoom == u8::MAX { 0 } else { min_zoom },
-        if max_zoom == u8::MIN { 0 } else { max_zoom },
-        header.internal_compression,
-        header.tile_compression,
-        header.tile_type,
-    );
+    }
 
-    let mut file = File::create(output)
-        .with_context(|| format!("failed to create output pmtiles: {}", output.display()))?;
-    write_header(&file, &header).context("write header")?;
-    file.seek(SeekFrom::Start(header.root_offset))
-        .context("seek root directory")?;
-    file.write_all(&dir_section)
-        .context("write root directory")?;
+    /// Block until `seq` may be dispatched; false once the window is closed.
+    fn wait_for(&self, seq: usize) -> bool {
+        let mut state = self.state.lock().expect("dispatch window lock");
+        while !state.1 && seq >= state.0 + self.size {
+            state = self.ready.wait(state).expect("dispatch window lock");
+        }
+        !state.1
+    }
 
-    if header.metadata_length > 0 {
-        file.seek(SeekFrom::Start(header.metadata_offset))
-            .context("seek metadata")?;
-        file.write_all(&metadata_bytes).context("write metadata")?;
+    fn advance(&self, next_seq: usize) {
+        let mut state = self.state.lock().expect("dispatch window lock");
+        state.0 = next_seq;
+        self.ready.notify_all();
     }
-    file.seek(SeekFrom::Start(header.data_offset))
-        .context("seek data")?;
-    file.write_all(&data_section).context("write data")?;
 
-    Ok(stats)
+    fn close(&self) {
+        let mut state = self.state.lock().expect("dispatch window lock");
+        state.1 = true;
+        self.ready.notify_all();
+    }
+}
+
+struct CloseWindowOnDrop(Arc<DispatchWindow>);
+
+impl Drop for CloseWindowOnDrop {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// Flatten the directory tree into tile entries in tile_id order.
+pub(crate) fn collect_tile_entries(
+    file: &File,
+    header: &Header,
+    entries: &[Entry],
+    tile_entries: &mut Vec<Entry>,
+) -> Result<()> {
+    for entry in entries {
+        if entry.run_length > 0 {
+            tile_entries.push(entry.clone());
+            continue;
+        }
+        if entry.length == 0 {
+            continue;
+        }
+        let leaf_offset = header.leaf_offset + entry.offset;
+        let leaf_entries = read_directory_section(file, header, leaf_offset, entry.length as u64)?;
+        collect_tile_entries(file, header, &leaf_entries, tile_entries)?;
+    }
+    Ok(())
+}
+
+fn write_tile_record(writer: &mut impl Write, tile_id: u64, data: &[u8]) -> Result<()> {
+    let length = u32::try_from(data.len()).context("tile data exceeds 4 GiB")?;
+    writer
+        .write_all(&tile_id.to_le_bytes())
+        .context("write temporary tile")?;
+    writer
+        .write_all(&length.to_le_bytes())
+        .context("write temporary tile")?;
+    writer.write_all(data).context("write temporary tile")?;
+    Ok(())
+}
+
+/// Visit the `(tile_id, data offset, data)` of each record written by
+/// `write_tile_record`.
+fn for_each_tile_record(
+    file: &mut File,
+    mut visit: impl FnMut(u64, u64, &[u8]) -> Result<()>,
+) -> Result<()> {
+    file.seek(SeekFrom::Start(0))
+        .context("seek temporary tiles")?;
+    let mut reader = std::io::BufReader::new(file);
+    let mut prefix = [0u8; 12];
+    let mut data = Vec::new();
+    let mut offset = 0u64;
+    loop {
+        match reader.read_exact(&mut prefix) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err).context("read temporary tile"),
+        }
+        let tile_id = u64::from_le_bytes(prefix[0..8].try_into().expect("tile id bytes"));
+        let length = u32::from_le_bytes(prefix[8..12].try_into().expect("length bytes"));
+        data.resize(length as usize, 0);
+        reader
+            .read_exact(&mut data)
+            .context("read temporary tile")?;
+        offset += prefix.len() as u64;
+        visit(tile_id, offset, &data)?;
+        offset += u64::from(length);
+    }
+    Ok(())
 }
 
 pub fn simplify_pmtiles_tile(
@@ -1237,6 +2195,7 @@ pub fn simplify_pmtiles_tile(
     coord: crate::mbtiles::TileCoord,
     layers: &[String],
     tolerance: Option<f64>,
+    method: crate::mbtiles::SimplifyMethod,
 ) -> Result<crate::mbtiles::SimplifyStats> {
     ensure_pmtiles_path(input)?;
     ensure_pmtiles_path(output)?;
@@ -1246,7 +2205,7 @@ pub fn simplify_pmtiles_tile(
     let header = read_header(&file).context("read header")?;
     let root_entries =
         read_directory_section(&file, &header, header.root_offset, header.root_length)?;
-    let metadata = read_metadata_section(&file, &header)?;
+    let metadata = read_metadata_json(&file, &header)?;
 
     let target_id = tile_id_from_xyz(coord.zoom, coord.x, coord.y);
     let mut data: Option<Vec<u8>> = None;
@@ -1291,7 +2250,7 @@ pub fn simplify_pmtiles_tile(
 
     let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
     let keep_layers: HashSet<String> = layers.iter().cloned().collect();
-    let (filtered, stats) = simplify_tile_payload(&payload, &keep_layers, tolerance)?;
+    let (filtered, stats) = simplify_tile_payload(&payload, &keep_layers, tolerance, method)?;
     let tile_data = encode_tile_payload_pmtiles(&filtered, header.tile_compression)?;
 
     let entry = Entry {
@@ -1302,19 +2261,11 @@ pub fn simplify_pmtiles_tile(
     };
     let dir_bytes = encode_directory(&[entry])?;
     let dir_section = encode_internal_bytes(&dir_bytes, header.internal_compression)?;
-    let metadata_bytes = if metadata.is_empty() {
-        Vec::new()
-    } else {
-        let mut map = serde_json::Map::new();
-        for (key, value) in metadata.into_iter() {
-            map.insert(key, Value::String(value));
-        }
-        let json = Value::Object(map).to_string();
-        encode_internal_bytes(json.as_bytes(), header.internal_compression)?
-    };
-    let header = build_header_with_metadata(
+    let metadata_bytes = encode_metadata_json(&metadata, header.internal_compression)?;
+    let mut header = build_header_with_metadata(
         dir_section.len() as u64,
         metadata_bytes.len() as u64,
+        0,
         tile_data.len() as u64,
         1,
         coord.zoom,
@@ -1323,6 +2274,16 @@ pub fn simplify_pmtiles_tile(
         header.tile_compression,
         header.tile_type,
     );
+    let bounds = tile_range_bounds(coord.zoom, coord.x, coord.y, coord.x, coord.y);
+    set_header_bounds(
+        &mut header,
+        bounds,
+        GeoCenter {
+            longitude: (bounds.min_longitude + bounds.max_longitude) / 2.0,
+            latitude: (bounds.min_latitude + bounds.max_latitude) / 2.0,
+            zoom: coord.zoom,
+        },
+    );
 
     let file = File::create(output)
         .with_context(|| format!("failed to create output pmtiles: {}", output.display()))?;
@@ -1347,139 +2308,222 @@ pub fn simplify_pmtiles_tile(
     Ok(stats)
 }
 
-pub fn mbtiles_to_pmtiles(input: &Path, output: &Path) -> Result<()> {
-    ensure_mbtiles_path(input)?;
+/// Simplify every tile of a PMTiles archive with per-layer/zoom tolerances on
+/// the same reader/worker/writer pipeline as `optimize`.
+pub fn simplify_pmtiles(
+    input: &Path,
+    output: &Path,
+    options: SimplifyOptions,
+) -> Result<TilesetSimplifyStats> {
+    ensure_pmtiles_path(input)?;
     ensure_pmtiles_path(output)?;
 
-    let conn = Connection::open(input)
-        .with_context(|| format!("failed to open input mbtiles: {}", input.display()))?;
+    let file = File::open(input)
+        .with_context(|| format!("failed to open input pmtiles: {}", input.display()))?;
+    let header = read_header(&file).context("read header")?;
+    let root_entries =
+        read_directory_section(&file, &header, header.root_offset, header.root_length)?;
+    let metadata = read_metadata_json(&file, &header)?;
+    let mut tile_entries = Vec::new();
+    collect_tile_entries(&file, &header, &root_entries, &mut tile_entries)?;
+    drop(file);
+
+    let mut writer = PmtilesWriter::new()?;
+    let pipeline = PipelineOptions::from(&options);
+    let stats = run_pmtiles_tile_pipeline(
+        input,
+        pending_entries(&header, tile_entries, None),
+        TilesetSimplify(options),
+        pipeline,
+        header.tile_compression,
+        header.tile_compression,
+        |tile_id, tile_data| writer.add_tile(tile_id, tile_data),
+    )?;
+    writer.finish(
+        output,
+        &encode_metadata_json(&metadata, header.internal_compression)?,
+        PmtilesWriterOptions {
+            internal_compression: header.internal_compression,
+            tile_compression: header.tile_compression,
+            tile_type: header.tile_type,
+            min_zoom: None,
+            max_zoom: None,
+            bounds: Some(header_bounds(&header)),
+            center: Some(header_center(&header)),
+        },
+    )?;
+    Ok(stats)
+}
 
-    let mut stmt = conn
-        .prepare(
-            "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles ORDER BY zoom_level, tile_column, tile_row",
-        )
-        .context("prepare tiles")?;
-    let mut rows = stmt.query([]).context("query tiles")?;
-
-    let mut tiles = Vec::new();
-    let mut min_zoom = u8::MAX;
-    let mut max_zoom = u8::MIN;
-    while let Some(row) = rows.next().context("read tile row")? {
-        let z: u8 = row.get::<_, u8>(0)?;
-        let x: u32 = row.get::<_, u32>(1)?;
-        let y: u32 = row.get::<_, u32>(2)?;
-        let data: Vec<u8> = row.get::<_, Vec<u8>>(3)?;
-        min_zoom = min_zoom.min(z);
-        max_zoom = max_zoom.max(z);
-        let tile_id = tile_id_from_xyz(z, x, y);
-        tiles.push((tile_id, data));
-    }
-
-    tiles.sort_by(|a, b| a.0.cmp(&b.0));
-
-    let mut entries = Vec::with_capacity(tiles.len());
-    let mut data_section = Vec::new();
-    for (tile_id, data) in tiles.iter() {
-        let offset = data_section.len() as u64;
-        let length = data.len() as u32;
-        data_section.extend_from_slice(data);
-        entries.push(Entry {
-            tile_id: *tile_id,
-            offset,
-            length,
-            run_length: 1,
-        });
-    }
+pub fn mbtiles_to_pmtiles(input: &Path, output: &Path) -> Result<()> {
+    mbtiles_to_pmtiles_with_scheme(input, output, MbtilesScheme::Tms)
+}
 
-    let dir_bytes = encode_directory(&entries)?;
-    let mut header = build_header(
-        dir_bytes.len() as u64,
-        data_section.len() as u64,
-        entries.len() as u64,
-        if min_zoom == u8::MAX { 0 } else { min_zoom },
-        if max_zoom == u8::MIN { 0 } else { max_zoom },
-    );
+/// Convert MBTiles to PMTiles, reading `tile_row` in the given scheme.
+pub fn mbtiles_to_pmtiles_with_scheme(
+    input: &Path,
+    output: &Path,
+    scheme: MbtilesScheme,
+) -> Result<()> {
+    ensure_mbtiles_path(input)?;
+    ensure_pmtiles_path(output)?;
 
-    if let Some((_, first_data)) = tiles.first() {
-        if first_data.starts_with(&[0x1f, 0x8b]) {
-            header.tile_compression = 1;
-        } else {
-            header.tile_compression = 0;
-        }
-    }
-    header.internal_compression = 0;
+    let mut source = MbtilesSource::open(input, scheme)?;
+    let mut sink = PmtilesSink::create(output, source.metadata()?)?;
+    copy_tiles(&mut source, &mut sink)?;
+    Ok(())
+}
 
-    let file = File::create(output)
-        .with_context(|| format!("failed to create output pmtiles: {}", output.display()))?;
-    write_header(&file, &header)?;
+/// PMTiles header fields taken from MBTiles metadata rows.
+pub(crate) fn mbtiles_writer_options(
+    metadata: &BTreeMap<String, String>,
+    tile_compression: u8,
+) -> PmtilesWriterOptions {
+    let zoom = |name: &str| {
+        metadata
+            .get(name)
+            .and_then(|value| value.trim().parse::<u8>().ok())
+    };
+    PmtilesWriterOptions {
+        internal_compression: 0,
+        tile_compression,
+        tile_type: metadata
+            .get("format")
+            .map(|format| tile_type_from_format(format))
+            .unwrap_or(0),
+        min_zoom: zoom("minzoom"),
+        max_zoom: zoom("maxzoom"),
+        bounds: metadata
+            .get("bounds")
+            .and_then(|value| parse_mbtiles_bounds(value)),
+        center: metadata
+            .get("center")
+            .and_then(|value| parse_mbtiles_center(value)),
+    }
+}
 
-    let mut file = file;
-    file.seek(SeekFrom::Start(header.root_offset))
-        .context("seek root directory")?;
-    file.write_all(&dir_bytes).context("write root directory")?;
+pub fn pmtiles_to_mbtiles(input: &Path, output: &Path) -> Result<()> {
+    pmtiles_to_mbtiles_with_scheme(input, output, MbtilesScheme::Tms)
+}
 
-    file.seek(SeekFrom::Start(header.data_offset))
-        .context("seek data")?;
-    file.write_all(&data_section).context("write data")?;
+/// Convert PMTiles to MBTiles, writing `tile_row` in the given scheme.
+pub fn pmtiles_to_mbtiles_with_scheme(
+    input: &Path,
+    output: &Path,
+    scheme: MbtilesScheme,
+) -> Result<()> {
+    ensure_pmtiles_path(input)?;
+    ensure_mbtiles_path(output)?;
 
+    let mut source = PmtilesSource::open(input)?;
+    let mut sink = MbtilesSink::create(output, source.metadata()?, scheme)?;
+    copy_tiles(&mut source, &mut sink)?;
     Ok(())
 }
 
-pub fn pmtiles_to_mbtiles(input: &Path, output: &Path) -> Result<()> {
+/// Prune a PMTiles archive straight into MBTiles in one pass; tiles compressed
+/// with anything but none are stored gzip-compressed.
+pub fn prune_pmtiles_to_mbtiles(
+    input: &Path,
+    output: &Path,
+    style: &crate::style::MapboxStyle,
+    apply_filters: bool,
+    options: PruneOptions,
+    scheme: MbtilesScheme,
+) -> Result<PruneStats> {
     ensure_pmtiles_path(input)?;
     ensure_mbtiles_path(output)?;
 
     let file = File::open(input)
         .with_context(|| format!("failed to open input pmtiles: {}", input.display()))?;
     let header = read_header(&file).context("read header")?;
+    let metadata = pmtiles_metadata_to_mbtiles(&read_metadata_json(&file, &header)?, &header);
+    let root_entries =
+        read_directory_section(&file, &header, header.root_offset, header.root_length)?;
+    let mut tile_entries = Vec::new();
+    collect_tile_entries(&file, &header, &root_entries, &mut tile_entries)?;
+    drop(file);
 
-    let mut file = file;
-    file.seek(SeekFrom::Start(header.root_offset))
-        .context("seek root directory")?;
-    let mut dir_buf = vec![0u8; header.root_length as usize];
-    file.read_exact(&mut dir_buf)
-        .context("read root directory")?;
-    let dir_bytes = decode_internal_bytes(dir_buf, header.internal_compression)?;
-    let entries = decode_directory(&dir_bytes)?;
-
-    let mut output_conn = Connection::open(output)
-        .with_context(|| format!("failed to open output mbtiles: {}", output.display()))?;
+    let output_conn = create_mbtiles_output(output, metadata)?;
+    apply_write_pragmas_with_cache(&output_conn, options.write_cache_mb)?;
     output_conn
-        .execute_batch(
-            "
-            CREATE TABLE metadata (name TEXT, value TEXT);
-            CREATE TABLE tiles (
-                zoom_level INTEGER,
-                tile_column INTEGER,
-                tile_row INTEGER,
-                tile_data BLOB
-            );
-            ",
+        .execute_batch("BEGIN")
+        .context("begin output transaction")?;
+    let mut insert = output_conn
+        .prepare(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
         )
-        .context("create output schema")?;
+        .context("prepare tile insert")?;
+    let output_tile_compression = if header.tile_compression == 0 { 0 } else { 1 };
+    let stats = run_pmtiles_tile_pipeline(
+        input,
+        pending_entries(&header, tile_entries, None),
+        StylePrune::new(style, apply_filters, &options),
+        PipelineOptions::from(&options),
+        header.tile_compression,
+        output_tile_compression,
+        |tile_id, tile_data| {
+            let (z, x, y) = tile_id_to_xyz(tile_id);
+            insert
+                .execute((z, x, scheme.y_to_row(z, y), tile_data))
+                .context("insert tile")?;
+            Ok(())
+        },
+    )?;
+    drop(insert);
+    output_conn
+        .execute_batch("COMMIT")
+        .context("commit output")?;
+    Ok(stats)
+}
 
-    let tx = output_conn
-        .transaction()
-        .context("begin output transaction")?;
+/// Rewrite a PMTiles archive in clustered tile_id order with fresh directories,
+/// optionally switching internal and tile compression.
+pub fn pmtiles_to_pmtiles(input: &Path, output: &Path, options: PmtilesCopyOptions) -> Result<()> {
+    ensure_pmtiles_path(input)?;
+    ensure_pmtiles_path(output)?;
 
+    let file = File::open(input)
+        .with_context(|| format!("failed to open input pmtiles: {}", input.display()))?;
+    let header = read_header(&file).context("read header")?;
+    let metadata = read_metadata_json(&file, &header)?;
+    let root_entries =
+        read_directory_section(&file, &header, header.root_offset, header.root_length)?;
+    let mut entries = Vec::new();
+    collect_tile_entries(&file, &header, &root_entries, &mut entries)?;
+    entries.sort_unstable_by_key(|entry| entry.tile_id);
+    let mut file = file;
+
+    let internal_compression = options
+        .internal_compression
+        .unwrap_or(header.internal_compression);
+    let tile_compression = options.tile_compression.unwrap_or(header.tile_compression);
+    let mut writer = PmtilesWriter::new()?;
     for entry in entries {
         let mut data = vec![0u8; entry.length as usize];
-        let data_offset = header.data_offset + entry.offset;
-        file.seek(SeekFrom::Start(data_offset))
+        file.seek(SeekFrom::Start(header.data_offset + entry.offset))
             .context("seek tile")?;
         file.read_exact(&mut data).context("read tile data")?;
-
+        if tile_compression != header.tile_compression {
+            let payload = decode_tile_payload_pmtiles(&data, header.tile_compression)?;
+            data = encode_tile_payload_pmtiles(&payload, tile_compression)?;
+        }
         for i in 0..entry.run_length.max(1) {
-            let tile_id = entry.tile_id + i as u64;
-            let (z, x, y) = tile_id_to_xyz(tile_id);
-            tx.execute(
-                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
-                (z as i64, x as i64, y as i64, data.clone()),
-            )
-            .context("insert tile")?;
+            writer.add_tile(entry.tile_id + u64::from(i), &data)?;
         }
     }
 
-    tx.commit().context("commit output")?;
-    Ok(())
+    writer.finish(
+        output,
+        &encode_metadata_json(&metadata, internal_compression)?,
+        PmtilesWriterOptions {
+            internal_compression,
+            tile_compression,
+            tile_type: header.tile_type,
+            min_zoom: Some(header.min_zoom),
+            max_zoom: Some(header.max_zoom),
+            bounds: Some(header_bounds(&header)),
+            center: Some(header_center(&header)),
+        },
+    )
 }
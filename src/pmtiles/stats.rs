@@ -16,11 +16,7 @@ impl StatAccum {
     }
 
     pub fn into_stats(self) -> MbtilesStats {
-        let avg_bytes = if self.tile_count == 0 {
-            0
-        } else {
-            self.total_bytes / self.tile_count
-        };
+        let avg_bytes = self.total_bytes.checked_div(self.tile_count).unwrap_or(0);
         MbtilesStats {
             tile_count: self.tile_count,
             total_bytes: self.total_bytes,
@@ -4,6 +4,12 @@ use std::time::Duration;
 pub const HEADER_SIZE: usize = 127;
 pub const MAGIC: &[u8; 7] = b"PMTiles";
 pub const VERSION: u8 = 3;
+/// The header and root directory must fit in the first 16 KiB of the archive.
+pub const ROOT_DIRECTORY_MAX_BYTES: u64 = 16_384;
+/// Smallest number of entries per leaf directory when the root overflows.
+pub const LEAF_DIRECTORY_MIN_ENTRIES: usize = 4096;
+/// Leaf directories deeper than this are treated as malformed.
+pub const MAX_DIRECTORY_DEPTH: usize = 4;
 
 #[derive(Debug, Clone)]
 pub struct Header {
@@ -19,6 +25,8 @@ pub struct Header {
     pub n_tile_entries: u64,
     pub n_tile_contents: u64,
     pub clustered: u8,
+    /// Compression in this crate's numbering (0 none, 1 gzip, 2 brotli,
+    /// 3 zstd); `read_header`/`write_header` convert to the spec's codes.
     pub internal_compression: u8,
     pub tile_compression: u8,
     pub tile_type: u8,
@@ -33,6 +41,31 @@ pub struct Header {
     pub center_latitude: i32,
 }
 
+/// Geographic bounds in degrees (west, south, east, north).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoBounds {
+    pub min_longitude: f64,
+    pub min_latitude: f64,
+    pub max_longitude: f64,
+    pub max_latitude: f64,
+}
+
+/// Initial view suggested to map clients.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoCenter {
+    pub longitude: f64,
+    pub latitude: f64,
+    pub zoom: u8,
+}
+
+/// Compression overrides for PMTiles to PMTiles copy; `None` keeps the input's.
+/// Values use this crate's numbering: 0 none, 1 gzip, 2 brotli, 3 zstd.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PmtilesCopyOptions {
+    pub internal_compression: Option<u8>,
+    pub tile_compression: Option<u8>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Entry {
     pub tile_id: u64,
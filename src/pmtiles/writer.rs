@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing::warn;
+
+use crate::pmtiles::{
+    Entry, GeoBounds, GeoCenter, build_directories, build_header_with_metadata, set_header_bounds,
+    tile_id_to_xyz, tile_range_bounds, write_header,
+};
+
+/// PMTiles writer that spills tile data to a temporary file as tiles arrive
+/// and keeps only directory entries and content hashes in memory. The archive
+/// is always clustered: `finish` lays out tile data in tile_id order.
+pub struct PmtilesWriter {
+    data: BufWriter<File>,
+    data_length: u64,
+    entries: Vec<Entry>,
+    contents: HashMap<u128, (u64, u32)>,
+    min_zoom: u8,
+    max_zoom: u8,
+    /// (min_x, min_y, max_x, max_y) of the tiles at `max_zoom`.
+    max_zoom_extent: Option<(u32, u32, u32, u32)>,
+}
+
+/// Header fields the writer cannot derive from the tiles themselves.
+#[derive(Debug, Clone, Copy)]
+pub struct PmtilesWriterOptions {
+    pub internal_compression: u8,
+    pub tile_compression: u8,
+    pub tile_type: u8,
+    /// Zoom range for the header; defaults to the zooms of the added tiles.
+    pub min_zoom: Option<u8>,
+    pub max_zoom: Option<u8>,
+    /// Defaults to the extent of the tiles at the highest zoom.
+    pub bounds: Option<GeoBounds>,
+    /// Defaults to the middle of the bounds at the lowest zoom.
+    pub center: Option<GeoCenter>,
+}
+
+impl PmtilesWriter {
+    pub fn new() -> Result<Self> {
+        let data = tempfile::tempfile().context("create temporary tile data")?;
+        Self::from_spill(data)
+    }
+
+    /// Use `spill` as the temporary tile data file. Tiles it already holds
+    /// are registered with `add_spilled_tile`; new tiles are appended.
+    pub(crate) fn from_spill(mut spill: File) -> Result<Self> {
+        let data_length = spill
+            .seek(SeekFrom::End(0))
+            .context("seek temporary tile data")?;
+        Ok(Self {
+            data: BufWriter::new(spill),
+            data_length,
+            entries: Vec::new(),
+            contents: HashMap::new(),
+            min_zoom: u8::MAX,
+            max_zoom: u8::MIN,
+            max_zoom_extent: None,
+        })
+    }
+
+    /// Add a tile in any order; identical blobs are stored once. If a
+    /// tile_id is added more than once, the last data wins.
+    pub fn add_tile(&mut self, tile_id: u64, data: &[u8]) -> Result<()> {
+        let key = content_key(data);
+        let location = match self.contents.get(&key).copied() {
+            Some(location) if self.stored_blob_matches(location, data)? => location,
+            _ => {
+                let location = (
+                    self.data_length,
+                    u32::try_from(data.len()).context("tile data exceeds 4 GiB")?,
+                );
+                self.data
+                    .write_all(data)
+                    .context("write temporary tile data")?;
+                self.data_length += data.len() as u64;
+                self.contents.entry(key).or_insert(location);
+                location
+            }
+        };
+        self.push_entry(tile_id, location);
+        Ok(())
+    }
+
+    /// Add a tile whose `data` is already stored at `offset` in the spill
+    /// file passed to `from_spill`.
+    pub(crate) fn add_spilled_tile(
+        &mut self,
+        tile_id: u64,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        let length = u32::try_from(data.len()).context("tile data exceeds 4 GiB")?;
+        let key = content_key(data);
+        let location = match self.contents.get(&key).copied() {
+            Some(location) if self.stored_blob_matches(location, data)? => location,
+            Some(_) => (offset, length),
+            None => {
+                self.contents.insert(key, (offset, length));
+                (offset, length)
+            }
+        };
+        self.push_entry(tile_id, location);
+        Ok(())
+    }
+
+    /// Compare `data` with the blob at `location` in the spill file, so a
+    /// content hash collision never makes two tiles share data. The file
+    /// position is restored because the spill may be shared with a reader.
+    fn stored_blob_matches(&mut self, (offset, length): (u64, u32), data: &[u8]) -> Result<bool> {
+        if length as usize != data.len() {
+            return Ok(false);
+        }
+        self.data.flush().context("flush temporary tile data")?;
+        let file = self.data.get_mut();
+        let position = file.stream_position().context("seek temporary tile data")?;
+        file.seek(SeekFrom::Start(offset))
+            .context("seek temporary tile data")?;
+        let mut stored = vec![0; data.len()];
+        file.read_exact(&mut stored)
+            .context("read temporary tile data")?;
+        file.seek(SeekFrom::Start(position))
+            .context("seek temporary tile data")?;
+        Ok(stored == data)
+    }
+
+    fn push_entry(&mut self, tile_id: u64, (offset, length): (u64, u32)) {
+        let (z, x, y) = tile_id_to_xyz(tile_id);
+        if self.max_zoom_extent.is_none() || z > self.max_zoom {
+            self.max_zoom_extent = Some((x, y, x, y));
+        } else if z == self.max_zoom
+            && let Some((min_x, min_y, max_x, max_y)) = self.max_zoom_extent.as_mut()
+        {
+            *min_x = (*min_x).min(x);
+            *min_y = (*min_y).min(y);
+            *max_x = (*max_x).max(x);
+            *max_y = (*max_y).max(y);
+        }
+        self.min_zoom = self.min_zoom.min(z);
+        self.max_zoom = self.max_zoom.max(z);
+        self.entries.push(Entry {
+            tile_id,
+            offset,
+            length,
+            run_length: 1,
+        });
+    }
+
+    /// Sort and merge entries into runs, then assemble the archive as
+    /// header, root directory, metadata, leaf directories and tile data.
+    /// `metadata` must already be encoded with `internal_compression`.
+    pub fn finish(
+        mut self,
+        output: &Path,
+        metadata: &[u8],
+        options: PmtilesWriterOptions,
+    ) -> Result<()> {
+        let mut entries = merge_runs(std::mem::take(&mut self.entries));
+        let addressed_tiles = entries
+            .iter()
+            .map(|entry| u64::from(entry.run_length))
+            .sum();
+        let (blobs, data_length) = cluster_offsets(&mut entries);
+        let (root, leaves) = build_directories(&entries, options.internal_compression)?;
+        let mut header = build_header_with_metadata(
+            root.len() as u64,
+            metadata.len() as u64,
+            leaves.len() as u64,
+            data_length,
+            addressed_tiles,
+            options.min_zoom.unwrap_or(if self.min_zoom == u8::MAX {
+                0
+            } else {
+                self.min_zoom
+            }),
+            options.max_zoom.unwrap_or(if self.max_zoom == u8::MIN {
+                0
+            } else {
+                self.max_zoom
+            }),
+            options.internal_compression,
+            options.tile_compression,
+            options.tile_type,
+        );
+        header.n_tile_entries = entries.len() as u64;
+        header.n_tile_contents = blobs.len() as u64;
+        header.clustered = 1;
+        let bounds = options.bounds.or_else(|| {
+            self.max_zoom_extent.map(|(min_x, min_y, max_x, max_y)| {
+                tile_range_bounds(self.max_zoom, min_x, min_y, max_x, max_y)
+            })
+        });
+        if let Some(bounds) = bounds {
+            let center = options.center.unwrap_or(GeoCenter {
+                longitude: (bounds.min_longitude + bounds.max_longitude) / 2.0,
+                latitude: (bounds.min_latitude + bounds.max_latitude) / 2.0,
+                zoom: header.min_zoom,
+            });
+            set_header_bounds(&mut header, bounds, center);
+        }
+
+        let mut data = self
+            .data
+            .into_inner()
+            .map_err(|err| anyhow::anyhow!("flush temporary tile data: {}", err.error()))?;
+        let mut file = File::create(output)
+            .with_context(|| format!("failed to create output pmtiles: {}", output.display()))?;
+        write_header(&file, &header).context("write header")?;
+        file.seek(SeekFrom::Start(header.root_offset))
+            .context("seek root directory")?;
+        file.write_all(&root).context("write root directory")?;
+        if header.metadata_length > 0 {
+            file.seek(SeekFrom::Start(header.metadata_offset))
+                .context("seek metadata")?;
+            file.write_all(metadata).context("write metadata")?;
+        }
+        if header.leaf_length > 0 {
+            file.seek(SeekFrom::Start(header.leaf_offset))
+                .context("seek leaf directories")?;
+            file.write_all(&leaves).context("write leaf directories")?;
+        }
+        file.seek(SeekFrom::Start(header.data_offset))
+            .context("seek data")?;
+        let mut in_place = data_length == self.data_length;
+        let mut expected = 0;
+        for &(offset, length) in &blobs {
+            in_place &= offset == expected;
+            expected += u64::from(length);
+        }
+        if in_place {
+            data.seek(SeekFrom::Start(0))
+                .context("seek temporary tile data")?;
+            std::io::copy(&mut data, &mut file).context("write data")?;
+            return Ok(());
+        }
+        let mut out = BufWriter::new(file);
+        let mut blob = Vec::new();
+        for (offset, length) in blobs {
+            blob.resize(length as usize, 0);
+            data.seek(SeekFrom::Start(offset))
+                .context("seek temporary tile data")?;
+            data.read_exact(&mut blob)
+                .context("read temporary tile data")?;
+            out.write_all(&blob).context("write data")?;
+        }
+        out.flush().context("write data")?;
+        Ok(())
+    }
+}
+
+/// Give each distinct blob an offset in order of first use by tile_id, so
+/// the data section is clustered. Returns the spill `(offset, length)` of the
+/// blobs in their new order and the new data length.
+fn cluster_offsets(entries: &mut [Entry]) -> (Vec<(u64, u32)>, u64) {
+    let mut moved = HashMap::new();
+    let mut blobs = Vec::new();
+    let mut data_length = 0u64;
+    for entry in entries.iter_mut() {
+        let offset = *moved.entry(entry.offset).or_insert_with(|| {
+            blobs.push((entry.offset, entry.length));
+            data_length += u64::from(entry.length);
+            data_length - u64::from(entry.length)
+        });
+        entry.offset = offset;
+    }
+    (blobs, data_length)
+}
+
+/// Merge consecutive tile_ids that point at the same blob into runs. Of
+/// several entries for one tile_id, the last added is kept.
+fn merge_runs(mut entries: Vec<Entry>) -> Vec<Entry> {
+    // Stable, so repeated tile_ids stay in the order they were added.
+    entries.sort_by_key(|entry| entry.tile_id);
+    let before = entries.len();
+    entries.dedup_by(|next, kept| {
+        let duplicate = next.tile_id == kept.tile_id;
+        if duplicate {
+            std::mem::swap(next, kept);
+        }
+        duplicate
+    });
+    if entries.len() < before {
+        warn!(
+            count = before - entries.len(),
+            "duplicate tile ids; keeping the last tile added for each"
+        );
+    }
+    let mut merged: Vec<Entry> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        if let Some(last) = merged.last_mut()
+            && last.offset == entry.offset
+            && last.length == entry.length
+            && last.tile_id + u64::from(last.run_length) == entry.tile_id
+            && last.run_length < u32::MAX
+        {
+            last.run_length += 1;
+            continue;
+        }
+        merged.push(entry);
+    }
+    merged
+}
+
+/// 128-bit content key from two independently seeded SipHash passes.
+fn content_key(data: &[u8]) -> u128 {
+    let mut high = DefaultHasher::new();
+    0u8.hash(&mut high);
+    data.hash(&mut high);
+    let mut low = DefaultHasher::new();
+    1u8.hash(&mut low);
+    data.hash(&mut low);
+    (u128::from(high.finish()) << 64) | u128::from(low.finish())
+}
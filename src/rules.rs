@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use mvt_reader::feature::Value;
+use serde::{Deserialize, Serialize};
+
+/// Layer name whose rules apply to source-layers without their own entry.
+pub const ANY_LAYER: &str = "*";
+
+/// Explicit per-layer attribute schema read from a JSON or TOML rules file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttributeRules {
+    /// Rules per source-layer (or `*`), checked in order; the first rule whose
+    /// zoom range contains the tile zoom applies.
+    #[serde(default)]
+    pub layers: BTreeMap<String, Vec<LayerRule>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayerRule {
+    /// Lowest zoom (inclusive) the rule applies to.
+    #[serde(default)]
+    pub minzoom: Option<u8>,
+    /// Highest zoom (inclusive) the rule applies to.
+    #[serde(default)]
+    pub maxzoom: Option<u8>,
+    /// Keep only these keys; every other key is dropped.
+    #[serde(default)]
+    pub keep: Option<BTreeSet<String>>,
+    /// Drop these keys.
+    #[serde(default)]
+    pub drop: BTreeSet<String>,
+    /// Rename keys, `old = new`.
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    /// Convert values of these keys.
+    #[serde(default)]
+    pub cast: BTreeMap<String, Cast>,
+}
+
+/// Value conversion. Values that cannot be converted are kept as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Cast {
+    /// Parse strings as numbers; whole numbers become integers.
+    Number,
+    /// Round numbers (and numeric strings) to the nearest integer.
+    Int,
+    /// Format numbers and booleans as strings.
+    String,
+    /// Round floats to the given number of decimals.
+    Round(u8),
+}
+
+impl AttributeRules {
+    /// The rule for `layer` at `zoom`, if any.
+    pub fn layer_rule(&self, layer: &str, zoom: u8) -> Option<&LayerRule> {
+        self.layers
+            .get(layer)
+            .or_else(|| self.layers.get(ANY_LAYER))?
+            .iter()
+            .find(|rule| rule.applies_on_zoom(zoom))
+    }
+}
+
+impl LayerRule {
+    pub fn applies_on_zoom(&self, zoom: u8) -> bool {
+        self.minzoom.is_none_or(|minzoom| zoom >= minzoom)
+            && self.maxzoom.is_none_or(|maxzoom| zoom <= maxzoom)
+    }
+
+    /// Apply keep/drop, then casts, then renames. Keep, drop and cast match
+    /// the original key. When two keys are renamed onto the same name, the
+    /// last one in key order wins.
+    pub fn transform(&self, props: BTreeMap<String, Value>) -> BTreeMap<String, Value> {
+        props
+            .into_iter()
+            .filter(|(key, _)| {
+                !self.drop.contains(key) && self.keep.as_ref().is_none_or(|keep| keep.contains(key))
+            })
+            .map(|(key, value)| {
+                let value = match self.cast.get(&key) {
+                    Some(cast) => cast.apply(value),
+                    None => value,
+                };
+                match self.rename.get(&key) {
+                    Some(renamed) => (renamed.clone(), value),
+                    None => (key, value),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Cast {
+    pub fn apply(self, value: Value) -> Value {
+        match (self, value) {
+            (Cast::Number, Value::String(text)) => match parse_number(&text) {
+                Some(number) => number_value(number),
+                None => Value::String(text),
+            },
+            (Cast::Int, Value::Float(val)) if val.is_finite() => integer_value(val.round() as i64),
+            (Cast::Int, Value::Double(val)) if val.is_finite() => integer_value(val.round() as i64),
+            (Cast::Int, Value::String(text)) => match parse_number(&text) {
+                Some(number) => integer_value(number.round() as i64),
+                None => Value::String(text),
+            },
+            (Cast::String, Value::Float(val)) => Value::String(val.to_string()),
+            (Cast::String, Value::Double(val)) => Value::String(val.to_string()),
+            (Cast::String, Value::Int(val) | Value::SInt(val)) => Value::String(val.to_string()),
+            (Cast::String, Value::UInt(val)) => Value::String(val.to_string()),
+            (Cast::String, Value::Bool(val)) => Value::String(val.to_string()),
+            (Cast::Round(digits), Value::Float(val)) => {
+                Value::Float(round_to(f64::from(val), digits) as f32)
+            }
+            (Cast::Round(digits), Value::Double(val)) => Value::Double(round_to(val, digits)),
+            (_, value) => value,
+        }
+    }
+}
+
+pub fn read_rules(path: &Path) -> Result<AttributeRules> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read rules file: {}", path.display()))?;
+    let is_toml = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    let rules: AttributeRules = if is_toml {
+        toml::from_str(&contents).context("parse rules toml")?
+    } else {
+        serde_json::from_str(&contents).context("parse rules json")?
+    };
+    for (layer, layer_rules) in rules.layers.iter() {
+        for rule in layer_rules {
+            if let (Some(minzoom), Some(maxzoom)) = (rule.minzoom, rule.maxzoom)
+                && minzoom > maxzoom
+            {
+                anyhow::bail!("rules for layer {layer}: minzoom {minzoom} > maxzoom {maxzoom}");
+            }
+        }
+    }
+    Ok(rules)
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    text.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+}
+
+fn number_value(number: f64) -> Value {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        integer_value(number as i64)
+    } else {
+        Value::Double(number)
+    }
+}
+
+/// Non-negative integers as `uint`, negative ones as zigzag `sint`.
+fn integer_value(number: i64) -> Value {
+    match u64::try_from(number) {
+        Ok(number) => Value::UInt(number),
+        Err(_) => Value::SInt(number),
+    }
+}
+
+/// Returns `value` unchanged when scaling by `10^digits` overflows.
+fn round_to(value: f64, digits: u8) -> f64 {
+    let scale = 10f64.powi(i32::from(digits));
+    let scaled = value * scale;
+    if !scaled.is_finite() {
+        return value;
+    }
+    scaled.round() / scale
+}
@@ -1,9 +1,11 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
 use anyhow::{Context, Result};
 use serde_json::Value;
+use tracing::warn;
 
 const PAINT_PROPERTIES_TO_CHECK: &[&str] = &[
     "fill-opacity",
@@ -21,27 +23,117 @@ const PAINT_PROPERTIES_TO_CHECK: &[&str] = &[
     "heatmap-opacity",
 ];
 
+/// A numeric paint property as a function of zoom.
 #[derive(Debug, Clone)]
 enum PaintValue {
     Number(f64),
-    Stops(Vec<(u8, f64)>),
+    Interpolate {
+        /// Exponential base; 1 is linear.
+        base: f64,
+        stops: Vec<(f64, PaintValue)>,
+    },
+    Step {
+        first: Box<PaintValue>,
+        stops: Vec<(f64, PaintValue)>,
+    },
+    /// Depends on feature data, so it cannot be resolved per zoom.
+    DataDriven,
 }
 
 impl PaintValue {
+    /// Whether the value may be nonzero anywhere in `[zoom, zoom + 1)`; tiles
+    /// of `zoom` are drawn for all of those display zooms. Between stops the
+    /// value is constant or monotonic, so the interval ends and the stops
+    /// inside it are enough to check.
     fn is_nonzero_at_zoom(&self, zoom: u8) -> bool {
+        let start = zoom as f64;
+        let end = start + 1.0;
+        let mut samples = vec![self.value_at_zoom(start), self.value_before_zoom(end)];
+        let mut stops = Vec::new();
+        self.stops_between(start, end, &mut stops);
+        samples.extend(stops.into_iter().map(|stop| self.value_at_zoom(stop)));
+        samples
+            .into_iter()
+            .any(|value| value.is_none_or(|value| value != 0.0))
+    }
+
+    /// Stop zooms strictly between `start` and `end`.
+    fn stops_between(&self, start: f64, end: f64, out: &mut Vec<f64>) {
+        let stops = match self {
+            PaintValue::Interpolate { stops, .. } => stops,
+            PaintValue::Step { first, stops } => {
+                first.stops_between(start, end, out);
+                stops
+            }
+            PaintValue::Number(_) | PaintValue::DataDriven => return,
+        };
+        for (stop, output) in stops {
+            if *stop > start && *stop < end {
+                out.push(*stop);
+            }
+            output.stops_between(start, end, out);
+        }
+    }
+
+    /// Value just below `zoom`, which differs from the value at `zoom` only
+    /// when a step starts exactly there.
+    fn value_before_zoom(&self, zoom: f64) -> Option<f64> {
         match self {
-            PaintValue::Number(value) => *value != 0.0,
-            PaintValue::Stops(stops) => {
-                if let Some((_, value)) = stops.iter().find(|(z, _)| *z == zoom) {
-                    *value != 0.0
-                } else {
-                    true
+            PaintValue::Step { first, stops } => stops
+                .iter()
+                .rev()
+                .find(|(stop, _)| *stop < zoom)
+                .map_or(first.as_ref(), |(_, output)| output)
+                .value_before_zoom(zoom),
+            _ => self.value_at_zoom(zoom),
+        }
+    }
+
+    fn value_at_zoom(&self, zoom: f64) -> Option<f64> {
+        match self {
+            PaintValue::Number(value) => Some(*value),
+            PaintValue::Interpolate { base, stops } => {
+                let upper = stops.iter().position(|(stop, _)| *stop > zoom);
+                match upper {
+                    Some(0) => stops[0].1.value_at_zoom(zoom),
+                    None => stops.last()?.1.value_at_zoom(zoom),
+                    Some(index) => {
+                        let (lower_zoom, lower) = &stops[index - 1];
+                        let (upper_zoom, upper) = &stops[index];
+                        let t = interpolation_factor(*base, zoom, *lower_zoom, *upper_zoom);
+                        let lower = lower.value_at_zoom(zoom)?;
+                        if t == 0.0 {
+                            return Some(lower);
+                        }
+                        let upper = upper.value_at_zoom(zoom)?;
+                        Some(lower + (upper - lower) * t)
+                    }
                 }
             }
+            PaintValue::Step { first, stops } => stops
+                .iter()
+                .rev()
+                .find(|(stop, _)| *stop <= zoom)
+                .map_or(first.as_ref(), |(_, output)| output)
+                .value_at_zoom(zoom),
+            PaintValue::DataDriven => None,
         }
     }
 }
 
+/// Position of `zoom` between two stops, as in MapLibre's exponential curve.
+fn interpolation_factor(base: f64, zoom: f64, lower: f64, upper: f64) -> f64 {
+    let range = upper - lower;
+    let progress = zoom - lower;
+    if range == 0.0 {
+        0.0
+    } else if base == 1.0 {
+        progress / range
+    } else {
+        (base.powf(progress) - 1.0) / (base.powf(range) - 1.0)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct MapboxStyleLayer {
     minzoom: Option<f64>,
@@ -49,6 +141,11 @@ struct MapboxStyleLayer {
     visibility: Option<String>,
     paint: HashMap<String, PaintValue>,
     filter: Option<Filter>,
+    /// Index into `MapboxStyle::style_names` of the style defining the layer.
+    style: usize,
+    /// Feature properties read by the filter, layout and paint; `None` when
+    /// the layer may read any property.
+    property_keys: Option<HashSet<String>>,
 }
 
 impl MapboxStyleLayer {
@@ -90,22 +187,68 @@ impl MapboxStyleLayer {
 #[derive(Debug, Clone)]
 pub struct MapboxStyle {
     layers_by_source_layer: HashMap<String, Vec<MapboxStyleLayer>>,
+    style_names: Vec<String>,
 }
 
 impl MapboxStyle {
+    /// Combine styles so a feature is kept when any of them keeps it. Layers
+    /// are evaluated in the order the styles are given.
+    pub fn union(styles: Vec<MapboxStyle>) -> MapboxStyle {
+        let mut combined = MapboxStyle {
+            layers_by_source_layer: HashMap::new(),
+            style_names: Vec::new(),
+        };
+        for style in styles {
+            let offset = combined.style_names.len();
+            combined.style_names.extend(style.style_names);
+            for (source_layer, layers) in style.layers_by_source_layer {
+                combined
+                    .layers_by_source_layer
+                    .entry(source_layer)
+                    .or_default()
+                    .extend(layers.into_iter().map(|layer| MapboxStyleLayer {
+                        style: layer.style + offset,
+                        ..layer
+                    }));
+            }
+        }
+        combined
+    }
+
+    /// Names of the combined styles (their file paths), in evaluation order.
+    pub fn style_names(&self) -> &[String] {
+        &self.style_names
+    }
+
     pub fn source_layers(&self) -> HashSet<String> {
         self.layers_by_source_layer.keys().cloned().collect()
     }
 
     pub fn is_layer_visible_on_zoom(&self, layer_name: &str, zoom: u8) -> bool {
+        self.visible_style(layer_name, zoom).is_some()
+    }
+
+    /// Index of the first style with a rendered layer for `layer_name` at
+    /// `zoom`.
+    pub fn visible_style(&self, layer_name: &str, zoom: u8) -> Option<usize> {
         self.layers_by_source_layer
-            .get(layer_name)
-            .map(|layers| {
-                layers
-                    .iter()
-                    .any(|layer| layer.is_visible_on_zoom(zoom) && layer.is_rendered(zoom))
-            })
-            .unwrap_or(false)
+            .get(layer_name)?
+            .iter()
+            .find(|layer| layer.is_visible_on_zoom(zoom) && layer.is_rendered(zoom))
+            .map(|layer| layer.style)
+    }
+
+    /// Property keys that the layers rendering `layer_name` at `zoom` read,
+    /// or `None` when every property must be kept.
+    pub fn used_property_keys(&self, layer_name: &str, zoom: u8) -> Option<HashSet<&str>> {
+        let mut keys = HashSet::new();
+        for layer in self.layers_by_source_layer.get(layer_name)? {
+            if !layer.is_visible_on_zoom(zoom) || !layer.is_rendered(zoom) {
+                continue;
+            }
+            keys.extend(layer.property_keys.as_ref()?.iter().map(String::as_str));
+        }
+        Some(keys)
     }
 
     pub fn should_keep_feature(
@@ -115,8 +258,21 @@ impl MapboxStyle {
         feature: &mvt_reader::feature::Feature,
         unknown_counter: &mut usize,
     ) -> FilterResult {
+        self.keeping_style(layer_name, zoom, feature, unknown_counter)
+            .0
+    }
+
+    /// Like [`Self::should_keep_feature`], also returning the index of the
+    /// first style whose layer keeps the feature.
+    pub fn keeping_style(
+        &self,
+        layer_name: &str,
+        zoom: u8,
+        feature: &mvt_reader::feature::Feature,
+        unknown_counter: &mut usize,
+    ) -> (FilterResult, Option<usize>) {
         let Some(layers) = self.layers_by_source_layer.get(layer_name) else {
-            return FilterResult::False;
+            return (FilterResult::False, None);
         };
         let mut saw_unknown = false;
         for layer in layers {
@@ -128,7 +284,7 @@ impl MapboxStyle {
                 Some(filter) => filter.evaluate(feature, zoom),
             };
             match result {
-                FilterResult::True => return FilterResult::True,
+                FilterResult::True => return (FilterResult::True, Some(layer.style)),
                 FilterResult::Unknown => {
                     saw_unknown = true;
                     *unknown_counter += 1;
@@ -137,9 +293,9 @@ impl MapboxStyle {
             }
         }
         if saw_unknown {
-            FilterResult::Unknown
+            (FilterResult::Unknown, None)
         } else {
-            FilterResult::False
+            (FilterResult::False, None)
         }
     }
 }
@@ -156,6 +312,8 @@ enum FilterValue {
     String(String),
     Number(f64),
     Bool(bool),
+    List(Vec<FilterValue>),
+    Object(BTreeMap<String, FilterValue>),
 }
 
 impl FilterValue {
@@ -164,9 +322,140 @@ impl FilterValue {
             (FilterValue::String(a), FilterValue::String(b)) => a == b,
             (FilterValue::Number(a), FilterValue::Number(b)) => (*a - *b).abs() < f64::EPSILON,
             (FilterValue::Bool(a), FilterValue::Bool(b)) => a == b,
+            (FilterValue::List(a), FilterValue::List(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.equals(b))
+            }
+            (FilterValue::Object(a), FilterValue::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|((ka, va), (kb, vb))| ka == kb && va.equals(vb))
+            }
             _ => false,
         }
     }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            FilterValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// `to-string` conversion; lists and objects become JSON.
+    fn to_text(&self) -> String {
+        match self {
+            FilterValue::String(text) => text.clone(),
+            FilterValue::Number(value) => number_to_string(*value),
+            FilterValue::Bool(value) => value.to_string(),
+            FilterValue::List(_) | FilterValue::Object(_) => self.to_json().to_string(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            FilterValue::String(text) => Value::String(text.clone()),
+            FilterValue::Number(value) => serde_json::json!(value),
+            FilterValue::Bool(value) => Value::Bool(*value),
+            FilterValue::List(items) => Value::Array(items.iter().map(Self::to_json).collect()),
+            FilterValue::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Ordering for `<`, `<=`, `>`, `>=`: numbers compare numerically and
+    /// strings lexicographically. Mixed or boolean operands have no ordering,
+    /// which makes the comparison false as in MapLibre.
+    fn ordering(&self, other: &FilterValue) -> Option<Ordering> {
+        match (self, other) {
+            (FilterValue::String(a), FilterValue::String(b)) => Some(a.cmp(b)),
+            (FilterValue::Number(a), FilterValue::Number(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CompareOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            "<" => Some(CompareOp::Lt),
+            "<=" => Some(CompareOp::Le),
+            ">" => Some(CompareOp::Gt),
+            ">=" => Some(CompareOp::Ge),
+            _ => None,
+        }
+    }
+
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MathOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Min,
+    Max,
+}
+
+impl MathOp {
+    fn parse(op: &str) -> Option<Self> {
+        match op {
+            "+" => Some(MathOp::Add),
+            "-" => Some(MathOp::Sub),
+            "*" => Some(MathOp::Mul),
+            "/" => Some(MathOp::Div),
+            "%" => Some(MathOp::Rem),
+            "^" => Some(MathOp::Pow),
+            "min" => Some(MathOp::Min),
+            "max" => Some(MathOp::Max),
+            _ => None,
+        }
+    }
+
+    fn accepts_arity(self, count: usize) -> bool {
+        match self {
+            MathOp::Sub => (1..=2).contains(&count),
+            MathOp::Div | MathOp::Rem | MathOp::Pow => count == 2,
+            MathOp::Add | MathOp::Mul | MathOp::Min | MathOp::Max => count >= 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match (self, args) {
+            (MathOp::Sub, [value]) => -value,
+            (MathOp::Sub, [a, b]) => a - b,
+            (MathOp::Div, [a, b]) => a / b,
+            (MathOp::Rem, [a, b]) => a % b,
+            (MathOp::Pow, [a, b]) => a.powf(*b),
+            (MathOp::Add, _) => args.iter().sum(),
+            (MathOp::Mul, _) => args.iter().product(),
+            (MathOp::Min, _) => args.iter().copied().fold(f64::INFINITY, f64::min),
+            (MathOp::Max, _) => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            _ => f64::NAN,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -180,9 +469,39 @@ enum FilterKey {
 enum Expr {
     Literal(FilterValue),
     Get(String),
+    GetFrom(String, Box<Expr>),
+    Properties,
+    Id,
     Zoom,
     Type,
     Coalesce(Vec<Expr>),
+    In {
+        needle: Box<Expr>,
+        haystack: Box<Expr>,
+    },
+    ToNumber(Vec<Expr>),
+    ToString(Box<Expr>),
+    ToBoolean(Box<Expr>),
+    Downcase(Box<Expr>),
+    Upcase(Box<Expr>),
+    Concat(Vec<Expr>),
+    Slice {
+        input: Box<Expr>,
+        start: Box<Expr>,
+        end: Option<Box<Expr>>,
+    },
+    IndexOf {
+        needle: Box<Expr>,
+        haystack: Box<Expr>,
+        from: Option<Box<Expr>>,
+    },
+    Math(MathOp, Vec<Expr>),
+    Length(Box<Expr>),
+    Step {
+        input: Box<Expr>,
+        first: Box<Expr>,
+        stops: Vec<(f64, Expr)>,
+    },
     Match {
         input: Box<Expr>,
         cases: Vec<(FilterValue, Expr)>,
@@ -198,6 +517,7 @@ enum Expr {
 enum Filter {
     Eq(Expr, Expr),
     Neq(Expr, Expr),
+    Compare(CompareOp, Expr, Expr),
     In(FilterKey, Vec<FilterValue>),
     NotIn(FilterKey, Vec<FilterValue>),
     Has(FilterKey),
@@ -206,6 +526,8 @@ enum Filter {
     Any(Vec<Filter>),
     None(Vec<Filter>),
     Not(Box<Filter>),
+    /// A boolean-valued expression such as expression-form `in`.
+    Value(Expr),
     Unknown,
 }
 
@@ -228,6 +550,17 @@ impl Filter {
                 }
                 _ => FilterResult::Unknown,
             },
+            Filter::Compare(op, left, right) => match (
+                eval_expr(left, feature, zoom),
+                eval_expr(right, feature, zoom),
+            ) {
+                (Some(actual), Some(expected)) => FilterResult::from_bool(
+                    actual
+                        .ordering(&expected)
+                        .is_some_and(|ordering| op.holds(ordering)),
+                ),
+                _ => FilterResult::Unknown,
+            },
             Filter::In(key, values) => match feature_value_by_key(feature, key, zoom) {
                 Some(actual) => FilterResult::from_bool(values.iter().any(|v| actual.equals(v))),
                 None => FilterResult::Unknown,
@@ -288,6 +621,11 @@ impl Filter {
                 FilterResult::False => FilterResult::True,
                 FilterResult::Unknown => FilterResult::Unknown,
             },
+            Filter::Value(expr) => match eval_expr(expr, feature, zoom) {
+                Some(FilterValue::Bool(value)) => FilterResult::from_bool(value),
+                Some(_) => FilterResult::False,
+                None => FilterResult::Unknown,
+            },
             Filter::Unknown => FilterResult::Unknown,
         }
     }
@@ -352,31 +690,102 @@ fn feature_type(feature: &mvt_reader::feature::Feature) -> &'static str {
     }
 }
 
+/// Parse a numeric paint value: a number, a legacy zoom function
+/// (`{stops, base, type}`), or an `interpolate`/`step` expression on
+/// `["zoom"]`. Non-numeric values (such as colors) yield `None`.
 fn parse_paint_value(value: &Value) -> Option<PaintValue> {
     if let Some(number) = value.as_f64() {
         return Some(PaintValue::Number(number));
     }
-    let stops = value.get("stops")?.as_array()?;
-    let mut parsed = Vec::new();
-    for stop in stops {
+    if value.is_object() {
+        return parse_legacy_paint_function(value);
+    }
+    let array = value.as_array()?;
+    match array.first()?.as_str()? {
+        "interpolate" if array.len() >= 5 && array.len() % 2 == 1 => {
+            if !is_zoom_expr(&array[2]) {
+                return Some(PaintValue::DataDriven);
+            }
+            let base = match array[1].as_array()?.as_slice() {
+                [kind] if kind == "linear" => 1.0,
+                [kind, base] if kind == "exponential" => base.as_f64()?,
+                // Eased curves keep their endpoints; treat them as linear.
+                [kind, ..] if kind == "cubic-bezier" => 1.0,
+                _ => return None,
+            };
+            Some(PaintValue::Interpolate {
+                base,
+                stops: parse_paint_stops(&array[3..])?,
+            })
+        }
+        "step" if array.len() >= 3 && array.len() % 2 == 1 => {
+            if !is_zoom_expr(&array[1]) {
+                return Some(PaintValue::DataDriven);
+            }
+            Some(PaintValue::Step {
+                first: Box::new(parse_paint_output(&array[2])),
+                stops: parse_paint_stops(&array[3..])?,
+            })
+        }
+        _ => Some(PaintValue::DataDriven),
+    }
+}
+
+fn parse_legacy_paint_function(value: &Value) -> Option<PaintValue> {
+    if value.get("property").is_some() {
+        return Some(PaintValue::DataDriven);
+    }
+    let mut stops = Vec::new();
+    for stop in value.get("stops")?.as_array()? {
         let arr = stop.as_array()?;
         if arr.len() < 2 {
             continue;
         }
-        let zoom = arr[0].as_f64()? as i64;
-        let value = arr[1].as_f64()?;
-        if !(0..=255).contains(&zoom) {
-            continue;
+        stops.push((arr[0].as_f64()?, PaintValue::Number(arr[1].as_f64()?)));
+    }
+    if stops.is_empty() {
+        return None;
+    }
+    match value.get("type").and_then(Value::as_str) {
+        None | Some("exponential") => Some(PaintValue::Interpolate {
+            base: value.get("base").and_then(Value::as_f64).unwrap_or(1.0),
+            stops,
+        }),
+        Some("interval") => Some(PaintValue::Step {
+            first: Box::new(stops[0].1.clone()),
+            stops,
+        }),
+        _ => None,
+    }
+}
+
+/// `zoom, output` pairs with strictly ascending zooms.
+fn parse_paint_stops(pairs: &[Value]) -> Option<Vec<(f64, PaintValue)>> {
+    let mut stops: Vec<(f64, PaintValue)> = Vec::new();
+    for pair in pairs.chunks(2) {
+        let zoom = pair[0].as_f64()?;
+        if stops.last().is_some_and(|(prev, _)| *prev >= zoom) {
+            return None;
         }
-        parsed.push((zoom as u8, value));
+        stops.push((zoom, parse_paint_output(&pair[1])));
     }
-    if parsed.is_empty() {
-        None
-    } else {
-        Some(PaintValue::Stops(parsed))
+    Some(stops)
+}
+
+/// A stop output; anything but a number depends on feature data.
+fn parse_paint_output(value: &Value) -> PaintValue {
+    match value.as_f64() {
+        Some(number) => PaintValue::Number(number),
+        None => PaintValue::DataDriven,
     }
 }
 
+fn is_zoom_expr(value: &Value) -> bool {
+    value
+        .as_array()
+        .is_some_and(|array| array.len() == 1 && array[0] == "zoom")
+}
+
 fn parse_filter(value: &Value) -> Option<Filter> {
     let array = value.as_array()?;
     if array.is_empty() {
@@ -403,6 +812,19 @@ fn parse_filter(value: &Value) -> Option<Filter> {
                 Some(Filter::Neq(left, right))
             }
         }
+        "<" | "<=" | ">" | ">=" => {
+            // A fourth argument is a collator, which we cannot honor.
+            if array.len() != 3 {
+                return Some(Filter::Unknown);
+            }
+            let op = CompareOp::parse(op)?;
+            let left = parse_filter_lhs(&array[1])?;
+            let right = parse_expr(&array[2])?;
+            Some(Filter::Compare(op, left, right))
+        }
+        "in" if is_expression_in(array) => {
+            Some(parse_expr(value).map_or(Filter::Unknown, Filter::Value))
+        }
         "in" | "!in" => {
             if array.len() < 3 {
                 return Some(Filter::Unknown);
@@ -458,7 +880,52 @@ fn parse_filter(value: &Value) -> Option<Filter> {
                 _ => Some(Filter::None(filters)),
             }
         }
-        _ => Some(Filter::Unknown),
+        _ => Some(match parse_expr(value) {
+            Some(expr) if expr.is_boolean() => Filter::Value(expr),
+            _ => Filter::Unknown,
+        }),
+    }
+}
+
+/// `["in", needle, haystack]` whose haystack is an expression such as
+/// `["literal", [...]]` or `["get", ...]`, rather than the legacy value list.
+fn is_expression_in(array: &[Value]) -> bool {
+    array.len() == 3 && array[2].is_array() && parse_expr(&array[2]).is_some()
+}
+
+/// Convert `literal` JSON to a value; `null` has no representation.
+fn parse_literal_value(value: &Value) -> Option<FilterValue> {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(parse_literal_value)
+            .collect::<Option<Vec<_>>>()
+            .map(FilterValue::List),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| Some((key.clone(), parse_literal_value(value)?)))
+            .collect::<Option<BTreeMap<_, _>>>()
+            .map(FilterValue::Object),
+        _ => parse_filter_value(value),
+    }
+}
+
+/// Format a number as `to-string` does: integral values without a fraction.
+fn number_to_string(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Resolve a JavaScript-style slice index against `len`.
+fn slice_index(index: f64, len: usize) -> usize {
+    let index = index.trunc();
+    if index < 0.0 {
+        len.saturating_sub((-index) as usize)
+    } else {
+        (index as usize).min(len)
     }
 }
 
@@ -491,12 +958,108 @@ fn parse_expr(value: &Value) -> Option<Expr> {
     }
     let op = array[0].as_str()?;
     match op {
+        "literal" => {
+            if array.len() != 2 {
+                return None;
+            }
+            Some(Expr::Literal(parse_literal_value(&array[1])?))
+        }
         "get" => {
-            let key = array.get(1)?.as_str()?;
-            Some(Expr::Get(key.to_string()))
+            let key = array.get(1)?.as_str()?.to_string();
+            match array.get(2) {
+                None => Some(Expr::Get(key)),
+                Some(object) => Some(Expr::GetFrom(key, Box::new(parse_expr(object)?))),
+            }
         }
+        "properties" => Some(Expr::Properties),
+        "id" => Some(Expr::Id),
         "zoom" => Some(Expr::Zoom),
         "geometry-type" => Some(Expr::Type),
+        "in" => {
+            if array.len() != 3 {
+                return None;
+            }
+            Some(Expr::In {
+                needle: Box::new(parse_expr(&array[1])?),
+                haystack: Box::new(parse_expr(&array[2])?),
+            })
+        }
+        "to-number" => {
+            let items = parse_exprs(&array[1..])?;
+            if items.is_empty() {
+                None
+            } else {
+                Some(Expr::ToNumber(items))
+            }
+        }
+        "to-string" | "to-boolean" | "downcase" | "upcase" | "length" => {
+            if array.len() != 2 {
+                return None;
+            }
+            let input = Box::new(parse_expr(&array[1])?);
+            Some(match op {
+                "to-string" => Expr::ToString(input),
+                "to-boolean" => Expr::ToBoolean(input),
+                "downcase" => Expr::Downcase(input),
+                "upcase" => Expr::Upcase(input),
+                _ => Expr::Length(input),
+            })
+        }
+        "concat" => Some(Expr::Concat(parse_exprs(&array[1..])?)),
+        "slice" => {
+            if !(3..=4).contains(&array.len()) {
+                return None;
+            }
+            Some(Expr::Slice {
+                input: Box::new(parse_expr(&array[1])?),
+                start: Box::new(parse_expr(&array[2])?),
+                end: match array.get(3) {
+                    Some(end) => Some(Box::new(parse_expr(end)?)),
+                    None => None,
+                },
+            })
+        }
+        "index-of" => {
+            if !(3..=4).contains(&array.len()) {
+                return None;
+            }
+            Some(Expr::IndexOf {
+                needle: Box::new(parse_expr(&array[1])?),
+                haystack: Box::new(parse_expr(&array[2])?),
+                from: match array.get(3) {
+                    Some(from) => Some(Box::new(parse_expr(from)?)),
+                    None => None,
+                },
+            })
+        }
+        "+" | "-" | "*" | "/" | "%" | "^" | "min" | "max" => {
+            let math = MathOp::parse(op)?;
+            let args = parse_exprs(&array[1..])?;
+            if !math.accepts_arity(args.len()) {
+                return None;
+            }
+            Some(Expr::Math(math, args))
+        }
+        "step" => {
+            if array.len() < 5 || array.len() % 2 == 0 {
+                return None;
+            }
+            let input = parse_expr(&array[1])?;
+            let first = parse_expr(&array[2])?;
+            let mut stops = Vec::new();
+            for pair in array[3..].chunks(2) {
+                let stop = pair[0].as_f64()?;
+                if stops.last().is_some_and(|(prev, _)| *prev >= stop) {
+                    return None;
+                }
+                stops.push((stop, parse_expr(&pair[1])?));
+            }
+            Some(Expr::Step {
+                input: Box::new(input),
+                first: Box::new(first),
+                stops,
+            })
+        }
         "coalesce" => {
             let mut items = Vec::new();
             for item in array.iter().skip(1) {
@@ -550,6 +1113,21 @@ fn parse_expr(value: &Value) -> Option<Expr> {
     }
 }
 
+fn parse_exprs(values: &[Value]) -> Option<Vec<Expr>> {
+    values.iter().map(parse_expr).collect()
+}
+
+impl Expr {
+    /// Whether the expression always yields a boolean, so it can stand in
+    /// for a filter.
+    fn is_boolean(&self) -> bool {
+        matches!(
+            self,
+            Expr::In { .. } | Expr::ToBoolean(_) | Expr::Literal(FilterValue::Bool(_))
+        )
+    }
+}
+
 fn parse_filter_key(value: &Value) -> Option<FilterKey> {
     if let Some(name) = value.as_str() {
         return Some(match name {
@@ -593,6 +1171,21 @@ fn eval_expr(expr: &Expr, feature: &mvt_reader::feature::Feature, zoom: u8) -> O
     match expr {
         Expr::Literal(value) => Some(value.clone()),
         Expr::Get(name) => feature_value_by_key(feature, &FilterKey::Property(name.clone()), zoom),
+        Expr::GetFrom(name, object) => match eval_expr(object, feature, zoom)? {
+            FilterValue::Object(mut map) => map.remove(name),
+            _ => None,
+        },
+        Expr::Properties => {
+            let mut map = BTreeMap::new();
+            for name in feature.properties.iter().flat_map(|props| props.keys()) {
+                let key = FilterKey::Property(name.clone());
+                if let Some(value) = feature_value_by_key(feature, &key, zoom) {
+                    map.insert(name.clone(), value);
+                }
+            }
+            Some(FilterValue::Object(map))
+        }
+        Expr::Id => feature.id.map(|id| FilterValue::Number(id as f64)),
         Expr::Zoom => Some(FilterValue::Number(zoom as f64)),
         Expr::Type => Some(FilterValue::String(feature_type(feature).to_string())),
         Expr::Coalesce(items) => {
@@ -616,6 +1209,149 @@ fn eval_expr(expr: &Expr, feature: &mvt_reader::feature::Feature, zoom: u8) -> O
             }
             eval_expr(fallback, feature, zoom)
         }
+        Expr::In { needle, haystack } => {
+            let needle = eval_expr(needle, feature, zoom)?;
+            match eval_expr(haystack, feature, zoom)? {
+                FilterValue::List(items) => Some(FilterValue::Bool(
+                    items.iter().any(|item| item.equals(&needle)),
+                )),
+                FilterValue::String(text) => match needle {
+                    FilterValue::String(part) => Some(FilterValue::Bool(text.contains(&part))),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        Expr::ToNumber(items) => {
+            for item in items {
+                match eval_expr(item, feature, zoom)? {
+                    FilterValue::Number(value) => return Some(FilterValue::Number(value)),
+                    FilterValue::Bool(value) => {
+                        return Some(FilterValue::Number(f64::from(u8::from(value))));
+                    }
+                    FilterValue::String(text) => {
+                        if let Ok(value) = text.trim().parse::<f64>() {
+                            return Some(FilterValue::Number(value));
+                        }
+                    }
+                    FilterValue::List(_) | FilterValue::Object(_) => {}
+                }
+            }
+            None
+        }
+        Expr::ToString(input) => Some(FilterValue::String(
+            eval_expr(input, feature, zoom)?.to_text(),
+        )),
+        Expr::ToBoolean(input) => Some(FilterValue::Bool(match eval_expr(input, feature, zoom)? {
+            FilterValue::Bool(value) => value,
+            FilterValue::Number(value) => value != 0.0 && !value.is_nan(),
+            FilterValue::String(text) => !text.is_empty(),
+            FilterValue::List(_) | FilterValue::Object(_) => true,
+        })),
+        Expr::Downcase(input) => match eval_expr(input, feature, zoom)? {
+            FilterValue::String(text) => Some(FilterValue::String(text.to_lowercase())),
+            _ => None,
+        },
+        Expr::Upcase(input) => match eval_expr(input, feature, zoom)? {
+            FilterValue::String(text) => Some(FilterValue::String(text.to_uppercase())),
+            _ => None,
+        },
+        Expr::Concat(items) => {
+            let mut text = String::new();
+            for item in items {
+                text.push_str(&eval_expr(item, feature, zoom)?.to_text());
+            }
+            Some(FilterValue::String(text))
+        }
+        Expr::Slice { input, start, end } => {
+            let start = eval_expr(start, feature, zoom)?.as_number()?;
+            let end = match end {
+                Some(end) => Some(eval_expr(end, feature, zoom)?.as_number()?),
+                None => None,
+            };
+            let range = |len: usize| {
+                let from = slice_index(start, len);
+                let to = end.map_or(len, |end| slice_index(end, len));
+                from..to.max(from)
+            };
+            match eval_expr(input, feature, zoom)? {
+                FilterValue::String(text) => {
+                    let chars: Vec<char> = text.chars().collect();
+                    Some(FilterValue::String(
+                        chars[range(chars.len())].iter().collect(),
+                    ))
+                }
+                FilterValue::List(items) => {
+                    Some(FilterValue::List(items[range(items.len())].to_vec()))
+                }
+                _ => None,
+            }
+        }
+        Expr::IndexOf {
+            needle,
+            haystack,
+            from,
+        } => {
+            let needle = eval_expr(needle, feature, zoom)?;
+            let from = match from {
+                Some(from) => eval_expr(from, feature, zoom)?.as_number()?,
+                None => 0.0,
+            };
+            let position = match eval_expr(haystack, feature, zoom)? {
+                FilterValue::List(items) => {
+                    let from = slice_index(from, items.len());
+                    items[from..]
+                        .iter()
+                        .position(|item| item.equals(&needle))
+                        .map(|index| index + from)
+                }
+                FilterValue::String(text) => {
+                    let FilterValue::String(part) = needle else {
+                        return None;
+                    };
+                    let chars: Vec<char> = text.chars().collect();
+                    let part: Vec<char> = part.chars().collect();
+                    let from = slice_index(from, chars.len());
+                    if part.is_empty() {
+                        Some(from)
+                    } else {
+                        chars[from..]
+                            .windows(part.len())
+                            .position(|window| window == part.as_slice())
+                            .map(|index| index + from)
+                    }
+                }
+                _ => return None,
+            };
+            Some(FilterValue::Number(
+                position.map_or(-1.0, |index| index as f64),
+            ))
+        }
+        Expr::Math(op, args) => {
+            let mut values = Vec::with_capacity(args.len());
+            for arg in args {
+                values.push(eval_expr(arg, feature, zoom)?.as_number()?);
+            }
+            Some(FilterValue::Number(op.apply(&values)))
+        }
+        Expr::Length(input) => match eval_expr(input, feature, zoom)? {
+            FilterValue::String(text) => Some(FilterValue::Number(text.chars().count() as f64)),
+            FilterValue::List(items) => Some(FilterValue::Number(items.len() as f64)),
+            _ => None,
+        },
+        Expr::Step {
+            input,
+            first,
+            stops,
+        } => {
+            let value = eval_expr(input, feature, zoom)?.as_number()?;
+            let output = stops
+                .iter()
+                .rev()
+                .find(|(stop, _)| *stop <= value)
+                .map_or(first.as_ref(), |(_, output)| output);
+            eval_expr(output, feature, zoom)
+        }
         Expr::Case { branches, fallback } => {
             for (condition, output) in branches {
                 match condition.evaluate(feature, zoom) {
@@ -630,17 +1366,32 @@ fn eval_expr(expr: &Expr, feature: &mvt_reader::feature::Feature, zoom: u8) -> O
 }
 
 pub fn read_style(path: &Path) -> Result<MapboxStyle> {
-    let contents = fs::read_to_string(path)
-        .with_context(|| format!("failed to read style file: {}", path.display()))?;
-    let value: Value = serde_json::from_str(&contents).context("parse style json")?;
+    read_style_with_source(path, None)
+}
+
+/// Read a style, keeping only layers that reference the `source` id when one
+/// is given.
+pub fn read_style_with_source(path: &Path, source: Option<&str>) -> Result<MapboxStyle> {
+    let value = read_style_json(path)?;
     let layers = value
         .get("layers")
         .and_then(|layers| layers.as_array())
         .ok_or_else(|| anyhow::anyhow!("style json missing layers array"))?;
+    if let Some(source) = source
+        && value
+            .get("sources")
+            .and_then(|sources| sources.get(source))
+            .is_none()
+    {
+        anyhow::bail!("style has no source named {source}");
+    }
 
     let mut layers_by_source_layer: HashMap<String, Vec<MapboxStyleLayer>> = HashMap::new();
     for layer in layers {
-        if layer.get("source").is_none() {
+        let Some(layer_source) = layer.get("source") else {
+            continue;
+        };
+        if source.is_some_and(|source| layer_source != source) {
             continue;
         }
         let Some(source_layer) = layer.get("source-layer").and_then(|v| v.as_str()) else {
@@ -671,6 +1422,8 @@ pub fn read_style(path: &Path) -> Result<MapboxStyle> {
                 visibility,
                 paint,
                 filter,
+                style: 0,
+                property_keys: collect_layer_property_keys(layer),
             });
     }
 
@@ -679,9 +1432,206 @@ pub fn read_style(path: &Path) -> Result<MapboxStyle> {
     }
     Ok(MapboxStyle {
         layers_by_source_layer,
+        style_names: vec![path.display().to_string()],
     })
 }
 
+/// Property keys a style layer reads in its `filter`, `layout` and `paint`,
+/// or `None` when a key is computed at runtime or `["properties"]` is used.
+fn collect_layer_property_keys(layer: &Value) -> Option<HashSet<String>> {
+    let mut keys = HashSet::new();
+    if let Some(filter) = layer.get("filter") {
+        collect_filter_keys(filter, &mut keys)?;
+    }
+    for section in ["layout", "paint"] {
+        for value in layer
+            .get(section)
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|props| props.values())
+        {
+            collect_expression_keys(value, &mut keys)?;
+        }
+    }
+    Some(keys)
+}
+
+fn collect_filter_keys(value: &Value, keys: &mut HashSet<String>) -> Option<()> {
+    let Some(array) = value.as_array() else {
+        return collect_expression_keys(value, keys);
+    };
+    match array.first().and_then(Value::as_str) {
+        Some("all" | "any" | "none" | "!") => {
+            for item in &array[1..] {
+                collect_filter_keys(item, keys)?;
+            }
+            Some(())
+        }
+        Some("==" | "!=" | "<" | "<=" | ">" | ">=" | "in" | "!in" | "has" | "!has") => {
+            // Legacy filters name the property directly.
+            if let Some(key) = array.get(1).and_then(Value::as_str)
+                && !matches!(key, "$type" | "$id" | "zoom")
+            {
+                keys.insert(key.to_string());
+            }
+            collect_expression_keys(value, keys)
+        }
+        _ => collect_expression_keys(value, keys),
+    }
+}
+
+fn collect_expression_keys(value: &Value, keys: &mut HashSet<String>) -> Option<()> {
+    match value {
+        // Token strings such as `text-field: "{name}"`.
+        Value::String(text) => {
+            for token in text.split('{').skip(1) {
+                if let Some((key, _)) = token.split_once('}') {
+                    keys.insert(key.to_string());
+                }
+            }
+            Some(())
+        }
+        // Legacy functions: `{"property": "rank", "stops": [...]}`.
+        Value::Object(map) => {
+            if let Some(key) = map.get("property").and_then(Value::as_str) {
+                keys.insert(key.to_string());
+            }
+            for (name, item) in map {
+                if name != "property" && name != "type" {
+                    collect_expression_keys(item, keys)?;
+                }
+            }
+            Some(())
+        }
+        Value::Array(array) => match array.first().and_then(Value::as_str) {
+            Some("literal") => Some(()),
+            Some("properties") => None,
+            Some("get" | "has") => {
+                let key = array.get(1)?.as_str()?;
+                keys.insert(key.to_string());
+                match array.get(2) {
+                    Some(object) if object != &serde_json::json!(["properties"]) => {
+                        collect_expression_keys(object, keys)
+                    }
+                    _ => Some(()),
+                }
+            }
+            Some(_) => {
+                for item in &array[1..] {
+                    collect_expression_keys(item, keys)?;
+                }
+                Some(())
+            }
+            None => {
+                for item in array {
+                    collect_expression_keys(item, keys)?;
+                }
+                Some(())
+            }
+        },
+        _ => Some(()),
+    }
+}
+
+fn read_style_json(path: &Path) -> Result<Value> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read style file: {}", path.display()))?;
+    serde_json::from_str(&contents).context("parse style json")
+}
+
+/// Pick the style's vector source that serves `input`: the one whose id, `url`
+/// file name, or `tiles` path segment before `{z}` matches the input file stem
+/// or the tileset's `name`/`id` metadata. Returns `None` when the style has
+/// at most one vector source or no single source matches; the latter is
+/// logged as a warning listing the candidates.
+pub fn match_style_source(
+    path: &Path,
+    input: &Path,
+    metadata: &BTreeMap<String, String>,
+) -> Result<Option<String>> {
+    let value = read_style_json(path)?;
+    let Some(sources) = value.get("sources").and_then(Value::as_object) else {
+        return Ok(None);
+    };
+    let vector_sources: Vec<(&String, &Value)> = sources
+        .iter()
+        .filter(|(_, source)| source.get("type").and_then(Value::as_str) == Some("vector"))
+        .collect();
+    if vector_sources.len() <= 1 {
+        return Ok(None);
+    }
+
+    let mut wanted: Vec<String> = ["name", "id"]
+        .iter()
+        .filter_map(|key| metadata.get(*key))
+        .map(|value| value.to_lowercase())
+        .collect();
+    if let Some(stem) = input.file_stem().and_then(|stem| stem.to_str()) {
+        wanted.push(stem.to_lowercase());
+    }
+    let candidates = vector_sources
+        .iter()
+        .map(|(id, _)| id.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let matches: Vec<&String> = vector_sources
+        .into_iter()
+        .filter(|(id, source)| {
+            style_source_names(id, source)
+                .iter()
+                .any(|name| wanted.contains(name))
+        })
+        .map(|(id, _)| id)
+        .collect();
+    let matched = match matches.as_slice() {
+        [id] => return Ok(Some((*id).clone())),
+        [] => "none of them matches".to_string(),
+        several => format!(
+            "{} all match",
+            several
+                .iter()
+                .map(|id| id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    warn!(
+        "style {} has vector sources {candidates}, and {matched} {}; using layers of every source, pass --source to pick one",
+        path.display(),
+        input.display()
+    );
+    Ok(None)
+}
+
+/// Lowercased names a style source goes by: its id, the file stem of its
+/// `url`, and the path segment before `{z}` in each `tiles` template.
+fn style_source_names(id: &str, source: &Value) -> Vec<String> {
+    let mut names = vec![id.to_lowercase()];
+    if let Some(url) = source.get("url").and_then(Value::as_str) {
+        let url = url.split(['?', '#']).next().unwrap_or(url);
+        if let Some(last) = url.rsplit('/').find(|segment| !segment.is_empty()) {
+            let last = last.trim_matches(|c| c == '{' || c == '}');
+            let stem = last.split('.').next().unwrap_or(last);
+            names.push(stem.to_lowercase());
+        }
+    }
+    for template in source
+        .get("tiles")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        let segments: Vec<&str> = template.split('/').collect();
+        if let Some(index) = segments.iter().position(|segment| *segment == "{z}")
+            && index > 0
+        {
+            names.push(segments[index - 1].to_lowercase());
+        }
+    }
+    names
+}
+
 pub fn read_style_source_layers(path: &Path) -> Result<HashSet<String>> {
     Ok(read_style(path)?.source_layers())
 }
@@ -0,0 +1,205 @@
+//! Format-neutral tile access: a [`TileSource`] enumerates and reads tiles,
+//! a [`TileSink`] writes them, and [`copy_tiles`] connects the two.
+//! [`prune_tiles`] and [`simplify_tiles`] stream tiles between them through
+//! parallel workers.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::thread;
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{bounded, unbounded};
+
+use crate::directory::{DirectorySink, DirectorySource};
+use crate::format::TileFormat;
+use crate::mbtiles::processing::{PipelineOptions, StylePrune, TileTransform, TilesetSimplify};
+use crate::mbtiles::{
+    MbtilesScheme, MbtilesSink, MbtilesSource, PruneOptions, PruneStats, SimplifyOptions,
+    TileCoord, TilesetSimplifyStats,
+};
+use crate::pmtiles::{
+    PmtilesSink, PmtilesSource, decode_tile_payload_pmtiles, encode_tile_payload_pmtiles,
+};
+use crate::style::MapboxStyle;
+
+/// A tile addressed in XYZ with its bytes as stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileRecord {
+    pub coord: TileCoord,
+    pub data: Vec<u8>,
+    /// Compression of `data`: 0 none, 1 gzip, 2 brotli, 3 zstd.
+    pub compression: u8,
+}
+
+/// Enumerates and reads tiles from a tileset.
+pub trait TileSource {
+    /// Tileset metadata as MBTiles-style name/value rows.
+    fn metadata(&self) -> Result<BTreeMap<String, String>>;
+
+    /// Visit every tile once, in the source's storage order.
+    fn for_each_tile(&mut self, visit: &mut dyn FnMut(TileRecord) -> Result<()>) -> Result<()>;
+
+    /// Read one tile, or `None` when the tileset does not contain it.
+    fn read_tile(&mut self, coord: TileCoord) -> Result<Option<TileRecord>>;
+}
+
+/// Writes tiles into a tileset.
+pub trait TileSink {
+    fn write_tile(&mut self, tile: &TileRecord) -> Result<()>;
+
+    /// Flush pending tiles; no tiles may be written afterwards.
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// Open `path` as a tile source. `scheme` applies to MBTiles only.
+pub fn open_source(
+    path: &Path,
+    format: TileFormat,
+    scheme: MbtilesScheme,
+) -> Result<Box<dyn TileSource>> {
+    Ok(match format {
+        TileFormat::Mbtiles => Box::new(MbtilesSource::open(path, scheme)?),
+        TileFormat::Pmtiles => Box::new(PmtilesSource::open(path)?),
+        TileFormat::Directory => Box::new(DirectorySource::open(path)?),
+    })
+}
+
+/// Create a tile sink at `path` carrying `metadata`. `scheme` applies to
+/// MBTiles only.
+pub fn create_sink(
+    path: &Path,
+    format: TileFormat,
+    metadata: BTreeMap<String, String>,
+    scheme: MbtilesScheme,
+) -> Result<Box<dyn TileSink>> {
+    Ok(match format {
+        TileFormat::Mbtiles => Box::new(MbtilesSink::create(path, metadata, scheme)?),
+        TileFormat::Pmtiles => Box::new(PmtilesSink::create(path, metadata)?),
+        TileFormat::Directory => Box::new(DirectorySink::create(path, metadata)?),
+    })
+}
+
+/// Write every tile of `source` into `sink` and finish it, returning the
+/// number of tiles copied.
+pub fn copy_tiles(source: &mut dyn TileSource, sink: &mut dyn TileSink) -> Result<u64> {
+    let mut copied = 0u64;
+    source.for_each_tile(&mut |tile| {
+        sink.write_tile(&tile)?;
+        copied += 1;
+        Ok(())
+    })?;
+    sink.finish()?;
+    Ok(copied)
+}
+
+/// Prune every tile of `source` against `style` into `sink` and finish it.
+pub fn prune_tiles(
+    source: &mut dyn TileSource,
+    sink: &mut dyn TileSink,
+    style: &MapboxStyle,
+    apply_filters: bool,
+    options: PruneOptions,
+) -> Result<PruneStats> {
+    let pipeline = PipelineOptions::from(&options);
+    run_tile_pipeline(
+        source,
+        sink,
+        StylePrune::new(style, apply_filters, &options),
+        pipeline,
+    )
+}
+
+/// Simplify every tile of `source` with per-layer/zoom tolerances into `sink`
+/// and finish it.
+pub fn simplify_tiles(
+    source: &mut dyn TileSource,
+    sink: &mut dyn TileSink,
+    options: SimplifyOptions,
+) -> Result<TilesetSimplifyStats> {
+    let pipeline = PipelineOptions::from(&options);
+    run_tile_pipeline(source, sink, TilesetSimplify(options), pipeline)
+}
+
+/// Read tiles from `source` on the calling thread, run `transform` on
+/// `options.threads` workers and write the results to `sink` as they arrive.
+/// Only `io_batch` tiles wait for a worker, so tiles stream through instead
+/// of being staged.
+fn run_tile_pipeline<T: TileTransform>(
+    source: &mut dyn TileSource,
+    sink: &mut dyn TileSink,
+    transform: T,
+    options: PipelineOptions,
+) -> Result<T::Stats> {
+    let (tx_in, rx_in) = bounded::<TileRecord>(options.io_batch.max(1) as usize);
+    // Unbounded so workers never block on the reading thread, which drains
+    // results between reads.
+    let (tx_out, rx_out) = unbounded::<Result<Option<TileRecord>>>();
+
+    let mut worker_handles = Vec::with_capacity(options.threads.max(1));
+    for _ in 0..options.threads.max(1) {
+        let rx_in = rx_in.clone();
+        let tx_out = tx_out.clone();
+        let transform = transform.clone();
+        let drop_empty_tiles = options.drop_empty_tiles;
+        worker_handles.push(thread::spawn(move || -> T::Stats {
+            let mut stats = T::Stats::default();
+            while let Ok(tile) = rx_in.recv() {
+                let TileCoord { zoom, x, y } = tile.coord;
+                let output = (|| -> Result<Option<TileRecord>> {
+                    let payload = decode_tile_payload_pmtiles(&tile.data, tile.compression)
+                        .with_context(|| format!("decode tile {zoom}/{x}/{y}"))?;
+                    let encoded = transform.transform(&payload, zoom, &mut stats)?;
+                    if encoded.empty && drop_empty_tiles {
+                        return Ok(None);
+                    }
+                    Ok(Some(TileRecord {
+                        coord: tile.coord,
+                        data: encode_tile_payload_pmtiles(&encoded.bytes, tile.compression)?,
+                        compression: tile.compression,
+                    }))
+                })();
+                let failed = output.is_err();
+                if tx_out.send(output).is_err() || failed {
+                    break;
+                }
+            }
+            stats
+        }));
+    }
+    drop(rx_in);
+    drop(tx_out);
+
+    let mut write = |output: Result<Option<TileRecord>>| -> Result<()> {
+        match output? {
+            Some(tile) => sink.write_tile(&tile),
+            None => Ok(()),
+        }
+    };
+    let read = source.for_each_tile(&mut |tile| {
+        for output in rx_out.try_iter() {
+            write(output)?;
+        }
+        tx_in.send(tile).context("tile workers stopped")
+    });
+    drop(tx_in);
+    let written = read.and_then(|()| rx_out.iter().try_for_each(&mut write));
+    // A failed worker stops sending, so its error may still be queued behind
+    // the read error it caused.
+    let written = written.map_err(|err| rx_out.try_iter().find_map(Result::err).unwrap_or(err));
+    drop(rx_out);
+
+    let mut panicked = false;
+    let mut stats = T::Stats::default();
+    for handle in worker_handles {
+        match handle.join() {
+            Ok(worker_stats) => T::merge(&mut stats, worker_stats),
+            Err(_) => panicked = true,
+        }
+    }
+    written?;
+    if panicked {
+        anyhow::bail!("tile pipeline thread panicked");
+    }
+    sink.finish()?;
+    Ok(stats)
+}
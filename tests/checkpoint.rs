@@ -0,0 +1,446 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use mvt::{GeomEncoder, GeomType, Tile};
+use mvt_reader::Reader;
+
+use vt_optimizer::checkpoint::{
+    Checkpoint, CheckpointConfig, CheckpointProgress, PendingProgress, RowidRangeProgress,
+    TileMarker, checkpoint_identity, prune_options_fingerprint,
+};
+use vt_optimizer::format::TileFormat;
+use vt_optimizer::mbtiles::{MbtilesScheme, PruneOptions, prune_mbtiles_with_checkpoint};
+use vt_optimizer::pmtiles::{
+    mbtiles_to_pmtiles, prune_pmtiles_layer_only, prune_pmtiles_with_checkpoint, tile_id_from_xyz,
+};
+use vt_optimizer::style::{MapboxStyle, read_style};
+
+const STYLE_HASH: &str = "style-v1";
+
+fn create_layer_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+
+    let layer = tile.create_layer("roads");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(1.0, 2.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.add_tag_string("class", "primary");
+    let layer = feature.into_layer();
+    tile.add_layer(layer).expect("add roads");
+
+    let layer = tile.create_layer("buildings");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(3.0, 4.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.add_tag_string("height", "10");
+    let layer = feature.into_layer();
+    tile.add_layer(layer).expect("add buildings");
+
+    tile.to_bytes().expect("tile bytes")
+}
+
+/// Five z2 tiles; rowid N holds tile 2/N/0.
+fn create_input_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        INSERT INTO metadata (name, value) VALUES ('name', 'sample');
+        ",
+    )
+    .expect("schema");
+    let data = create_layer_tile();
+    for rowid in 1..=5i64 {
+        conn.execute(
+            "INSERT INTO tiles (rowid, zoom_level, tile_column, tile_row, tile_data) VALUES (?1, 2, ?2, 0, ?3)",
+            (rowid, rowid % 4, data.clone()),
+        )
+        .expect("tile insert");
+    }
+    conn.execute("UPDATE tiles SET tile_row = 1 WHERE rowid = 5", [])
+        .expect("unique coords");
+}
+
+fn write_style(dir: &Path) -> MapboxStyle {
+    let path = dir.join("style.json");
+    fs::write(
+        &path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    read_style(&path).expect("read style")
+}
+
+fn prune_options() -> PruneOptions {
+    PruneOptions {
+        threads: 2,
+        io_batch: 2,
+        ..PruneOptions::default()
+    }
+}
+
+fn config(path: &Path, resume: bool) -> CheckpointConfig {
+    CheckpointConfig {
+        path: path.to_path_buf(),
+        resume,
+        style_hash: STYLE_HASH.to_string(),
+    }
+}
+
+fn mbtiles_identity(
+    input: &Path,
+    output: &Path,
+    style_hash: &str,
+) -> vt_optimizer::checkpoint::CheckpointIdentity {
+    let options = prune_options();
+    checkpoint_identity(
+        input,
+        TileFormat::Mbtiles,
+        output,
+        TileFormat::Mbtiles,
+        style_hash,
+        prune_options_fingerprint(
+            false,
+            options.keep_unknown_filters,
+            options.drop_empty_tiles,
+            options.prune_attributes,
+            options.keep_languages.as_deref(),
+            options.rules.as_ref(),
+        ),
+    )
+    .expect("identity")
+}
+
+/// Copy `reference` to `output` keeping only the tiles produced from `rowids`.
+fn partial_output(input: &Path, reference: &Path, output: &Path, rowids: &[i64]) {
+    fs::copy(reference, output).expect("copy reference");
+    let conn = rusqlite::Connection::open(output).expect("open output");
+    conn.execute("ATTACH DATABASE ?1 AS input", [input.to_str().unwrap()])
+        .expect("attach input");
+    let keep = rowids
+        .iter()
+        .map(|rowid| rowid.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    conn.execute_batch(&format!(
+        "DELETE FROM tiles WHERE (zoom_level, tile_column, tile_row) NOT IN \
+         (SELECT zoom_level, tile_column, tile_row FROM input.tiles WHERE rowid IN ({keep}))"
+    ))
+    .expect("trim output");
+}
+
+fn tile_rows(path: &Path) -> Vec<(i64, i64, i64, Vec<u8>)> {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    let mut stmt = conn
+        .prepare(
+            "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles ORDER BY zoom_level, tile_column, tile_row",
+        )
+        .expect("prepare");
+    stmt.query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    })
+    .expect("query")
+    .map(|row| row.expect("row"))
+    .collect()
+}
+
+fn run_reference(dir: &Path, input: &Path, style: &MapboxStyle) -> std::path::PathBuf {
+    let reference = dir.join("reference.mbtiles");
+    prune_mbtiles_with_checkpoint(input, &reference, style, false, prune_options(), None)
+        .expect("reference prune");
+    reference
+}
+
+#[test]
+fn checkpoint_is_removed_after_successful_mbtiles_run() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let state = dir.path().join("state.json");
+    create_input_mbtiles(&input);
+    let style = write_style(dir.path());
+
+    prune_mbtiles_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        prune_options(),
+        Some(&config(&state, false)),
+    )
+    .expect("prune");
+
+    assert!(!state.exists());
+    let reference = run_reference(dir.path(), &input, &style);
+    assert_eq!(tile_rows(&output), tile_rows(&reference));
+}
+
+#[test]
+fn resume_mbtiles_skips_committed_rows() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let state = dir.path().join("state.json");
+    create_input_mbtiles(&input);
+    let style = write_style(dir.path());
+    let reference = run_reference(dir.path(), &input, &style);
+    partial_output(&input, &reference, &output, &[1, 2, 4]);
+
+    let checkpoint = Checkpoint::new(
+        mbtiles_identity(&input, &output, STYLE_HASH),
+        CheckpointProgress::Rowid {
+            ranges: vec![RowidRangeProgress {
+                start: 1,
+                end: 5,
+                committed: Some(2),
+                written_above: vec![4],
+            }],
+        },
+    );
+    checkpoint.save(&state).expect("save checkpoint");
+
+    prune_mbtiles_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        prune_options(),
+        Some(&config(&state, true)),
+    )
+    .expect("resume");
+
+    assert!(!state.exists());
+    let rows = tile_rows(&output);
+    assert_eq!(rows.len(), 5);
+    assert_eq!(rows, tile_rows(&reference));
+    let reader = Reader::new(rows[0].3.clone()).expect("decode");
+    let layers = reader.get_layer_metadata().expect("layers");
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].name, "roads");
+}
+
+#[test]
+fn resume_mbtiles_adopts_pending_progress_when_marker_was_committed() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let state = dir.path().join("state.json");
+    create_input_mbtiles(&input);
+    let style = write_style(dir.path());
+    let reference = run_reference(dir.path(), &input, &style);
+    partial_output(&input, &reference, &output, &[1, 2, 3]);
+
+    let range = |committed| RowidRangeProgress {
+        start: 1,
+        end: 5,
+        committed: Some(committed),
+        written_above: Vec::new(),
+    };
+    let mut checkpoint = Checkpoint::new(
+        mbtiles_identity(&input, &output, STYLE_HASH),
+        CheckpointProgress::Rowid {
+            ranges: vec![range(1)],
+        },
+    );
+    checkpoint.pending = Some(PendingProgress {
+        progress: CheckpointProgress::Rowid {
+            ranges: vec![range(3)],
+        },
+        marker: TileMarker {
+            zoom: 2,
+            x: 3,
+            y: 0,
+        },
+    });
+    checkpoint.save(&state).expect("save checkpoint");
+
+    prune_mbtiles_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        prune_options(),
+        Some(&config(&state, true)),
+    )
+    .expect("resume");
+
+    assert_eq!(tile_rows(&output), tile_rows(&reference));
+}
+
+#[test]
+fn resume_mbtiles_after_final_commit_does_not_duplicate_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let state = dir.path().join("state.json");
+    create_input_mbtiles(&input);
+    let style = write_style(dir.path());
+    let reference = run_reference(dir.path(), &input, &style);
+    fs::copy(&reference, &output).expect("copy reference");
+
+    // Crash after the last batch committed but before the checkpoint was removed.
+    let range = |committed| RowidRangeProgress {
+        start: 1,
+        end: 5,
+        committed: Some(committed),
+        written_above: Vec::new(),
+    };
+    let mut checkpoint = Checkpoint::new(
+        mbtiles_identity(&input, &output, STYLE_HASH),
+        CheckpointProgress::Rowid {
+            ranges: vec![range(2)],
+        },
+    );
+    checkpoint.pending = Some(PendingProgress {
+        progress: CheckpointProgress::Rowid {
+            ranges: vec![range(5)],
+        },
+        marker: TileMarker {
+            zoom: 2,
+            x: 1,
+            y: 1,
+        },
+    });
+    checkpoint.save(&state).expect("save checkpoint");
+
+    prune_mbtiles_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        prune_options(),
+        Some(&config(&state, true)),
+    )
+    .expect("resume");
+
+    assert!(!state.exists());
+    assert_eq!(tile_rows(&output), tile_rows(&reference));
+}
+
+#[test]
+fn resume_refuses_mismatched_fingerprint() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let state = dir.path().join("state.json");
+    create_input_mbtiles(&input);
+    let style = write_style(dir.path());
+    let reference = run_reference(dir.path(), &input, &style);
+    partial_output(&input, &reference, &output, &[1]);
+
+    let checkpoint = Checkpoint::new(
+        mbtiles_identity(&input, &output, "another-style"),
+        CheckpointProgress::Rowid {
+            ranges: vec![RowidRangeProgress {
+                start: 1,
+                end: 5,
+                committed: Some(1),
+                written_above: Vec::new(),
+            }],
+        },
+    );
+    checkpoint.save(&state).expect("save checkpoint");
+
+    let err = prune_mbtiles_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        prune_options(),
+        Some(&config(&state, true)),
+    )
+    .expect_err("mismatch must fail");
+    assert!(err.to_string().contains("style hash"), "{err}");
+    assert!(state.exists());
+}
+
+#[test]
+fn resume_pmtiles_continues_after_committed_tile_id() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    let input = dir.path().join("input.pmtiles");
+    let output = dir.path().join("output.pmtiles");
+    let reference = dir.path().join("reference.pmtiles");
+    let state = dir.path().join("state.json");
+    create_input_mbtiles(&mbtiles);
+    mbtiles_to_pmtiles(&mbtiles, &input).expect("convert");
+    let style = write_style(dir.path());
+    prune_pmtiles_layer_only(&input, &reference, &style, false, true).expect("reference");
+
+    // Seed the temporary data file with the first tile as an interrupted run
+    // would have left it: tile_id (u64 LE), length (u32 LE), bytes.
+    // MBTiles rows are TMS, so flip them to find the lowest XYZ tile_id.
+    let pruned_mbtiles = run_reference(dir.path(), &mbtiles, &style);
+    let (first_tile_id, tile) = tile_rows(&pruned_mbtiles)
+        .into_iter()
+        .map(|(z, x, row, data)| {
+            let (z, x, row) = (z as u8, x as u32, row as u32);
+            let y = MbtilesScheme::Tms.row_to_y(z, row);
+            (tile_id_from_xyz(z, x, y), data)
+        })
+        .min_by_key(|(tile_id, _)| *tile_id)
+        .expect("first tile");
+    let mut partial = fs::File::create(dir.path().join("output.pmtiles.partial")).expect("partial");
+    partial
+        .write_all(&first_tile_id.to_le_bytes())
+        .expect("write id");
+    partial
+        .write_all(&(tile.len() as u32).to_le_bytes())
+        .expect("write len");
+    partial.write_all(&tile).expect("write tile");
+    drop(partial);
+
+    let mut identity = checkpoint_identity(
+        &input,
+        TileFormat::Pmtiles,
+        &output,
+        TileFormat::Pmtiles,
+        STYLE_HASH,
+        prune_options_fingerprint(false, true, false, false, None, None),
+    )
+    .expect("identity");
+    identity.output.temporary_path = Some(
+        dir.path()
+            .join("output.pmtiles.partial")
+            .display()
+            .to_string(),
+    );
+    let checkpoint = Checkpoint::new(
+        identity,
+        CheckpointProgress::TileId {
+            committed: Some(first_tile_id),
+            data_bytes: (12 + tile.len()) as u64,
+        },
+    );
+    checkpoint.save(&state).expect("save checkpoint");
+
+    prune_pmtiles_with_checkpoint(
+        &input,
+        &output,
+        &style,
+        false,
+        prune_options(),
+        Some(&config(&state, true)),
+    )
+    .expect("resume");
+
+    assert!(!state.exists());
+    assert!(!dir.path().join("output.pmtiles.partial").exists());
+    assert_eq!(
+        fs::read(&output).expect("output"),
+        fs::read(&reference).expect("reference")
+    );
+}
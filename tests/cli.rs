@@ -1,7 +1,10 @@
 use clap::{CommandFactory, Parser};
 
 use vt_optimizer::cli::ReportFormat;
-use vt_optimizer::cli::{Cli, Command, StyleMode, TileInfoFormat, UnknownFilterMode};
+use vt_optimizer::cli::{
+    Cli, Command, CompressionArg, ExportFormatArg, MbtilesSchemeArg, SimplifyAlgorithmArg,
+    StyleMode, TileInfoFormat, UnknownFilterMode,
+};
 
 #[test]
 fn parse_optimize_minimal() {
@@ -13,12 +16,16 @@ fn parse_optimize_minimal() {
             assert_eq!(args.input_format, None);
             assert_eq!(args.output_format, None);
             assert_eq!(args.report_format, ReportFormat::Text);
-            assert_eq!(args.style, None);
+            assert!(args.style.is_empty());
+            assert_eq!(args.source, None);
             assert_eq!(args.style_mode, StyleMode::LayerFilter);
             assert_eq!(args.unknown_filter, UnknownFilterMode::Keep);
             assert_eq!(args.max_tile_bytes, 1_280_000);
             assert_eq!(args.threads, None);
             assert_eq!(args.io_batch, 1_000);
+            assert!(!args.prune_attributes);
+            assert!(args.keep_languages.is_empty());
+            assert_eq!(args.rules, None);
             assert_eq!(args.checkpoint, None);
             assert!(!args.resume);
         }
@@ -42,6 +49,10 @@ fn parse_optimize_options() {
         "json",
         "--style",
         "style.json",
+        "--style",
+        "dark.json",
+        "--source",
+        "overlay",
         "--style-mode",
         "layer+filter",
         "--unknown-filter",
@@ -52,6 +63,11 @@ fn parse_optimize_options() {
         "8",
         "--io-batch",
         "200",
+        "--prune-attributes",
+        "--keep-languages",
+        "en,ja,de",
+        "--rules",
+        "rules.toml",
         "--checkpoint",
         "state.json",
         "--resume",
@@ -64,12 +80,22 @@ fn parse_optimize_options() {
             assert_eq!(args.input_format.unwrap(), "mbtiles");
             assert_eq!(args.output_format.unwrap(), "pmtiles");
             assert_eq!(args.report_format, ReportFormat::Json);
-            assert_eq!(args.style.unwrap().as_os_str(), "style.json");
+            assert_eq!(
+                args.style,
+                vec![
+                    std::path::PathBuf::from("style.json"),
+                    std::path::PathBuf::from("dark.json")
+                ]
+            );
+            assert_eq!(args.source.as_deref(), Some("overlay"));
             assert_eq!(args.style_mode, StyleMode::LayerFilter);
             assert_eq!(args.unknown_filter, UnknownFilterMode::Drop);
             assert_eq!(args.max_tile_bytes, 2048);
             assert_eq!(args.threads, Some(8));
             assert_eq!(args.io_batch, 200);
+            assert!(args.prune_attributes);
+            assert_eq!(args.keep_languages, vec!["en", "ja", "de"]);
+            assert_eq!(args.rules.unwrap().as_os_str(), "rules.toml");
             assert_eq!(args.checkpoint.unwrap().as_os_str(), "state.json");
             assert!(args.resume);
         }
@@ -255,3 +281,236 @@ fn inspect_help_describes_fields() {
     assert!(help.contains("Include per-layer stats"));
     assert!(help.contains("Tile summary detail level"));
 }
+
+#[test]
+fn parse_verify_report_format() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "verify",
+        "planet.pmtiles",
+        "--report-format",
+        "ndjson",
+    ]);
+    match cli.command {
+        Some(Command::Verify(args)) => {
+            assert_eq!(args.input.as_os_str(), "planet.pmtiles");
+            assert_eq!(args.report_format, ReportFormat::Ndjson);
+        }
+        _ => panic!("expected verify command"),
+    }
+}
+
+#[test]
+fn parse_legacy_checkpoint_resume() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "-m",
+        "planet.mbtiles",
+        "-s",
+        "style.json",
+        "--checkpoint",
+        "state.json",
+        "--resume",
+    ]);
+    assert!(cli.command.is_none());
+    assert_eq!(cli.checkpoint.unwrap().as_os_str(), "state.json");
+    assert!(cli.resume);
+}
+
+#[test]
+fn parse_mbtiles_scheme() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "copy",
+        "planet.mbtiles",
+        "--output",
+        "planet.pmtiles",
+        "--mbtiles-scheme",
+        "xyz",
+    ]);
+    match cli.command {
+        Some(Command::Copy(args)) => {
+            assert_eq!(args.mbtiles_scheme, MbtilesSchemeArg::Xyz);
+        }
+        _ => panic!("expected copy command"),
+    }
+
+    let cli = Cli::parse_from(["vt-optimizer", "inspect", "planet.mbtiles"]);
+    match cli.command {
+        Some(Command::Inspect(args)) => {
+            assert_eq!(args.mbtiles_scheme, MbtilesSchemeArg::Tms);
+        }
+        _ => panic!("expected inspect command"),
+    }
+
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "optimize",
+        "planet.pmtiles",
+        "--output",
+        "planet.mbtiles",
+        "--style",
+        "style.json",
+        "--mbtiles-scheme",
+        "xyz",
+    ]);
+    match cli.command {
+        Some(Command::Optimize(args)) => {
+            assert_eq!(args.mbtiles_scheme, MbtilesSchemeArg::Xyz);
+        }
+        _ => panic!("expected optimize command"),
+    }
+}
+
+#[test]
+fn parse_copy_compression_options() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "copy",
+        "input.pmtiles",
+        "--output",
+        "output.pmtiles",
+        "--internal-compression",
+        "zstd",
+        "--tile-compression",
+        "gzip",
+    ]);
+    match cli.command {
+        Some(Command::Copy(args)) => {
+            assert_eq!(args.internal_compression, Some(CompressionArg::Zstd));
+            assert_eq!(args.tile_compression, Some(CompressionArg::Gzip));
+        }
+        _ => panic!("expected copy command"),
+    }
+}
+
+#[test]
+fn parse_export_options() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "export",
+        "planet.pmtiles",
+        "--zoom",
+        "5",
+        "--bbox",
+        "-10,-5,10,5",
+        "--layer",
+        "roads,water",
+        "--format",
+        "geojsonseq",
+    ]);
+    match cli.command {
+        Some(Command::Export(args)) => {
+            assert_eq!(args.zoom, Some(5));
+            assert_eq!(args.bbox.as_deref(), Some("-10,-5,10,5"));
+            assert_eq!(args.layer, vec!["roads", "water"]);
+            assert_eq!(args.format, ExportFormatArg::Geojsonseq);
+        }
+        _ => panic!("expected export command"),
+    }
+
+    assert!(
+        Cli::try_parse_from([
+            "vt-optimizer",
+            "export",
+            "planet.pmtiles",
+            "--tile",
+            "1/0/0",
+            "--zoom",
+            "1",
+        ])
+        .is_err()
+    );
+}
+
+#[test]
+fn parse_simplify_tileset_options() {
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "simplify",
+        "planet.mbtiles",
+        "--tolerance",
+        "1",
+        "--tolerance-rule",
+        "landuse@0-8=4",
+        "--tolerance-rule",
+        "@12=0.5",
+        "--maxzoom",
+        "12",
+        "--threads",
+        "4",
+    ]);
+    match cli.command {
+        Some(Command::Simplify(args)) => {
+            assert_eq!((args.z, args.x, args.y), (None, None, None));
+            assert_eq!(args.tolerance, Some(1.0));
+            assert_eq!(args.tolerance_rules, vec!["landuse@0-8=4", "@12=0.5"]);
+            assert_eq!(args.minzoom, None);
+            assert_eq!(args.maxzoom, Some(12));
+            assert_eq!(args.threads, Some(4));
+            assert_eq!(args.io_batch, 1_000);
+            assert_eq!(args.algorithm, SimplifyAlgorithmArg::DouglasPeucker);
+            assert!(!args.preserve_topology);
+        }
+        _ => panic!("expected simplify command"),
+    }
+
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "simplify",
+        "planet.mbtiles",
+        "--tolerance",
+        "2",
+        "--algorithm",
+        "visvalingam",
+        "--preserve-topology",
+    ]);
+    match cli.command {
+        Some(Command::Simplify(args)) => {
+            assert_eq!(args.algorithm, SimplifyAlgorithmArg::Visvalingam);
+            assert!(args.preserve_topology);
+        }
+        _ => panic!("expected simplify command"),
+    }
+
+    let cli = Cli::parse_from([
+        "vt-optimizer",
+        "simplify",
+        "planet.mbtiles",
+        "--tolerance",
+        "1",
+        "--simplify-layer",
+        "roads",
+    ]);
+    match cli.command {
+        Some(Command::Simplify(args)) => {
+            assert_eq!(args.simplify_layers, vec!["roads"]);
+            assert!(args.layer.is_empty());
+        }
+        _ => panic!("expected simplify command"),
+    }
+    // --layer keeps layers of one tile; --simplify-layer scopes a tileset.
+    for flags in [
+        &["--layer", "roads"][..],
+        &[
+            "--simplify-layer",
+            "roads",
+            "--z",
+            "1",
+            "--x",
+            "0",
+            "--y",
+            "0",
+        ][..],
+    ] {
+        let mut argv = vec![
+            "vt-optimizer",
+            "simplify",
+            "planet.mbtiles",
+            "--tolerance",
+            "1",
+        ];
+        argv.extend_from_slice(flags);
+        assert!(Cli::try_parse_from(argv).is_err(), "{flags:?}");
+    }
+}
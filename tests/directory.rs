@@ -0,0 +1,384 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use mvt::{GeomEncoder, GeomType, Tile};
+use vt_optimizer::directory::{DirectorySink, DirectorySource, inspect_directory_with_options};
+use vt_optimizer::format::TileFormat;
+use vt_optimizer::mbtiles::{InspectOptions, MbtilesScheme, PruneOptions, TileCoord};
+use vt_optimizer::pmtiles::{mbtiles_to_pmtiles, read_header};
+use vt_optimizer::style::read_style;
+use vt_optimizer::tiles::{TileSource, copy_tiles, create_sink, open_source, prune_tiles};
+
+fn create_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("roads");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(1.0, 2.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let layer = layer.into_feature(geom).into_layer();
+    tile.add_layer(layer).expect("add roads");
+    tile.to_bytes().expect("tile bytes")
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).expect("gzip");
+    encoder.finish().expect("gzip finish")
+}
+
+fn create_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        r#"
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        INSERT INTO metadata (name, value) VALUES
+            ('name', 'sample'),
+            ('format', 'pbf'),
+            ('json', '{"vector_layers":[{"id":"roads","fields":{}}]}');
+        "#,
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, 0, 0, ?1), (1, 1, 1, ?2)",
+        (gzip(&create_tile()), create_tile()),
+    )
+    .expect("tile insert");
+}
+
+type TileRow = (u8, u32, u32, Vec<u8>);
+
+fn read_rows(path: &Path) -> (Vec<TileRow>, Vec<(String, String)>) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    let mut stmt = conn
+        .prepare(
+            "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles ORDER BY zoom_level, tile_column, tile_row",
+        )
+        .expect("prepare tiles");
+    let tiles = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .expect("query tiles")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("tiles");
+    let mut stmt = conn
+        .prepare("SELECT name, value FROM metadata ORDER BY name")
+        .expect("prepare metadata");
+    let metadata = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .expect("query metadata")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("metadata");
+    (tiles, metadata)
+}
+
+#[test]
+fn mbtiles_round_trips_through_directory() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let tiles_dir = dir.path().join("tiles");
+    let output = dir.path().join("output.mbtiles");
+    create_mbtiles(&input);
+
+    let mut source =
+        open_source(&input, TileFormat::Mbtiles, MbtilesScheme::Tms).expect("open mbtiles");
+    let mut sink = create_sink(
+        &tiles_dir,
+        TileFormat::Directory,
+        source.metadata().expect("metadata"),
+        MbtilesScheme::Tms,
+    )
+    .expect("create directory");
+    assert_eq!(copy_tiles(source.as_mut(), sink.as_mut()).expect("copy"), 2);
+
+    // TMS rows 0 and 1 at z1 are XYZ y 1 and 0; gzip tiles stay gzip.
+    assert_eq!(
+        fs::read(tiles_dir.join("1/0/1.pbf")).expect("gzip tile"),
+        gzip(&create_tile())
+    );
+    assert_eq!(
+        fs::read(tiles_dir.join("1/1/0.pbf")).expect("plain tile"),
+        create_tile()
+    );
+    let metadata: serde_json::Value =
+        serde_json::from_slice(&fs::read(tiles_dir.join("metadata.json")).expect("metadata"))
+            .expect("metadata json");
+    assert_eq!(metadata["name"], "sample");
+    assert!(metadata["json"].is_string());
+
+    let mut source = DirectorySource::open(&tiles_dir).expect("open directory");
+    let mut sink = create_sink(
+        &output,
+        TileFormat::Mbtiles,
+        source.metadata().expect("metadata"),
+        MbtilesScheme::Tms,
+    )
+    .expect("create mbtiles");
+    copy_tiles(&mut source, sink.as_mut()).expect("copy back");
+    assert_eq!(read_rows(&output), read_rows(&input));
+}
+
+#[test]
+fn pmtiles_header_fields_round_trip_through_string_metadata() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    let input = dir.path().join("input.pmtiles");
+    let tiles_dir = dir.path().join("tiles");
+    let output = dir.path().join("output.pmtiles");
+    create_mbtiles(&mbtiles);
+    rusqlite::Connection::open(&mbtiles)
+        .expect("open")
+        .execute_batch(
+            "INSERT INTO metadata (name, value) VALUES
+                ('minzoom', '1'),
+                ('maxzoom', '1'),
+                ('bounds', '-10.5,-5.25,10.5,5.25'),
+                ('center', '1.5,-2.5,1');",
+        )
+        .expect("metadata");
+    mbtiles_to_pmtiles(&mbtiles, &input).expect("mbtiles->pmtiles");
+
+    let mut source =
+        open_source(&input, TileFormat::Pmtiles, MbtilesScheme::Tms).expect("open pmtiles");
+    let mut sink = create_sink(
+        &tiles_dir,
+        TileFormat::Directory,
+        source.metadata().expect("metadata"),
+        MbtilesScheme::Tms,
+    )
+    .expect("create directory");
+    copy_tiles(source.as_mut(), sink.as_mut()).expect("copy");
+
+    // Every value is a string, as tippecanoe writes metadata.json.
+    let metadata: serde_json::Value =
+        serde_json::from_slice(&fs::read(tiles_dir.join("metadata.json")).expect("metadata"))
+            .expect("metadata json");
+    assert_eq!(metadata["minzoom"], "1");
+    assert_eq!(metadata["maxzoom"], "1");
+    assert_eq!(metadata["bounds"], "-10.5,-5.25,10.5,5.25");
+    assert_eq!(metadata["center"], "1.5,-2.5,1");
+    assert!(
+        metadata
+            .as_object()
+            .expect("object")
+            .values()
+            .all(|value| value.is_string())
+    );
+
+    let mut source = DirectorySource::open(&tiles_dir).expect("open directory");
+    let mut sink = create_sink(
+        &output,
+        TileFormat::Pmtiles,
+        source.metadata().expect("metadata"),
+        MbtilesScheme::Tms,
+    )
+    .expect("create pmtiles");
+    copy_tiles(&mut source, sink.as_mut()).expect("copy back");
+
+    let header = |path: &Path| read_header(&fs::File::open(path).expect("open")).expect("header");
+    let (before, after) = (header(&input), header(&output));
+    assert_eq!(
+        (after.min_zoom, after.max_zoom, after.center_zoom),
+        (before.min_zoom, before.max_zoom, before.center_zoom)
+    );
+    assert_eq!(
+        (
+            after.min_longitude,
+            after.min_latitude,
+            after.max_longitude,
+            after.max_latitude
+        ),
+        (
+            before.min_longitude,
+            before.min_latitude,
+            before.max_longitude,
+            before.max_latitude
+        )
+    );
+    assert_eq!(
+        (after.center_longitude, after.center_latitude),
+        (before.center_longitude, before.center_latitude)
+    );
+    assert_eq!(before.max_longitude, 105_000_000);
+}
+
+#[test]
+fn directory_source_reads_tippecanoe_layout() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let tiles_dir = dir.path().join("tiles");
+    fs::create_dir_all(tiles_dir.join("2/3")).expect("mkdir");
+    fs::write(tiles_dir.join("2/3/1.pbf"), gzip(&create_tile())).expect("write tile");
+    fs::write(tiles_dir.join("2/3/notes.txt"), "ignored").expect("write note");
+    fs::write(
+        tiles_dir.join("metadata.json"),
+        r#"{"name":"tippecanoe","minzoom":"2","maxzoom":"2","vector_layers":[{"id":"roads"}]}"#,
+    )
+    .expect("write metadata");
+
+    let mut source = DirectorySource::open(&tiles_dir).expect("open directory");
+    let metadata = source.metadata().expect("metadata");
+    assert_eq!(metadata.get("name").map(String::as_str), Some("tippecanoe"));
+    assert_eq!(
+        metadata.get("json").map(String::as_str),
+        Some(r#"{"vector_layers":[{"id":"roads"}]}"#)
+    );
+    let tile = source
+        .read_tile(TileCoord {
+            zoom: 2,
+            x: 3,
+            y: 1,
+        })
+        .expect("read tile")
+        .expect("tile exists");
+    assert_eq!(tile.compression, 1);
+
+    let report = inspect_directory_with_options(
+        &tiles_dir,
+        InspectOptions {
+            no_progress: true,
+            ..InspectOptions::default()
+        },
+    )
+    .expect("inspect directory");
+    assert_eq!(report.overall.tile_count, 1);
+    assert_eq!(report.by_zoom.len(), 1);
+    assert_eq!(report.by_zoom[0].zoom, 2);
+}
+
+#[test]
+fn directory_sink_refuses_non_empty_output() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    fs::write(dir.path().join("existing.txt"), "data").expect("write");
+    let err = DirectorySink::create(dir.path(), Default::default())
+        .err()
+        .expect("non-empty directory");
+    assert!(err.to_string().contains("not empty"));
+}
+
+fn create_two_layer_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    for name in ["roads", "water"] {
+        let layer = tile.create_layer(name);
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(1.0, 2.0)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let layer = layer.into_feature(geom).into_layer();
+        tile.add_layer(layer).expect("add layer");
+    }
+    tile.to_bytes().expect("tile bytes")
+}
+
+fn prune_directory(input: &Path, output: &Path) -> anyhow::Result<()> {
+    let style_path = input.with_extension("style.json");
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+    let mut source = open_source(input, TileFormat::Directory, MbtilesScheme::Tms)?;
+    let mut sink = create_sink(
+        output,
+        TileFormat::Directory,
+        source.metadata()?,
+        MbtilesScheme::Tms,
+    )?;
+    prune_tiles(
+        source.as_mut(),
+        sink.as_mut(),
+        &style,
+        false,
+        PruneOptions {
+            threads: 2,
+            io_batch: 1,
+            ..PruneOptions::default()
+        },
+    )?;
+    Ok(())
+}
+
+#[test]
+fn prune_tiles_streams_directory_to_directory() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let tiles_dir = dir.path().join("tiles");
+    let output = dir.path().join("pruned");
+    for (path, data) in [
+        ("0/0/0.pbf", gzip(&create_two_layer_tile())),
+        ("1/1/0.pbf", create_two_layer_tile()),
+    ] {
+        let path = tiles_dir.join(path);
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        fs::write(path, data).expect("write tile");
+    }
+    fs::write(tiles_dir.join("metadata.json"), r#"{"name":"stream"}"#).expect("write metadata");
+
+    prune_directory(&tiles_dir, &output).expect("prune directory");
+
+    let mut source = DirectorySource::open(&output).expect("open output");
+    assert_eq!(
+        source
+            .metadata()
+            .expect("metadata")
+            .get("name")
+            .map(String::as_str),
+        Some("stream")
+    );
+    for (coord, compression) in [((0, 0, 0), 1), ((1, 1, 0), 0)] {
+        let (zoom, x, y) = coord;
+        let tile = source
+            .read_tile(TileCoord { zoom, x, y })
+            .expect("read tile")
+            .expect("tile exists");
+        assert_eq!(tile.compression, compression);
+        let data = if compression == 1 {
+            let mut decoder = flate2::read::GzDecoder::new(tile.data.as_slice());
+            let mut data = Vec::new();
+            std::io::Read::read_to_end(&mut decoder, &mut data).expect("gunzip");
+            data
+        } else {
+            tile.data
+        };
+        let reader = mvt_reader::Reader::new(data).expect("decode");
+        let layers = reader.get_layer_metadata().expect("layers");
+        assert_eq!(
+            layers
+                .iter()
+                .map(|layer| layer.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["roads"]
+        );
+    }
+}
+
+#[test]
+fn prune_tiles_stops_on_corrupt_directory_tile() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let tiles_dir = dir.path().join("tiles");
+    fs::create_dir_all(tiles_dir.join("3/1")).expect("mkdir");
+    for y in 0..8 {
+        fs::write(
+            tiles_dir.join(format!("3/1/{y}.pbf")),
+            create_two_layer_tile(),
+        )
+        .expect("write tile");
+    }
+    fs::write(tiles_dir.join("3/1/3.pbf"), [0x1f, 0x8b, 0x08, 0x00]).expect("write corrupt");
+    fs::write(tiles_dir.join("metadata.json"), "{}").expect("write metadata");
+
+    let err =
+        prune_directory(&tiles_dir, &dir.path().join("pruned")).expect_err("corrupt tile fails");
+    assert!(format!("{err:#}").contains("decode tile 3/1/3"), "{err:#}");
+}
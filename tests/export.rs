@@ -0,0 +1,328 @@
+use std::path::Path;
+
+use mvt::{GeomEncoder, GeomType, Tile};
+use serde_json::Value;
+use vt_optimizer::export::{
+    ExportFormat, ExportOptions, SHADOWED_LAYER_KEY, export_geojson, tile_to_lon_lat,
+};
+use vt_optimizer::format::TileFormat;
+use vt_optimizer::mbtiles::{MbtilesScheme, TileCoord};
+use vt_optimizer::pmtiles::GeoBounds;
+
+fn create_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+
+    let layer = tile.create_layer("poi");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(2048.0, 2048.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.set_id(7);
+    feature.add_tag_string("name", "center");
+    feature.add_tag_int("rank", 3);
+    tile.add_layer(feature.into_layer()).expect("add poi");
+
+    let layer = tile.create_layer("landuse");
+    let geom = GeomEncoder::new(GeomType::Polygon)
+        .point(0.0, 0.0)
+        .expect("point")
+        .point(4096.0, 0.0)
+        .expect("point")
+        .point(4096.0, 4096.0)
+        .expect("point")
+        .complete()
+        .expect("ring")
+        .encode()
+        .expect("encode");
+    let feature = layer.into_feature(geom);
+    tile.add_layer(feature.into_layer()).expect("add landuse");
+
+    tile.to_bytes().expect("tile bytes")
+}
+
+fn create_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    // TMS rows: (1, 0, 1) is XYZ 1/0/0 and (1, 1, 0) is XYZ 1/1/1.
+    for (x, row) in [(0, 1), (1, 0)] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, ?1, ?2, ?3)",
+            (x, row, create_tile()),
+        )
+        .expect("tile insert");
+    }
+}
+
+fn export(path: &Path, options: &ExportOptions, format: ExportFormat) -> (String, u64) {
+    let mut out = Vec::new();
+    let stats = export_geojson(
+        path,
+        TileFormat::Mbtiles,
+        MbtilesScheme::Tms,
+        options,
+        format,
+        &mut out,
+    )
+    .expect("export");
+    assert_eq!(stats.features, stats.tiles * 2);
+    (String::from_utf8(out).expect("utf8"), stats.tiles)
+}
+
+fn assert_close(actual: &Value, expected: [f64; 2]) {
+    let actual = [
+        actual[0].as_f64().expect("lon"),
+        actual[1].as_f64().expect("lat"),
+    ];
+    assert!(
+        (actual[0] - expected[0]).abs() < 1e-9 && (actual[1] - expected[1]).abs() < 1e-9,
+        "{actual:?} != {expected:?}"
+    );
+}
+
+#[test]
+fn tile_to_lon_lat_maps_tile_corners() {
+    let tile = TileCoord {
+        zoom: 1,
+        x: 1,
+        y: 0,
+    };
+    let corner = tile_to_lon_lat(tile, 4096, &geo_types::Coord { x: 0.0, y: 4096.0 });
+    assert!((corner[0] - 0.0).abs() < 1e-9);
+    assert!((corner[1] - 0.0).abs() < 1e-9);
+    let corner = tile_to_lon_lat(tile, 4096, &geo_types::Coord { x: 4096.0, y: 0.0 });
+    assert!((corner[0] - 180.0).abs() < 1e-9);
+    assert!((corner[1] - 85.051_128_779_806_59).abs() < 1e-9);
+}
+
+#[test]
+fn export_tile_writes_features_in_wgs84() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_mbtiles(&input);
+
+    let options = ExportOptions {
+        tile: Some(TileCoord {
+            zoom: 1,
+            x: 0,
+            y: 0,
+        }),
+        ..ExportOptions::default()
+    };
+    let (text, tiles) = export(&input, &options, ExportFormat::GeoJson);
+    assert_eq!(tiles, 1);
+    let collection: Value = serde_json::from_str(&text).expect("geojson");
+    assert_eq!(collection["type"], "FeatureCollection");
+    let features = collection["features"].as_array().expect("features");
+    assert_eq!(features.len(), 2);
+
+    let poi = &features[0];
+    assert_eq!(poi["id"], 7);
+    assert_eq!(poi["properties"]["layer"], "poi");
+    assert_eq!(poi["properties"]["name"], "center");
+    assert_eq!(poi["properties"]["rank"], 3);
+    assert_eq!(poi["geometry"]["type"], "MultiPoint");
+    assert_close(
+        &poi["geometry"]["coordinates"][0],
+        [-90.0, 66.513_260_443_111_84],
+    );
+
+    let landuse = &features[1];
+    assert_eq!(landuse["properties"]["layer"], "landuse");
+    // mvt_reader decodes points and polygons as multi-geometries.
+    assert_eq!(landuse["geometry"]["type"], "MultiPolygon");
+    let ring = landuse["geometry"]["coordinates"][0][0]
+        .as_array()
+        .expect("ring");
+    assert_eq!(ring.len(), 4);
+    assert_eq!(ring.first(), ring.last());
+    assert_close(&ring[1], [0.0, 85.051_128_779_806_59]);
+}
+
+#[test]
+fn export_selects_tiles_by_zoom_bbox_and_layer() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_mbtiles(&input);
+
+    let options = ExportOptions {
+        zoom: Some(1),
+        bbox: Some(GeoBounds {
+            min_longitude: 10.0,
+            min_latitude: -20.0,
+            max_longitude: 20.0,
+            max_latitude: -10.0,
+        }),
+        ..ExportOptions::default()
+    };
+    let (text, tiles) = export(&input, &options, ExportFormat::GeoJsonSeq);
+    assert_eq!(tiles, 1);
+    let lines: Vec<Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("feature"))
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|feature| feature["type"] == "Feature"));
+
+    let mut out = Vec::new();
+    let stats = export_geojson(
+        &input,
+        TileFormat::Mbtiles,
+        MbtilesScheme::Tms,
+        &ExportOptions {
+            layers: vec!["poi".to_string()],
+            ..ExportOptions::default()
+        },
+        ExportFormat::GeoJsonSeq,
+        &mut out,
+    )
+    .expect("export layer");
+    assert_eq!((stats.tiles, stats.features), (2, 2));
+    assert!(
+        String::from_utf8(out)
+            .expect("utf8")
+            .lines()
+            .all(|line| line.contains(r#""layer":"poi""#))
+    );
+}
+
+#[test]
+fn export_rejects_out_of_grid_zoom_before_writing() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_mbtiles(&input);
+
+    for options in [
+        ExportOptions {
+            zoom: Some(40),
+            ..ExportOptions::default()
+        },
+        ExportOptions {
+            tile: Some(TileCoord {
+                zoom: 32,
+                x: 0,
+                y: 0,
+            }),
+            ..ExportOptions::default()
+        },
+        ExportOptions {
+            tile: Some(TileCoord {
+                zoom: 1,
+                x: 2,
+                y: 0,
+            }),
+            ..ExportOptions::default()
+        },
+    ] {
+        let mut out = Vec::new();
+        let result = export_geojson(
+            &input,
+            TileFormat::Mbtiles,
+            MbtilesScheme::Tms,
+            &options,
+            ExportFormat::GeoJson,
+            &mut out,
+        );
+        assert!(result.is_err(), "{options:?}");
+        assert!(out.is_empty());
+    }
+
+    let mut out = Vec::new();
+    let missing = export_geojson(
+        &input,
+        TileFormat::Mbtiles,
+        MbtilesScheme::Tms,
+        &ExportOptions {
+            tile: Some(TileCoord {
+                zoom: 3,
+                x: 0,
+                y: 0,
+            }),
+            ..ExportOptions::default()
+        },
+        ExportFormat::GeoJson,
+        &mut out,
+    );
+    assert!(missing.is_err());
+    assert!(out.is_empty());
+}
+
+#[test]
+fn export_skips_stored_tiles_outside_the_grid() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_mbtiles(&input);
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (40, 0, 0, ?1)",
+        (create_tile(),),
+    )
+    .expect("tile insert");
+
+    let mut out = Vec::new();
+    let stats = export_geojson(
+        &input,
+        TileFormat::Mbtiles,
+        MbtilesScheme::Tms,
+        &ExportOptions::default(),
+        ExportFormat::GeoJsonSeq,
+        &mut out,
+    )
+    .expect("export");
+    assert_eq!((stats.tiles, stats.skipped_tiles), (2, 1));
+}
+
+#[test]
+fn export_keeps_feature_layer_property() {
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("poi");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(2048.0, 2048.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.add_tag_string("layer", "bridge");
+    tile.add_layer(feature.into_layer()).expect("add poi");
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    create_mbtiles(&input);
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (tile.to_bytes().expect("tile bytes"),),
+    )
+    .expect("tile insert");
+
+    let mut out = Vec::new();
+    let stats = export_geojson(
+        &input,
+        TileFormat::Mbtiles,
+        MbtilesScheme::Tms,
+        &ExportOptions {
+            zoom: Some(0),
+            ..ExportOptions::default()
+        },
+        ExportFormat::GeoJson,
+        &mut out,
+    )
+    .expect("export");
+    assert_eq!(stats.shadowed_layer_properties, 1);
+    let collection: Value = serde_json::from_slice(&out).expect("geojson");
+    let properties = &collection["features"][0]["properties"];
+    assert_eq!(properties["layer"], "bridge");
+    assert_eq!(properties[SHADOWED_LAYER_KEY], "poi");
+}
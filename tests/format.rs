@@ -208,3 +208,28 @@ fn resolve_output_path_defaults_to_pruned() {
     let path = resolve_output_path(Path::new("input.mbtiles"), None, TileFormat::Mbtiles);
     assert_eq!(path.as_os_str(), "input.pruned.mbtiles");
 }
+
+#[test]
+fn decide_formats_detects_directories() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let decision =
+        decide_formats(dir.path(), Some(Path::new("out/")), None, None).expect("decision");
+
+    assert_eq!(decision.input, TileFormat::Directory);
+    assert_eq!(decision.output, TileFormat::Directory);
+
+    let decision = decide_formats(
+        Path::new("input.mbtiles"),
+        Some(Path::new("tiles")),
+        None,
+        Some("directory"),
+    )
+    .expect("decision");
+    assert_eq!(decision.output, TileFormat::Directory);
+}
+
+#[test]
+fn default_output_path_pruned_for_directory_has_no_extension() {
+    let path = default_output_path_pruned(Path::new("data/tiles/"), TileFormat::Directory);
+    assert_eq!(path, Path::new("data/tiles.pruned"));
+}
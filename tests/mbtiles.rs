@@ -1,8 +1,8 @@
 use std::path::Path;
 
 use vt_optimizer::mbtiles::{
-    InspectOptions, MbtilesStats, MbtilesZoomStats, SampleSpec, copy_mbtiles, inspect_mbtiles,
-    inspect_mbtiles_with_options, parse_sample_spec,
+    InspectOptions, MbtilesScheme, MbtilesStats, MbtilesZoomStats, SampleSpec, copy_mbtiles,
+    inspect_mbtiles, inspect_mbtiles_with_options, parse_sample_spec,
 };
 
 fn create_sample_mbtiles(path: &Path) {
@@ -257,6 +257,7 @@ fn inspect_mbtiles_topn_and_histogram() {
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
     assert_eq!(report.top_tiles.len(), 1);
@@ -297,6 +298,7 @@ fn inspect_mbtiles_sample_count() {
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
     assert_eq!(report.sample_used_tiles, 1);
@@ -2,7 +2,9 @@ use std::path::Path;
 
 use mvt::{GeomEncoder, GeomType, Tile};
 
-use vt_optimizer::mbtiles::{FileLayerSummary, InspectOptions, inspect_mbtiles_with_options};
+use vt_optimizer::mbtiles::{
+    FileLayerSummary, InspectOptions, MbtilesScheme, inspect_mbtiles_with_options,
+};
 
 fn create_layer_tile() -> Vec<u8> {
     let mut tile = Tile::new(4096);
@@ -76,6 +78,7 @@ fn inspect_collects_file_layer_list() {
         recommend: false,
         include_layer_list: true,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -122,6 +125,7 @@ fn inspect_filters_file_layer_list() {
         recommend: false,
         include_layer_list: true,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
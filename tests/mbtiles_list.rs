@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use vt_optimizer::mbtiles::{
-    InspectOptions, TileListOptions, TileSort, inspect_mbtiles_with_options,
+    InspectOptions, MbtilesScheme, TileListOptions, TileSort, inspect_mbtiles_with_options,
 };
 
 fn create_list_mbtiles(path: &Path) {
@@ -28,8 +28,9 @@ fn create_list_mbtiles(path: &Path) {
         (medium,),
     )
     .expect("medium");
+    // TMS row 3 is XYZ y=0 at z2.
     conn.execute(
-        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (2, 0, 0, ?1)",
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (2, 0, 3, ?1)",
         (large,),
     )
     .expect("large");
@@ -63,6 +64,7 @@ fn list_tiles_sorted_by_size_with_limit() {
             limit: 2,
             sort: TileSort::Size,
         }),
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -94,6 +96,7 @@ fn list_tiles_sorted_by_zxy() {
             limit: 10,
             sort: TileSort::Zxy,
         }),
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
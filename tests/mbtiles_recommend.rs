@@ -2,7 +2,7 @@ use std::path::Path;
 
 use mvt::{GeomEncoder, GeomType, Tile};
 
-use vt_optimizer::mbtiles::{InspectOptions, inspect_mbtiles_with_options};
+use vt_optimizer::mbtiles::{InspectOptions, MbtilesScheme, inspect_mbtiles_with_options};
 
 fn create_tile_with_points(count: usize) -> Vec<u8> {
     let mut tile = Tile::new(4096);
@@ -75,6 +75,7 @@ fn inspect_recommend_selects_over_limit_bucket_and_summaries() {
         recommend: true,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -4,7 +4,9 @@ use std::path::Path;
 use flate2::{Compression, write::GzEncoder};
 use mvt::{GeomEncoder, GeomType, Tile};
 
-use vt_optimizer::mbtiles::{InspectOptions, TileCoord, inspect_mbtiles_with_options};
+use vt_optimizer::mbtiles::{
+    InspectOptions, MbtilesScheme, TileCoord, inspect_mbtiles_with_options,
+};
 
 fn create_vector_tile() -> Vec<u8> {
     let mut tile = Tile::new(4096);
@@ -58,8 +60,9 @@ fn create_summary_mbtiles(path: &Path, tile_data: Vec<u8>) {
     )
     .expect("schema");
 
+    // TMS row 2 is XYZ y=5 at z3.
     conn.execute(
-        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (3, 4, 5, ?1)",
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (3, 4, 2, ?1)",
         (tile_data,),
     )
     .expect("tile insert");
@@ -90,6 +93,7 @@ fn inspect_tile_summary_reports_layer_counts() {
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -148,6 +152,7 @@ fn inspect_tile_summary_decodes_gzip_tiles() {
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -181,6 +186,7 @@ fn inspect_tile_summary_filters_layer() {
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -200,3 +206,26 @@ fn inspect_tile_summary_filters_layer() {
         vec!["class".to_string(), "name".to_string()]
     );
 }
+
+#[test]
+fn inspect_tile_summary_reads_xyz_rows_when_requested() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("input.mbtiles");
+    create_summary_mbtiles(&path, create_vector_tile());
+
+    let options = InspectOptions {
+        tile: Some(TileCoord {
+            zoom: 3,
+            x: 4,
+            y: 2,
+        }),
+        summary: true,
+        mbtiles_scheme: MbtilesScheme::Xyz,
+        ..InspectOptions::default()
+    };
+
+    let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
+    let summary = report.tile_summary.expect("summary");
+    assert_eq!(summary.y, 2);
+    assert_eq!(summary.total_features, 3);
+}
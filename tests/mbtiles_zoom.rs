@@ -1,6 +1,8 @@
 use std::path::Path;
 
-use vt_optimizer::mbtiles::{InspectOptions, MbtilesStats, inspect_mbtiles_with_options};
+use vt_optimizer::mbtiles::{
+    InspectOptions, MbtilesScheme, MbtilesStats, inspect_mbtiles_with_options,
+};
 
 fn create_zoom_sample_mbtiles(path: &Path) {
     let conn = rusqlite::Connection::open(path).expect("open");
@@ -64,6 +66,7 @@ fn inspect_zoom_limits_stats_and_histogram() {
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -107,6 +110,7 @@ fn inspect_zoom_sampling_uses_zoom_tile_count() {
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -135,6 +139,7 @@ fn inspect_histograms_by_zoom_includes_each_zoom() {
         recommend: false,
         include_layer_list: false,
         list_tiles: None,
+        mbtiles_scheme: MbtilesScheme::Tms,
     };
 
     let report = inspect_mbtiles_with_options(&path, options).expect("inspect");
@@ -7,9 +7,16 @@ use brotli::CompressorWriter;
 use flate2::read::GzDecoder;
 use mvt::{GeomEncoder, GeomType, Tile};
 use mvt_reader::Reader;
-use vt_optimizer::mbtiles::{InspectOptions, inspect_mbtiles};
+use vt_optimizer::mbtiles::{
+    InspectOptions, MbtilesScheme, PruneOptions, VerifyIssueKind, inspect_mbtiles,
+    prune_mbtiles_layer_only, prune_mbtiles_to_pmtiles,
+};
 use vt_optimizer::pmtiles::{
-    inspect_pmtiles_with_options, mbtiles_to_pmtiles, pmtiles_to_mbtiles, prune_pmtiles_layer_only,
+    PmtilesCopyOptions, PmtilesWriter, PmtilesWriterOptions, encode_metadata_json,
+    inspect_pmtiles_with_options, mbtiles_to_pmtiles, mbtiles_to_pmtiles_with_scheme,
+    pmtiles_to_mbtiles, pmtiles_to_mbtiles_with_scheme, pmtiles_to_pmtiles,
+    prune_pmtiles_layer_only, prune_pmtiles_to_mbtiles, prune_pmtiles_with_checkpoint, read_header,
+    read_metadata_json, tile_id_from_xyz, verify_pmtiles,
 };
 use vt_optimizer::style::read_style;
 
@@ -50,6 +57,10 @@ fn create_sample_mbtiles(path: &Path) {
 }
 
 fn create_layer_tile() -> Vec<u8> {
+    create_named_layer_tile("Main")
+}
+
+fn create_named_layer_tile(name: &str) -> Vec<u8> {
     let mut tile = Tile::new(4096);
 
     let layer = tile.create_layer("roads");
@@ -60,7 +71,7 @@ fn create_layer_tile() -> Vec<u8> {
         .expect("encode");
     let mut feature = layer.into_feature(geom);
     feature.add_tag_string("class", "primary");
-    feature.add_tag_string("name", "Main");
+    feature.add_tag_string("name", name);
     let layer = feature.into_layer();
     tile.add_layer(layer).expect("add roads");
 
@@ -102,9 +113,10 @@ fn create_layer_mbtiles(path: &Path) {
 }
 
 fn write_pmtiles_with_metadata(path: &Path, metadata_json: &str) {
-    write_pmtiles_with_metadata_and_compression(path, metadata_json, 0);
+    write_pmtiles_with_metadata_and_compression(path, metadata_json, 1);
 }
 
+/// `internal_compression` is a PMTiles v3 spec code (1 none, 3 brotli).
 fn write_pmtiles_with_metadata_and_compression(
     path: &Path,
     metadata_json: &str,
@@ -115,8 +127,8 @@ fn write_pmtiles_with_metadata_and_compression(
     const VERSION: u8 = 3;
 
     let metadata_bytes = match internal_compression {
-        0 => metadata_json.as_bytes().to_vec(),
-        2 => {
+        1 => metadata_json.as_bytes().to_vec(),
+        3 => {
             let mut compressed = Vec::new();
             {
                 let mut writer = CompressorWriter::new(&mut compressed, 4096, 5, 22);
@@ -156,7 +168,7 @@ fn write_pmtiles_with_metadata_and_compression(
 
     header.push(0); // clustered
     header.push(internal_compression); // internal_compression
-    header.push(0); // tile_compression
+    header.push(1); // tile_compression
     header.push(0); // tile_type
     header.push(0); // min_zoom
     header.push(0); // max_zoom
@@ -228,7 +240,7 @@ fn inspect_pmtiles_reads_metadata() {
 fn inspect_pmtiles_reads_brotli_metadata() {
     let dir = tempfile::tempdir().expect("tempdir");
     let pmtiles = dir.path().join("metadata-brotli.pmtiles");
-    write_pmtiles_with_metadata_and_compression(&pmtiles, r#"{"name":"sample","minzoom":1}"#, 2);
+    write_pmtiles_with_metadata_and_compression(&pmtiles, r#"{"name":"sample","minzoom":1}"#, 3);
 
     let report = inspect_pmtiles_with_options(&pmtiles, &InspectOptions::default())
         .expect("inspect pmtiles");
@@ -340,6 +352,12 @@ fn read_tile_compression(path: &Path) -> std::io::Result<u8> {
     read_u8(&mut cursor)
 }
 
+/// Raw (internal, tile) compression codes as stored in the header.
+fn read_header_compression_codes(path: &Path) -> (u8, u8) {
+    let bytes = fs::read(path).expect("read pmtiles");
+    (bytes[97], bytes[98])
+}
+
 fn read_u8(cursor: &mut &[u8]) -> std::io::Result<u8> {
     if cursor.is_empty() {
         return Err(std::io::Error::new(
@@ -447,9 +465,10 @@ fn inspect_pmtiles_collects_top_tiles() {
 
     assert_eq!(report.top_tiles.len(), 1);
     let tile = &report.top_tiles[0];
+    // MBTiles row 1 at z1 is XYZ y=0.
     assert_eq!(tile.zoom, 1);
     assert_eq!(tile.x, 1);
-    assert_eq!(tile.y, 1);
+    assert_eq!(tile.y, 0);
     assert_eq!(tile.bytes, 20);
 }
 
@@ -482,3 +501,752 @@ fn inspect_pmtiles_collects_layer_list() {
     assert_eq!(layers[1].property_key_count, 2);
     assert_eq!(layers[1].property_value_count, 2);
 }
+
+fn create_grid_layer_mbtiles(path: &Path, zoom: u8) {
+    let mut conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+
+    let tx = conn.transaction().expect("begin");
+    let size = 1u32 << zoom;
+    for x in 0..size {
+        for y in 0..size {
+            tx.execute(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+                (zoom, x, y, create_named_layer_tile(&format!("{x}/{y}"))),
+            )
+            .expect("tile insert");
+        }
+    }
+    tx.commit().expect("commit");
+}
+
+fn assert_root_fits_with_leaves(path: &Path) {
+    let file = File::open(path).expect("open pmtiles");
+    let header = read_header(&file).expect("read header");
+    assert!(header.root_offset + header.root_length <= 16_384);
+    assert!(header.leaf_length > 0);
+    let report = verify_pmtiles(path).expect("verify");
+    assert!(report.is_ok(), "{:?}", report.issues);
+    assert_eq!(report.tile_count, 128 * 128);
+}
+
+#[test]
+fn pmtiles_writers_split_large_directories_into_leaves() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input_mbtiles = dir.path().join("input.mbtiles");
+    let input_pmtiles = dir.path().join("input.pmtiles");
+    let output_pmtiles = dir.path().join("output.pmtiles");
+    let output_mbtiles = dir.path().join("output.mbtiles");
+    let style_path = dir.path().join("style.json");
+    create_grid_layer_mbtiles(&input_mbtiles, 7);
+
+    mbtiles_to_pmtiles(&input_mbtiles, &input_pmtiles).expect("mbtiles->pmtiles");
+    assert_root_fits_with_leaves(&input_pmtiles);
+
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+    prune_pmtiles_layer_only(&input_pmtiles, &output_pmtiles, &style, false, true)
+        .expect("prune pmtiles");
+    assert_root_fits_with_leaves(&output_pmtiles);
+
+    pmtiles_to_mbtiles(&output_pmtiles, &output_mbtiles).expect("pmtiles->mbtiles");
+    let report = inspect_mbtiles(&output_mbtiles).expect("inspect output");
+    assert_eq!(report.overall.tile_count, 128 * 128);
+}
+
+#[test]
+fn mbtiles_to_pmtiles_dedups_contents_and_merges_runs() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    let output = dir.path().join("roundtrip.mbtiles");
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    // z1 tile_ids in reverse Hilbert order: (1,0), (1,1), (0,1), (0,0), given
+    // as XYZ.
+    for (x, y, data) in [
+        (1, 0, vec![7u8; 10]),
+        (1, 1, vec![9u8; 20]),
+        (0, 1, vec![7u8; 10]),
+        (0, 0, vec![7u8; 10]),
+    ] {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (1, ?1, ?2, ?3)",
+            (x, 1 - y, data),
+        )
+        .expect("tile insert");
+    }
+    drop(conn);
+
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+    let header = read_header(&File::open(&pmtiles).expect("open pmtiles")).expect("read header");
+    assert_eq!(header.n_addressed_tiles, 4);
+    assert_eq!(header.n_tile_entries, 3);
+    assert_eq!(header.n_tile_contents, 2);
+    assert_eq!(header.data_length, 30);
+    assert_eq!(header.clustered, 1);
+    // The placeholder tiles are not MVT; the layout must still verify.
+    let report = verify_pmtiles(&pmtiles).expect("verify");
+    assert!(
+        report
+            .issues
+            .iter()
+            .all(|issue| issue.kind == VerifyIssueKind::Mvt),
+        "{:?}",
+        report.issues
+    );
+
+    pmtiles_to_mbtiles(&pmtiles, &output).expect("pmtiles->mbtiles");
+    let report = inspect_mbtiles(&output).expect("inspect output");
+    assert_eq!(report.overall.tile_count, 4);
+    assert_eq!(report.overall.total_bytes, 50);
+}
+
+#[test]
+fn pmtiles_writer_accepts_tiles_out_of_order() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let pmtiles = dir.path().join("written.pmtiles");
+    let output = dir.path().join("roundtrip.mbtiles");
+
+    let mut writer = PmtilesWriter::new().expect("writer");
+    writer
+        .add_tile(tile_id_from_xyz(1, 1, 0), &[3u8; 8])
+        .expect("add");
+    writer
+        .add_tile(tile_id_from_xyz(0, 0, 0), &[1u8; 4])
+        .expect("add");
+    writer
+        .add_tile(tile_id_from_xyz(1, 0, 0), &[3u8; 8])
+        .expect("add");
+    let metadata = br#"{"name":"written"}"#;
+    writer
+        .finish(
+            &pmtiles,
+            metadata,
+            PmtilesWriterOptions {
+                internal_compression: 0,
+                tile_compression: 0,
+                tile_type: 1,
+                min_zoom: None,
+                max_zoom: None,
+                bounds: None,
+                center: None,
+            },
+        )
+        .expect("finish");
+
+    let header = read_header(&File::open(&pmtiles).expect("open pmtiles")).expect("read header");
+    assert_eq!(header.min_zoom, 0);
+    assert_eq!(header.max_zoom, 1);
+    assert_eq!(header.n_addressed_tiles, 3);
+    assert_eq!(header.n_tile_contents, 2);
+    assert_eq!(header.data_length, 12);
+    assert_eq!(header.metadata_length, metadata.len() as u64);
+    assert_eq!(header.clustered, 1);
+    // The placeholder tiles are not MVT; the layout must still verify.
+    let report = verify_pmtiles(&pmtiles).expect("verify");
+    assert!(
+        report
+            .issues
+            .iter()
+            .all(|issue| issue.kind == VerifyIssueKind::Mvt),
+        "{:?}",
+        report.issues
+    );
+
+    // XYZ 1/1/0 is stored as TMS row 1.
+    pmtiles_to_mbtiles(&pmtiles, &output).expect("pmtiles->mbtiles");
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = 1 AND tile_row = 1",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read tile");
+    assert_eq!(data, vec![3u8; 8]);
+}
+
+#[test]
+fn prune_pmtiles_output_is_independent_of_thread_counts() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input_mbtiles = dir.path().join("input.mbtiles");
+    let input_pmtiles = dir.path().join("input.pmtiles");
+    let serial = dir.path().join("serial.pmtiles");
+    let parallel = dir.path().join("parallel.pmtiles");
+    let style_path = dir.path().join("style.json");
+    create_grid_layer_mbtiles(&input_mbtiles, 4);
+    mbtiles_to_pmtiles(&input_mbtiles, &input_pmtiles).expect("mbtiles->pmtiles");
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+    let options = |threads, readers, io_batch| PruneOptions {
+        threads,
+        io_batch,
+        readers,
+        ..PruneOptions::default()
+    };
+
+    let serial_stats = prune_pmtiles_with_checkpoint(
+        &input_pmtiles,
+        &serial,
+        &style,
+        false,
+        options(1, 1, 1),
+        None,
+    )
+    .expect("serial prune");
+    let parallel_stats = prune_pmtiles_with_checkpoint(
+        &input_pmtiles,
+        &parallel,
+        &style,
+        false,
+        options(4, 3, 7),
+        None,
+    )
+    .expect("parallel prune");
+
+    assert_eq!(
+        fs::read(&serial).expect("serial"),
+        fs::read(&parallel).expect("parallel")
+    );
+    assert_eq!(
+        serial_stats.removed_features_by_zoom,
+        parallel_stats.removed_features_by_zoom
+    );
+    assert_eq!(
+        serial_stats.removed_layers_by_zoom,
+        parallel_stats.removed_layers_by_zoom
+    );
+}
+
+#[test]
+fn prune_pmtiles_fails_instead_of_hanging_on_corrupt_tile() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input_mbtiles = dir.path().join("input.mbtiles");
+    let input_pmtiles = dir.path().join("input.pmtiles");
+    let output_pmtiles = dir.path().join("output.pmtiles");
+    let style_path = dir.path().join("style.json");
+    create_grid_layer_mbtiles(&input_mbtiles, 4);
+    mbtiles_to_pmtiles(&input_mbtiles, &input_pmtiles).expect("mbtiles->pmtiles");
+    // Overwrite the first stored tile with a gzip header and an invalid body.
+    let header = read_header(&File::open(&input_pmtiles).expect("open")).expect("read header");
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(&input_pmtiles)
+        .expect("open for write");
+    file.seek(SeekFrom::Start(header.data_offset))
+        .expect("seek data");
+    let mut corrupt = vec![0u8; 16];
+    corrupt[..4].copy_from_slice(&[0x1f, 0x8b, 0x08, 0x00]);
+    file.write_all(&corrupt).expect("corrupt tile");
+    drop(file);
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let result = prune_pmtiles_with_checkpoint(
+            &input_pmtiles,
+            &output_pmtiles,
+            &style,
+            false,
+            PruneOptions {
+                threads: 2,
+                io_batch: 1,
+                ..PruneOptions::default()
+            },
+            None,
+        );
+        let _ = tx.send(result.map(|_| ()));
+    });
+    let result = rx
+        .recv_timeout(std::time::Duration::from_secs(60))
+        .expect("prune should not hang");
+    assert!(result.is_err());
+}
+
+#[test]
+fn prune_pmtiles_drops_empty_tiles_when_requested() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input_mbtiles = dir.path().join("input.mbtiles");
+    let input_pmtiles = dir.path().join("input.pmtiles");
+    let output_pmtiles = dir.path().join("output.pmtiles");
+    let style_path = dir.path().join("style.json");
+    create_grid_layer_mbtiles(&input_mbtiles, 2);
+    mbtiles_to_pmtiles(&input_mbtiles, &input_pmtiles).expect("mbtiles->pmtiles");
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"water","type":"fill","source":"osm","source-layer":"water","paint":{"fill-opacity":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+
+    prune_pmtiles_with_checkpoint(
+        &input_pmtiles,
+        &output_pmtiles,
+        &style,
+        false,
+        PruneOptions {
+            threads: 2,
+            io_batch: 2,
+            readers: 2,
+            drop_empty_tiles: true,
+            ..PruneOptions::default()
+        },
+        None,
+    )
+    .expect("prune pmtiles");
+
+    let header =
+        read_header(&File::open(&output_pmtiles).expect("open output")).expect("read header");
+    assert_eq!(header.n_addressed_tiles, 0);
+}
+
+#[test]
+fn mbtiles_to_pmtiles_derives_bounds_from_max_zoom_tiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_sample_mbtiles(&input);
+
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+
+    let header = read_header(&File::open(&pmtiles).expect("open pmtiles")).expect("read header");
+    // Only TMS tile 1/1/1 (XYZ 1/1/0) exists at max zoom: the north-east quadrant.
+    assert_eq!(header.min_longitude, 0);
+    assert_eq!(header.max_longitude, 1_800_000_000);
+    assert_eq!(header.min_latitude, 0);
+    assert_eq!(header.max_latitude, 850_511_288);
+    assert_eq!(header.center_zoom, 0);
+    assert_eq!(header.center_longitude, 900_000_000);
+    assert_eq!(header.center_latitude, 425_255_644);
+}
+
+#[test]
+fn pmtiles_bounds_follow_mbtiles_metadata_through_prune() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input_mbtiles = dir.path().join("input.mbtiles");
+    let input_pmtiles = dir.path().join("input.pmtiles");
+    let output_pmtiles = dir.path().join("output.pmtiles");
+    let style_path = dir.path().join("style.json");
+    create_layer_mbtiles(&input_mbtiles);
+    let conn = rusqlite::Connection::open(&input_mbtiles).expect("open");
+    conn.execute_batch(
+        "
+        INSERT INTO metadata (name, value) VALUES ('bounds', '139.5,35.5,140.0,36.0');
+        INSERT INTO metadata (name, value) VALUES ('center', '139.75,35.68,12');
+        ",
+    )
+    .expect("metadata");
+    drop(conn);
+
+    mbtiles_to_pmtiles(&input_mbtiles, &input_pmtiles).expect("mbtiles->pmtiles");
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+    prune_pmtiles_layer_only(&input_pmtiles, &output_pmtiles, &style, false, true)
+        .expect("prune pmtiles");
+
+    for path in [&input_pmtiles, &output_pmtiles] {
+        let header = read_header(&File::open(path).expect("open pmtiles")).expect("read header");
+        assert_eq!(header.min_longitude, 1_395_000_000);
+        assert_eq!(header.min_latitude, 355_000_000);
+        assert_eq!(header.max_longitude, 1_400_000_000);
+        assert_eq!(header.max_latitude, 360_000_000);
+        assert_eq!(header.center_zoom, 12);
+        assert_eq!(header.center_longitude, 1_397_500_000);
+        assert_eq!(header.center_latitude, 356_800_000);
+    }
+}
+
+#[test]
+fn metadata_round_trips_between_mbtiles_and_pmtiles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    let output = dir.path().join("roundtrip.mbtiles");
+    create_layer_mbtiles(&input);
+    let vector_layers = r#"{"vector_layers":[{"id":"roads","fields":{"class":"String"}}],"tilestats":{"layerCount":1}}"#;
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    for (name, value) in [
+        ("name", "sample"),
+        ("attribution", "© contributors"),
+        ("format", "pbf"),
+        ("minzoom", "0"),
+        ("maxzoom", "5"),
+        ("json", vector_layers),
+    ] {
+        conn.execute(
+            "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+            (name, value),
+        )
+        .expect("metadata");
+    }
+    drop(conn);
+
+    mbtiles_to_pmtiles(&input, &pmtiles).expect("mbtiles->pmtiles");
+    let file = File::open(&pmtiles).expect("open pmtiles");
+    let header = read_header(&file).expect("read header");
+    assert_eq!(header.tile_type, 1);
+    assert_eq!(header.min_zoom, 0);
+    assert_eq!(header.max_zoom, 5);
+    let json = read_metadata_json(&file, &header).expect("metadata json");
+    assert_eq!(json["name"], "sample");
+    assert_eq!(json["attribution"], "© contributors");
+    assert_eq!(json["vector_layers"][0]["id"], "roads");
+    assert_eq!(json["tilestats"]["layerCount"], 1);
+    assert!(json.get("json").is_none());
+
+    pmtiles_to_mbtiles(&pmtiles, &output).expect("pmtiles->mbtiles");
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let value = |name: &str| -> String {
+        conn.query_row(
+            "SELECT value FROM metadata WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        )
+        .expect("metadata row")
+    };
+    assert_eq!(value("name"), "sample");
+    assert_eq!(value("attribution"), "© contributors");
+    assert_eq!(value("format"), "pbf");
+    assert_eq!(value("minzoom"), "0");
+    assert_eq!(value("maxzoom"), "5");
+    let nested: serde_json::Value = serde_json::from_str(&value("json")).expect("json row");
+    let expected: serde_json::Value = serde_json::from_str(vector_layers).expect("expected");
+    assert_eq!(nested, expected);
+}
+
+#[test]
+fn mbtiles_scheme_controls_row_flip_in_conversion() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let tms = dir.path().join("tms.pmtiles");
+    let xyz = dir.path().join("xyz.pmtiles");
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (2, 1, 0, ?1)",
+        (vec![5u8; 16],),
+    )
+    .expect("tile insert");
+    drop(conn);
+    let top_tile = |path: &Path| {
+        let options = InspectOptions {
+            topn: 1,
+            ..Default::default()
+        };
+        let report = inspect_pmtiles_with_options(path, &options).expect("inspect pmtiles");
+        let tile = &report.top_tiles[0];
+        (tile.zoom, tile.x, tile.y)
+    };
+    let tile_rows = |path: &Path| {
+        let conn = rusqlite::Connection::open(path).expect("open output");
+        conn.query_row(
+            "SELECT zoom_level, tile_column, tile_row FROM tiles",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, u8>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, u32>(2)?,
+                ))
+            },
+        )
+        .expect("read tile")
+    };
+
+    mbtiles_to_pmtiles(&input, &tms).expect("tms mbtiles->pmtiles");
+    assert_eq!(top_tile(&tms), (2, 1, 3));
+    mbtiles_to_pmtiles_with_scheme(&input, &xyz, MbtilesScheme::Xyz).expect("xyz mbtiles->pmtiles");
+    assert_eq!(top_tile(&xyz), (2, 1, 0));
+
+    let tms_roundtrip = dir.path().join("tms.mbtiles");
+    pmtiles_to_mbtiles(&tms, &tms_roundtrip).expect("tms pmtiles->mbtiles");
+    assert_eq!(tile_rows(&tms_roundtrip), (2, 1, 0));
+    let xyz_output = dir.path().join("xyz.mbtiles");
+    pmtiles_to_mbtiles_with_scheme(&tms, &xyz_output, MbtilesScheme::Xyz)
+        .expect("xyz pmtiles->mbtiles");
+    assert_eq!(tile_rows(&xyz_output), (2, 1, 3));
+}
+
+#[test]
+fn pmtiles_to_pmtiles_recompresses_writer_output() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let out_of_order = dir.path().join("out_of_order.pmtiles");
+    let recompressed = dir.path().join("recompressed.pmtiles");
+    let restored = dir.path().join("restored.pmtiles");
+    let output = dir.path().join("restored.mbtiles");
+
+    let mut writer = PmtilesWriter::new().expect("writer");
+    for (tile_id, data) in [
+        (tile_id_from_xyz(1, 1, 0), create_named_layer_tile("c")),
+        (tile_id_from_xyz(0, 0, 0), create_named_layer_tile("a")),
+        (tile_id_from_xyz(1, 0, 0), create_named_layer_tile("b")),
+    ] {
+        writer.add_tile(tile_id, &data).expect("add");
+    }
+    writer
+        .finish(
+            &out_of_order,
+            &encode_metadata_json(
+                &serde_json::json!({"name": "copy"})
+                    .as_object()
+                    .cloned()
+                    .expect("object"),
+                1,
+            )
+            .expect("metadata"),
+            PmtilesWriterOptions {
+                internal_compression: 1,
+                tile_compression: 0,
+                tile_type: 1,
+                min_zoom: None,
+                max_zoom: None,
+                bounds: None,
+                center: None,
+            },
+        )
+        .expect("finish");
+    // The writer lays tile data out in tile_id order whatever the add order.
+    let header = read_header(&File::open(&out_of_order).expect("open")).expect("read header");
+    assert_eq!(header.clustered, 1);
+    let report = verify_pmtiles(&out_of_order).expect("verify");
+    assert!(report.is_ok(), "{:?}", report.issues);
+    assert_eq!(read_header_compression_codes(&out_of_order), (2, 1));
+
+    pmtiles_to_pmtiles(
+        &out_of_order,
+        &recompressed,
+        PmtilesCopyOptions {
+            internal_compression: Some(3),
+            tile_compression: Some(2),
+        },
+    )
+    .expect("recompress");
+    let file = File::open(&recompressed).expect("open recompressed");
+    let header = read_header(&file).expect("read header");
+    assert_eq!(header.clustered, 1);
+    assert_eq!(header.internal_compression, 3);
+    assert_eq!(header.tile_compression, 2);
+    // The header stores spec codes: 4 zstd, 3 brotli.
+    assert_eq!(read_header_compression_codes(&recompressed), (4, 3));
+    assert_eq!(header.n_addressed_tiles, 3);
+    assert_eq!(
+        read_metadata_json(&file, &header).expect("metadata")["name"],
+        "copy"
+    );
+    let report = verify_pmtiles(&recompressed).expect("verify");
+    assert!(report.is_ok(), "{:?}", report.issues);
+
+    pmtiles_to_pmtiles(
+        &recompressed,
+        &restored,
+        PmtilesCopyOptions {
+            internal_compression: None,
+            tile_compression: Some(0),
+        },
+    )
+    .expect("restore");
+    let header = read_header(&File::open(&restored).expect("open")).expect("read header");
+    assert_eq!(header.internal_compression, 3);
+    assert_eq!(header.tile_compression, 0);
+    assert_eq!(read_header_compression_codes(&restored), (4, 1));
+    pmtiles_to_mbtiles_with_scheme(&restored, &output, MbtilesScheme::Xyz)
+        .expect("pmtiles->mbtiles");
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = 1 AND tile_row = 0",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read tile");
+    assert_eq!(data, create_named_layer_tile("c"));
+}
+
+#[test]
+fn pmtiles_writer_keeps_last_tile_for_repeated_tile_id() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("repeated.pmtiles");
+    let output = dir.path().join("repeated.mbtiles");
+
+    let mut writer = PmtilesWriter::new().expect("writer");
+    for (tile_id, data) in [
+        (tile_id_from_xyz(1, 0, 0), create_named_layer_tile("a")),
+        (tile_id_from_xyz(1, 1, 0), create_named_layer_tile("b")),
+        (tile_id_from_xyz(1, 0, 0), create_named_layer_tile("c")),
+    ] {
+        writer.add_tile(tile_id, &data).expect("add");
+    }
+    writer
+        .finish(
+            &path,
+            &[],
+            PmtilesWriterOptions {
+                internal_compression: 1,
+                tile_compression: 0,
+                tile_type: 1,
+                min_zoom: None,
+                max_zoom: None,
+                bounds: None,
+                center: None,
+            },
+        )
+        .expect("finish");
+
+    let header = read_header(&File::open(&path).expect("open")).expect("read header");
+    assert_eq!(header.n_addressed_tiles, 2);
+    assert_eq!(header.n_tile_entries, 2);
+    assert_eq!(header.n_tile_contents, 2);
+    let report = verify_pmtiles(&path).expect("verify");
+    assert!(report.is_ok(), "{:?}", report.issues);
+    pmtiles_to_mbtiles_with_scheme(&path, &output, MbtilesScheme::Xyz).expect("pmtiles->mbtiles");
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = 1 AND tile_column = 0 AND tile_row = 0",
+            [],
+            |row| row.get(0),
+        )
+        .expect("read tile");
+    assert_eq!(data, create_named_layer_tile("c"));
+}
+
+#[test]
+fn cross_format_prune_matches_prune_then_convert() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input_mbtiles = dir.path().join("input.mbtiles");
+    let input_pmtiles = dir.path().join("input.pmtiles");
+    let style_path = dir.path().join("style.json");
+    create_grid_layer_mbtiles(&input_mbtiles, 2);
+    mbtiles_to_pmtiles(&input_mbtiles, &input_pmtiles).expect("mbtiles->pmtiles");
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads","paint":{"line-width":1}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+    let options = PruneOptions {
+        threads: 2,
+        io_batch: 3,
+        readers: 2,
+        ..PruneOptions::default()
+    };
+    let tiles = |path: &Path| {
+        let conn = rusqlite::Connection::open(path).expect("open output");
+        let mut stmt = conn
+            .prepare(
+                "SELECT zoom_level, tile_column, tile_row, tile_data FROM tiles \
+                 ORDER BY zoom_level, tile_column, tile_row",
+            )
+            .expect("prepare");
+        stmt.query_map([], |row| {
+            let data: Vec<u8> = row.get(3)?;
+            let payload = if data.starts_with(&[0x1f, 0x8b]) {
+                let mut decoded = Vec::new();
+                GzDecoder::new(data.as_slice())
+                    .read_to_end(&mut decoded)
+                    .expect("decode gzip");
+                decoded
+            } else {
+                data
+            };
+            Ok((
+                row.get::<_, u8>(0)?,
+                row.get::<_, u32>(1)?,
+                row.get::<_, u32>(2)?,
+                payload,
+            ))
+        })
+        .expect("query")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("rows")
+    };
+
+    let pruned_mbtiles = dir.path().join("pruned.mbtiles");
+    prune_mbtiles_layer_only(
+        &input_mbtiles,
+        &pruned_mbtiles,
+        &style,
+        false,
+        options.clone(),
+    )
+    .expect("prune mbtiles");
+    let pmtiles_to_mbtiles_out = dir.path().join("from-pmtiles.mbtiles");
+    prune_pmtiles_to_mbtiles(
+        &input_pmtiles,
+        &pmtiles_to_mbtiles_out,
+        &style,
+        false,
+        options.clone(),
+        MbtilesScheme::Tms,
+    )
+    .expect("prune pmtiles->mbtiles");
+    let expected = tiles(&pruned_mbtiles);
+    assert_eq!(expected.len(), 16);
+    assert_eq!(tiles(&pmtiles_to_mbtiles_out), expected);
+
+    let mbtiles_to_pmtiles_out = dir.path().join("from-mbtiles.pmtiles");
+    prune_mbtiles_to_pmtiles(
+        &input_mbtiles,
+        &mbtiles_to_pmtiles_out,
+        &style,
+        false,
+        options,
+        MbtilesScheme::Tms,
+    )
+    .expect("prune mbtiles->pmtiles");
+    verify_pmtiles(&mbtiles_to_pmtiles_out).expect("verify pmtiles");
+    let roundtrip = dir.path().join("roundtrip.mbtiles");
+    pmtiles_to_mbtiles(&mbtiles_to_pmtiles_out, &roundtrip).expect("pmtiles->mbtiles");
+    assert_eq!(tiles(&roundtrip), expected);
+}
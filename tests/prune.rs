@@ -1,11 +1,15 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
 use mvt::{GeomEncoder, GeomType, Tile};
 use mvt_reader::Reader;
 
-use vt_optimizer::mbtiles::{PruneOptions, inspect_mbtiles, prune_mbtiles_layer_only};
-use vt_optimizer::style::read_style;
+use vt_optimizer::mbtiles::{
+    PruneOptions, RemovedNames, inspect_mbtiles, name_language, prune_mbtiles_layer_only,
+};
+use vt_optimizer::rules::read_rules;
+use vt_optimizer::style::{MapboxStyle, read_style};
 
 fn create_layer_tile() -> Vec<u8> {
     let mut tile = Tile::new(4096);
@@ -58,6 +62,30 @@ fn create_layer_mbtiles(path: &Path) {
     .expect("tile insert");
 }
 
+/// MBTiles with each tile stored at `(zoom, 0, 0)`.
+fn create_tiles_mbtiles(path: &Path, tiles: Vec<(u8, Tile)>) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    for (zoom, tile) in tiles {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, 0, 0, ?2)",
+            (zoom, tile.to_bytes().expect("tile bytes")),
+        )
+        .expect("tile insert");
+    }
+}
+
 fn create_layer_mbtiles_multiple(path: &Path) {
     let conn = rusqlite::Connection::open(path).expect("open");
     conn.execute_batch(
@@ -130,13 +158,8 @@ fn prune_mbtiles_removes_unlisted_layers() {
         &style,
         false,
         PruneOptions {
-            threads: 1,
             io_batch: 10,
-            readers: 1,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            ..PruneOptions::default()
         },
     )
     .expect("prune mbtiles");
@@ -176,13 +199,8 @@ fn prune_mbtiles_supports_map_images_schema() {
         &style,
         false,
         PruneOptions {
-            threads: 1,
             io_batch: 10,
-            readers: 1,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            ..PruneOptions::default()
         },
     )
     .expect("prune mbtiles");
@@ -216,10 +234,7 @@ fn prune_mbtiles_handles_multiple_tiles() {
             threads: 2,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            ..PruneOptions::default()
         },
     )
     .expect("prune mbtiles");
@@ -291,10 +306,7 @@ fn prune_mbtiles_filters_features_by_style() {
             threads: 2,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            ..PruneOptions::default()
         },
     )
     .expect("prune mbtiles");
@@ -346,10 +358,7 @@ fn prune_mbtiles_keeps_features_on_unknown_filter() {
             threads: 2,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            ..PruneOptions::default()
         },
     )
     .expect("prune mbtiles");
@@ -393,10 +402,8 @@ fn prune_mbtiles_drops_features_on_unknown_filter_when_configured() {
             threads: 2,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
             keep_unknown_filters: false,
+            ..PruneOptions::default()
         },
     )
     .expect("prune mbtiles");
@@ -439,10 +446,7 @@ fn prune_mbtiles_handles_multiple_readers() {
             threads: 4,
             io_batch: 10,
             readers: 2,
-            read_cache_mb: None,
-            write_cache_mb: None,
-            drop_empty_tiles: false,
-            keep_unknown_filters: true,
+            ..PruneOptions::default()
         },
     )
     .expect("prune mbtiles");
@@ -475,11 +479,8 @@ fn prune_mbtiles_drop_empty_tiles() {
         PruneOptions {
             threads: 2,
             io_batch: 10,
-            readers: 1,
-            read_cache_mb: None,
-            write_cache_mb: None,
             drop_empty_tiles: true,
-            keep_unknown_filters: true,
+            ..PruneOptions::default()
         },
     )
     .expect("prune mbtiles");
@@ -487,3 +488,361 @@ fn prune_mbtiles_drop_empty_tiles() {
     let report = inspect_mbtiles(&output).expect("inspect output");
     assert_eq!(report.overall.tile_count, 0);
 }
+
+#[test]
+fn prune_mbtiles_keeps_union_of_styles() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    let mut tile = Tile::new(4096);
+    let mut layer = tile.create_layer("roads");
+    for (index, class) in ["primary", "secondary", "service"].into_iter().enumerate() {
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(index as f64, 1.0)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let mut feature = layer.into_feature(geom);
+        feature.add_tag_string("class", class);
+        layer = feature.into_layer();
+    }
+    tile.add_layer(layer).expect("add roads");
+    conn.execute(
+        "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (0, 0, 0, ?1)",
+        (tile.to_bytes().expect("tile bytes"),),
+    )
+    .expect("tile insert");
+
+    let style_json = |class: &str| {
+        format!(
+            r#"{{"version":8,"sources":{{"osm":{{"type":"vector"}}}},"layers":[{{"id":"roads","type":"line","source":"osm","source-layer":"roads","filter":["in","class","primary","{class}"]}}]}}"#
+        )
+    };
+    let light_path = dir.path().join("light.json");
+    let dark_path = dir.path().join("dark.json");
+    fs::write(&light_path, style_json("primary")).expect("write light");
+    fs::write(&dark_path, style_json("secondary")).expect("write dark");
+    let style = MapboxStyle::union(vec![
+        read_style(&light_path).expect("read light"),
+        read_style(&dark_path).expect("read dark"),
+    ]);
+    assert_eq!(style.style_names().len(), 2);
+
+    let stats = prune_mbtiles_layer_only(
+        &input,
+        &output,
+        &style,
+        true,
+        PruneOptions {
+            io_batch: 10,
+            ..PruneOptions::default()
+        },
+    )
+    .expect("prune mbtiles");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let reader = Reader::new(data).expect("decode");
+    let features = reader.get_features(0).expect("features");
+    let classes: Vec<_> = features
+        .iter()
+        .map(|feature| feature.properties.as_ref().expect("props")["class"].clone())
+        .collect();
+    assert_eq!(
+        classes,
+        vec![
+            mvt_reader::feature::Value::String("primary".to_string()),
+            mvt_reader::feature::Value::String("secondary".to_string()),
+        ]
+    );
+
+    // The primary road is credited to the first style that keeps it.
+    let kept = &stats.kept_features_by_style["roads"];
+    assert_eq!(kept[&light_path.display().to_string()], 1);
+    assert_eq!(kept[&dark_path.display().to_string()], 1);
+}
+
+#[test]
+fn prune_mbtiles_drops_properties_the_style_does_not_read() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style_path = dir.path().join("style.json");
+
+    let mut tile = Tile::new(4096);
+    let mut layer = tile.create_layer("roads");
+    for (index, name) in ["Main", "High"].into_iter().enumerate() {
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(index as f64, 1.0)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let mut feature = layer.into_feature(geom);
+        feature.add_tag_string("class", "primary");
+        feature.add_tag_string("name", name);
+        feature.add_tag_string("name:de", &format!("{name}strasse"));
+        feature.add_tag_uint("osm_id", 42);
+        layer = feature.into_layer();
+    }
+    tile.add_layer(layer).expect("add roads");
+    create_tiles_mbtiles(&input, vec![(0, tile)]);
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"symbol","source":"osm","source-layer":"roads","filter":["==","class","primary"],"layout":{"text-field":"{name}"}}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+
+    let stats = prune_mbtiles_layer_only(
+        &input,
+        &output,
+        &style,
+        true,
+        PruneOptions {
+            io_batch: 10,
+            prune_attributes: true,
+            ..PruneOptions::default()
+        },
+    )
+    .expect("prune mbtiles");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let reader = Reader::new(data).expect("decode");
+    let features = reader.get_features(0).expect("features");
+    assert_eq!(features.len(), 2);
+    for feature in &features {
+        let mut keys: Vec<_> = feature
+            .properties
+            .as_ref()
+            .expect("props")
+            .keys()
+            .cloned()
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["class", "name"]);
+    }
+
+    let removed = &stats.removed_attribute_bytes_by_key;
+    assert_eq!(
+        removed.keys().collect::<Vec<_>>(),
+        vec!["name:de", "osm_id"]
+    );
+    // Two distinct names ("Mainstrasse", "Highstrasse") outweigh one shared id.
+    assert!(removed["name:de"] > removed["osm_id"]);
+}
+
+#[test]
+fn name_language_recognizes_translation_keys() {
+    assert_eq!(name_language("name:fr"), Some("fr"));
+    assert_eq!(name_language("name_de"), Some("de"));
+    assert_eq!(name_language("name:zh-Hant"), Some("zh-Hant"));
+    assert_eq!(name_language("name"), None);
+    assert_eq!(name_language("name_int"), None);
+    assert_eq!(name_language("name:latin"), None);
+    assert_eq!(name_language("name:nonlatin"), None);
+    assert_eq!(name_language("name:left"), None);
+    assert_eq!(name_language("name_en_short"), None);
+}
+
+#[test]
+fn prune_mbtiles_keeps_only_requested_languages() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style_path = dir.path().join("style.json");
+
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("places");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(1.0, 1.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.add_tag_string("name", "Tokyo");
+    feature.add_tag_string("name:en", "Tokyo");
+    feature.add_tag_string("name:ja", "東京");
+    feature.add_tag_string("name:fr", "Tokyo");
+    feature.add_tag_string("name:zh-Hant", "東京");
+    feature.add_tag_string("name_de", "Tokio");
+    feature.add_tag_string("name_int", "Tokyo");
+    feature.add_tag_string("name:latin", "Tokyo");
+    tile.add_layer(feature.into_layer()).expect("add places");
+    create_tiles_mbtiles(&input, vec![(0, tile)]);
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"places","type":"symbol","source":"osm","source-layer":"places"}]}"#,
+    )
+    .expect("write style");
+    let style = read_style(&style_path).expect("read style");
+
+    let stats = prune_mbtiles_layer_only(
+        &input,
+        &output,
+        &style,
+        true,
+        PruneOptions {
+            io_batch: 10,
+            keep_languages: Some(vec!["en".to_string(), "ja".to_string(), "de".to_string()]),
+            ..PruneOptions::default()
+        },
+    )
+    .expect("prune mbtiles");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row("SELECT tile_data FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    let reader = Reader::new(data).expect("decode");
+    let features = reader.get_features(0).expect("features");
+    let mut keys: Vec<_> = features[0]
+        .properties
+        .as_ref()
+        .expect("props")
+        .keys()
+        .cloned()
+        .collect();
+    keys.sort_unstable();
+    assert_eq!(
+        keys,
+        vec![
+            "name",
+            "name:en",
+            "name:ja",
+            "name:latin",
+            "name_de",
+            "name_int"
+        ]
+    );
+
+    let removed = &stats.removed_names_by_language;
+    assert_eq!(removed.keys().collect::<Vec<_>>(), vec!["fr", "zh-Hant"]);
+    assert_eq!(
+        removed["fr"],
+        RemovedNames {
+            properties: 1,
+            value_bytes: 5,
+        }
+    );
+    assert_eq!(
+        removed["zh-Hant"],
+        RemovedNames {
+            properties: 1,
+            value_bytes: 6,
+        }
+    );
+}
+
+#[test]
+fn prune_mbtiles_applies_attribute_rules_per_zoom() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    let style_path = dir.path().join("style.json");
+    let rules_path = dir.path().join("rules.toml");
+
+    let mut tiles = Vec::new();
+    for zoom in [1u8, 2] {
+        let mut tile = Tile::new(4096);
+        let layer = tile.create_layer("roads");
+        let geom = GeomEncoder::new(GeomType::Point)
+            .point(1.0, 1.0)
+            .expect("point")
+            .encode()
+            .expect("encode");
+        let mut feature = layer.into_feature(geom);
+        feature.add_tag_string("class", "primary");
+        feature.add_tag_string("ref", "101");
+        feature.add_tag_double("width", 7.25);
+        feature.add_tag_uint("osm_id", 42);
+        tile.add_layer(feature.into_layer()).expect("add roads");
+        tiles.push((zoom, tile));
+    }
+    create_tiles_mbtiles(&input, tiles);
+    fs::write(
+        &style_path,
+        r#"{"version":8,"sources":{"osm":{"type":"vector"}},"layers":[{"id":"roads","type":"line","source":"osm","source-layer":"roads"}]}"#,
+    )
+    .expect("write style");
+    fs::write(
+        &rules_path,
+        r#"
+[[layers.roads]]
+maxzoom = 1
+keep = ["class"]
+
+[[layers.roads]]
+minzoom = 2
+drop = ["osm_id"]
+rename = { ref = "route" }
+cast = { ref = "number", width = "int" }
+"#,
+    )
+    .expect("write rules");
+    let style = read_style(&style_path).expect("read style");
+
+    prune_mbtiles_layer_only(
+        &input,
+        &output,
+        &style,
+        true,
+        PruneOptions {
+            io_batch: 10,
+            rules: Some(read_rules(&rules_path).expect("read rules")),
+            ..PruneOptions::default()
+        },
+    )
+    .expect("prune mbtiles");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let read_props = |zoom: u8| {
+        let data: Vec<u8> = conn
+            .query_row(
+                "SELECT tile_data FROM tiles WHERE zoom_level = ?1",
+                [zoom],
+                |row| row.get(0),
+            )
+            .expect("read tile");
+        let reader = Reader::new(data).expect("decode");
+        let features = reader.get_features(0).expect("features");
+        let props = features[0].properties.clone().expect("props");
+        props.into_iter().collect::<BTreeMap<_, _>>()
+    };
+    assert_eq!(
+        read_props(1),
+        BTreeMap::from([(
+            "class".to_string(),
+            mvt_reader::feature::Value::String("primary".to_string())
+        )])
+    );
+    assert_eq!(
+        read_props(2),
+        BTreeMap::from([
+            (
+                "class".to_string(),
+                mvt_reader::feature::Value::String("primary".to_string())
+            ),
+            ("route".to_string(), mvt_reader::feature::Value::UInt(101)),
+            ("width".to_string(), mvt_reader::feature::Value::UInt(7)),
+        ])
+    );
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use mvt_reader::feature::Value;
+use vt_optimizer::rules::{AttributeRules, Cast, read_rules};
+
+fn props(entries: &[(&str, Value)]) -> BTreeMap<String, Value> {
+    entries
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect()
+}
+
+#[test]
+fn read_rules_accepts_json_and_toml() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let json_path = dir.path().join("rules.json");
+    let toml_path = dir.path().join("rules.toml");
+    fs::write(
+        &json_path,
+        r#"{"layers":{"roads":[{"maxzoom":9,"keep":["class","ref"],"rename":{"ref":"r"},"cast":{"ref":"number","width":{"round":1}}}]}}"#,
+    )
+    .expect("write json");
+    fs::write(
+        &toml_path,
+        r#"
+[[layers.roads]]
+maxzoom = 9
+keep = ["class", "ref"]
+rename = { ref = "r" }
+cast = { ref = "number", width = { round = 1 } }
+"#,
+    )
+    .expect("write toml");
+
+    let json = read_rules(&json_path).expect("read json");
+    let toml = read_rules(&toml_path).expect("read toml");
+    assert_eq!(json, toml);
+    let rule = &json.layers["roads"][0];
+    assert_eq!(rule.minzoom, None);
+    assert_eq!(rule.maxzoom, Some(9));
+    assert_eq!(rule.cast["ref"], Cast::Number);
+    assert_eq!(rule.cast["width"], Cast::Round(1));
+
+    fs::write(
+        &json_path,
+        r#"{"layers":{"roads":[{"minzoom":8,"maxzoom":4}]}}"#,
+    )
+    .expect("write json");
+    let err = read_rules(&json_path).expect_err("inverted zoom range");
+    assert!(err.to_string().contains("minzoom 8 > maxzoom 4"));
+}
+
+#[test]
+fn layer_rule_picks_first_rule_covering_zoom() {
+    let rules: AttributeRules = serde_json::from_str(
+        r#"{"layers":{
+            "roads":[{"maxzoom":5,"drop":["name"]},{"minzoom":6,"drop":["ref"]}],
+            "*":[{"minzoom":10,"drop":["osm_id"]}]
+        }}"#,
+    )
+    .expect("parse rules");
+
+    let low = rules.layer_rule("roads", 5).expect("low rule");
+    assert!(low.drop.contains("name"));
+    let high = rules.layer_rule("roads", 14).expect("high rule");
+    assert!(high.drop.contains("ref"));
+    assert!(rules.layer_rule("water", 9).is_none());
+    let fallback = rules.layer_rule("water", 10).expect("fallback rule");
+    assert!(fallback.drop.contains("osm_id"));
+}
+
+#[test]
+fn layer_rule_transforms_properties() {
+    let rules: AttributeRules = serde_json::from_str(
+        r#"{"layers":{"roads":[{
+            "drop":["osm_id"],
+            "rename":{"ref":"r","lanes":"l"},
+            "cast":{"ref":"number","lanes":"int","height":{"round":1},"layer":"string","bad":"number"}
+        }]}}"#,
+    )
+    .expect("parse rules");
+    let rule = rules.layer_rule("roads", 0).expect("rule");
+
+    let out = rule.transform(props(&[
+        ("osm_id", Value::UInt(42)),
+        ("ref", Value::String("-12".to_string())),
+        ("lanes", Value::Double(2.6)),
+        ("height", Value::Double(12.345)),
+        ("layer", Value::Int(1)),
+        ("bad", Value::String("n/a".to_string())),
+    ]));
+    assert_eq!(
+        out,
+        props(&[
+            ("r", Value::SInt(-12)),
+            ("l", Value::UInt(3)),
+            ("height", Value::Double(12.3)),
+            ("layer", Value::String("1".to_string())),
+            ("bad", Value::String("n/a".to_string())),
+        ])
+    );
+
+    let keep: AttributeRules = serde_json::from_str(
+        r#"{"layers":{"roads":[{"keep":["class"],"cast":{"rank":"number"}}]}}"#,
+    )
+    .expect("parse rules");
+    let out = keep
+        .layer_rule("roads", 0)
+        .expect("rule")
+        .transform(props(&[
+            ("class", Value::String("primary".to_string())),
+            ("rank", Value::String("1.5".to_string())),
+        ]));
+    assert_eq!(
+        out,
+        props(&[("class", Value::String("primary".to_string()))])
+    );
+}
+
+#[test]
+fn round_cast_keeps_value_when_scaling_overflows() {
+    let rules: AttributeRules = serde_json::from_str(
+        r#"{"layers":{"roads":[{"cast":{"height":{"round":255},"width":{"round":15}}}]}}"#,
+    )
+    .expect("parse rules");
+    let out = rules
+        .layer_rule("roads", 0)
+        .expect("rule")
+        .transform(props(&[
+            ("height", Value::Double(12.345)),
+            ("width", Value::Double(1e300)),
+        ]));
+    assert_eq!(
+        out,
+        props(&[
+            ("height", Value::Double(12.345)),
+            ("width", Value::Double(1e300)),
+        ])
+    );
+}
@@ -1,10 +1,20 @@
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
 use std::path::Path;
 
+use geo_types::{Coord, Geometry, LineString, Polygon};
+
 use mvt::{GeomEncoder, GeomType, Tile};
 use mvt_reader::Reader;
-use vt_optimizer::mbtiles::{InspectOptions, TileCoord, simplify_mbtiles_tile};
+use vt_optimizer::mbtiles::{
+    DouglasPeucker, InspectOptions, MbtilesScheme, SimplifyAlgorithm, SimplifyEngine,
+    SimplifyMethod, SimplifyOptions, TileCoord, ToleranceRule, TopologyPreserving,
+    VisvalingamWhyatt, parse_tolerance_rule, simplify_mbtiles, simplify_mbtiles_tile,
+    simplify_mbtiles_tile_with_scheme,
+};
 use vt_optimizer::pmtiles::{
-    inspect_pmtiles_with_options, mbtiles_to_pmtiles, simplify_pmtiles_tile,
+    inspect_pmtiles_with_options, mbtiles_to_pmtiles, read_header, simplify_pmtiles,
+    simplify_pmtiles_tile,
 };
 
 fn create_layer_tile() -> Vec<u8> {
@@ -208,9 +218,373 @@ fn simplify_pmtiles_tile_outputs_single_tile() {
         x: 0,
         y: 0,
     };
-    simplify_pmtiles_tile(&pmtiles, &output, coord, &[], None).expect("simplify");
+    simplify_pmtiles_tile(
+        &pmtiles,
+        &output,
+        coord,
+        &[],
+        None,
+        SimplifyMethod::default(),
+    )
+    .expect("simplify");
 
     let report =
         inspect_pmtiles_with_options(&output, &InspectOptions::default()).expect("inspect");
     assert_eq!(report.overall.tile_count, 1);
 }
+
+#[test]
+fn simplify_mbtiles_tile_addresses_tms_rows_in_xyz() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_layer_mbtiles(&input);
+    let conn = rusqlite::Connection::open(&input).expect("open");
+    conn.execute(
+        "UPDATE tiles SET zoom_level = 2, tile_column = 1, tile_row = 0",
+        [],
+    )
+    .expect("move tile");
+    drop(conn);
+
+    // TMS row 0 at z2 is XYZ y=3; the output keeps the TMS row.
+    let coord = TileCoord {
+        zoom: 2,
+        x: 1,
+        y: 3,
+    };
+    simplify_mbtiles_tile(&input, &output, coord, &[], None).expect("simplify");
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let row: u32 = conn
+        .query_row("SELECT tile_row FROM tiles", [], |row| row.get(0))
+        .expect("read tile");
+    assert_eq!(row, 0);
+
+    let xyz_output = dir.path().join("xyz.mbtiles");
+    let coord = TileCoord {
+        zoom: 2,
+        x: 1,
+        y: 0,
+    };
+    simplify_mbtiles_tile_with_scheme(
+        &input,
+        &xyz_output,
+        coord,
+        MbtilesScheme::Xyz,
+        &[],
+        None,
+        SimplifyMethod::default(),
+    )
+    .expect("simplify xyz");
+}
+
+fn create_line_tileset(path: &Path, zooms: &[u8]) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    for zoom in zooms {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, 0, 0, ?2)",
+            (zoom, create_line_tile()),
+        )
+        .expect("tile insert");
+    }
+}
+
+fn simplify_options(minzoom: Option<u8>, maxzoom: Option<u8>, rules: &[&str]) -> SimplifyOptions {
+    SimplifyOptions {
+        threads: 2,
+        io_batch: 1,
+        readers: 2,
+        read_cache_mb: None,
+        write_cache_mb: None,
+        minzoom,
+        maxzoom,
+        tolerances: rules
+            .iter()
+            .map(|rule| parse_tolerance_rule(rule).expect("tolerance rule"))
+            .collect(),
+        method: SimplifyMethod::default(),
+    }
+}
+
+fn line_vertices(path: &Path, zoom: u8) -> usize {
+    let conn = rusqlite::Connection::open(path).expect("open output");
+    let data: Vec<u8> = conn
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = ?1",
+            [zoom],
+            |row| row.get(0),
+        )
+        .expect("read tile");
+    let reader = Reader::new(data).expect("decode");
+    let features = reader.get_features(0).expect("features");
+    match features[0].get_geometry() {
+        geo_types::Geometry::LineString(line) => line.0.len(),
+        _ => panic!("expected linestring geometry"),
+    }
+}
+
+#[test]
+fn parse_tolerance_rule_reads_layer_and_zoom_range() {
+    assert_eq!(
+        parse_tolerance_rule("2").expect("bare"),
+        ToleranceRule {
+            layer: None,
+            minzoom: 0,
+            maxzoom: u8::MAX,
+            tolerance: 2.0,
+        }
+    );
+    assert_eq!(
+        parse_tolerance_rule("roads@0-8=4").expect("layer and range"),
+        ToleranceRule {
+            layer: Some("roads".to_string()),
+            minzoom: 0,
+            maxzoom: 8,
+            tolerance: 4.0,
+        }
+    );
+    assert_eq!(
+        parse_tolerance_rule("@12=0.5").expect("zoom only"),
+        ToleranceRule {
+            layer: None,
+            minzoom: 12,
+            maxzoom: 12,
+            tolerance: 0.5,
+        }
+    );
+    assert!(parse_tolerance_rule("roads@9-3=1").is_err());
+    assert!(parse_tolerance_rule("roads=-1").is_err());
+    assert!(parse_tolerance_rule("roads=").is_err());
+
+    // Later rules override earlier ones; zero leaves the layer untouched.
+    let options = simplify_options(None, Some(10), &["1", "roads@5-8=3", "water=0"]);
+    assert_eq!(options.tolerance("roads", 4), Some(1.0));
+    assert_eq!(options.tolerance("roads", 6), Some(3.0));
+    assert_eq!(options.tolerance("water", 6), None);
+    assert_eq!(options.tolerance("roads", 11), None);
+}
+
+#[test]
+fn simplify_mbtiles_applies_tolerances_per_zoom() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_line_tileset(&input, &[0, 1, 2]);
+
+    let stats = simplify_mbtiles(
+        &input,
+        &output,
+        simplify_options(None, Some(1), &["roads@1-2=0.5", "buildings=4"]),
+    )
+    .expect("simplify");
+
+    assert_eq!(stats.tiles, 3);
+    // z0 is only covered by the buildings rule, and z2 is above --maxzoom.
+    assert_eq!(stats.simplified_tiles, 2);
+    assert_eq!(stats.by_layer.keys().collect::<Vec<_>>(), vec!["roads"]);
+    let roads = &stats.by_layer["roads"];
+    assert_eq!(roads.keys().copied().collect::<Vec<_>>(), vec![1]);
+    assert_eq!(roads[&1].feature_count, 1);
+    assert_eq!(roads[&1].vertices_before, 5);
+    assert!(roads[&1].vertices_after <= 3);
+
+    assert_eq!(line_vertices(&output, 0), 5);
+    assert_eq!(line_vertices(&output, 1) as u64, roads[&1].vertices_after);
+    assert_eq!(line_vertices(&output, 2), 5);
+}
+
+#[test]
+fn simplify_pmtiles_simplifies_every_tile() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("input.pmtiles");
+    let output = dir.path().join("output.pmtiles");
+    create_line_tileset(&mbtiles, &[0, 1, 2]);
+    mbtiles_to_pmtiles(&mbtiles, &pmtiles).expect("to pmtiles");
+
+    let stats = simplify_pmtiles(&pmtiles, &output, simplify_options(None, None, &["0.5"]))
+        .expect("simplify");
+
+    assert_eq!(stats.tiles, 3);
+    assert_eq!(stats.simplified_tiles, 3);
+    let total = stats.total();
+    assert_eq!(total.feature_count, 3);
+    assert_eq!(total.vertices_before, 15);
+    assert!(total.vertices_after <= 9);
+    let report =
+        inspect_pmtiles_with_options(&output, &InspectOptions::default()).expect("inspect");
+    assert_eq!(report.overall.tile_count, 3);
+}
+
+#[test]
+fn simplify_pmtiles_fails_on_corrupt_tile() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("input.pmtiles");
+    let output = dir.path().join("output.pmtiles");
+    create_line_tileset(&mbtiles, &[0, 1, 2]);
+    mbtiles_to_pmtiles(&mbtiles, &pmtiles).expect("to pmtiles");
+    // Overwrite the stored tile with a gzip header and an invalid body.
+    let header = read_header(&fs::File::open(&pmtiles).expect("open")).expect("read header");
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(&pmtiles)
+        .expect("open for write");
+    file.seek(SeekFrom::Start(header.data_offset))
+        .expect("seek data");
+    let mut corrupt = vec![0u8; 16];
+    corrupt[..4].copy_from_slice(&[0x1f, 0x8b, 0x08, 0x00]);
+    file.write_all(&corrupt).expect("corrupt tile");
+    drop(file);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let result = simplify_pmtiles(&pmtiles, &output, simplify_options(None, None, &["0.5"]));
+        let _ = tx.send(result.map(|_| ()));
+    });
+    let err = rx
+        .recv_timeout(std::time::Duration::from_secs(60))
+        .expect("simplify should not hang")
+        .expect_err("corrupt tile fails");
+    assert!(format!("{err:#}").contains("decode tile"), "{err:#}");
+}
+
+fn coords(points: &[(f32, f32)]) -> Vec<Coord<f32>> {
+    points.iter().map(|(x, y)| Coord { x: *x, y: *y }).collect()
+}
+
+fn polygon(points: &[(f32, f32)]) -> Geometry<f32> {
+    Geometry::Polygon(Polygon::new(LineString::from(coords(points)), Vec::new()))
+}
+
+fn exterior(geometry: &Geometry<f32>) -> Vec<Coord<f32>> {
+    match geometry {
+        Geometry::Polygon(polygon) => polygon.exterior().0.clone(),
+        _ => panic!("expected polygon geometry"),
+    }
+}
+
+/// Two squares sharing a wiggly edge along x=10.
+fn adjacent_squares() -> Vec<Geometry<f32>> {
+    vec![
+        polygon(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.2, 2.0),
+            (11.0, 5.0),
+            (10.2, 8.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (0.0, 0.0),
+        ]),
+        polygon(&[
+            (10.0, 0.0),
+            (20.0, 0.0),
+            (20.0, 10.0),
+            (10.0, 10.0),
+            (10.2, 8.0),
+            (11.0, 5.0),
+            (10.2, 2.0),
+            (10.0, 0.0),
+        ]),
+    ]
+}
+
+#[test]
+fn simplify_geometry_stays_available_from_algo() {
+    let line = Geometry::LineString(LineString::from(coords(&[
+        (0.0, 0.0),
+        (1.0, 0.1),
+        (2.0, 0.0),
+    ])));
+    let simplified = vt_optimizer::mbtiles::algo::simplify_geometry(&line, 1.0);
+    assert_eq!(
+        simplified,
+        vt_optimizer::mbtiles::simplify_geometry_with(&DouglasPeucker, &line, 1.0)
+    );
+    assert_eq!(
+        simplified,
+        Geometry::LineString(LineString::from(coords(&[(0.0, 0.0), (2.0, 0.0)])))
+    );
+}
+
+#[test]
+fn visvalingam_drops_points_below_tolerance_area() {
+    let line = coords(&[(0.0, 0.0), (1.0, 0.1), (2.0, 0.0), (5.0, 3.0), (8.0, 0.0)]);
+    assert_eq!(
+        VisvalingamWhyatt.simplify_line(&line, 1.0),
+        coords(&[(0.0, 0.0), (2.0, 0.0), (5.0, 3.0), (8.0, 0.0)])
+    );
+    assert_eq!(
+        VisvalingamWhyatt.simplify_line(&line, 4.0),
+        coords(&[(0.0, 0.0), (8.0, 0.0)])
+    );
+    assert_eq!(VisvalingamWhyatt.simplify_line(&line, 0.0), line);
+}
+
+#[test]
+fn topology_preserving_simplifies_shared_edges_identically() {
+    let engine = TopologyPreserving(DouglasPeucker);
+    let simplified = engine.simplify_tile(&adjacent_squares(), &[0.5, 0.5]);
+    let left = exterior(&simplified[0]);
+    let right = exterior(&simplified[1]);
+
+    let shared = |ring: &[Coord<f32>]| {
+        let mut points = ring
+            .iter()
+            .filter(|point| point.x >= 10.0 && point.x < 20.0)
+            .map(|point| (point.x, point.y))
+            .collect::<Vec<_>>();
+        points.sort_by(|a, b| a.partial_cmp(b).expect("finite"));
+        points.dedup();
+        points
+    };
+    assert_eq!(shared(&left), vec![(10.0, 0.0), (10.0, 10.0), (11.0, 5.0)]);
+    assert_eq!(shared(&left), shared(&right));
+    assert_eq!(left.len(), 6);
+    assert_eq!(left.first(), left.last());
+    assert_eq!(right.len(), 6);
+
+    // A zero tolerance on one side keeps the shared edge for both.
+    let simplified = engine.simplify_tile(&adjacent_squares(), &[0.5, 0.0]);
+    assert_eq!(simplified[1], adjacent_squares()[1]);
+    assert_eq!(shared(&exterior(&simplified[0])).len(), 5);
+}
+
+#[test]
+fn topology_preserving_keeps_rings_from_collapsing() {
+    let squares = adjacent_squares();
+    let simplified = TopologyPreserving(VisvalingamWhyatt).simplify_tile(&squares, &[100.0, 100.0]);
+    assert_eq!(exterior(&simplified[0]), exterior(&squares[0]));
+    assert_eq!(exterior(&simplified[1]), exterior(&squares[1]));
+}
+
+#[test]
+fn simplify_mbtiles_uses_selected_engine() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = dir.path().join("input.mbtiles");
+    let output = dir.path().join("output.mbtiles");
+    create_line_tileset(&input, &[0]);
+
+    let mut options = simplify_options(None, None, &["0.5"]);
+    options.method = SimplifyMethod {
+        algorithm: SimplifyAlgorithm::Visvalingam,
+        preserve_topology: true,
+    };
+    let stats = simplify_mbtiles(&input, &output, options).expect("simplify");
+
+    assert_eq!(stats.total().vertices_after, 2);
+    assert_eq!(line_vertices(&output, 0), 2);
+}
@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::Path;
 
-use vt_optimizer::style::read_style;
+use vt_optimizer::style::{match_style_source, read_style, read_style_with_source};
 
 #[test]
 fn style_visibility_checks_zoom_and_paint() {
@@ -28,7 +30,9 @@ fn style_visibility_checks_zoom_and_paint() {
 
     assert!(!style.is_layer_visible_on_zoom("water", 1));
     assert!(style.is_layer_visible_on_zoom("water", 3));
-    assert!(!style.is_layer_visible_on_zoom("roads", 3));
+    assert!(!style.is_layer_visible_on_zoom("roads", 2));
+    // z3 tiles are also drawn at z3.5, where the width is already 1.
+    assert!(style.is_layer_visible_on_zoom("roads", 3));
     assert!(style.is_layer_visible_on_zoom("roads", 4));
 }
 
@@ -405,3 +409,352 @@ fn style_filter_supports_legacy_not_syntax() {
         vt_optimizer::style::FilterResult::True
     );
 }
+
+#[test]
+fn style_filter_supports_ordering_comparisons() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "poi-legacy", "type": "symbol", "source": "osm", "source-layer": "poi",
+      "filter": ["all", ["<=", "rank", 5], [">=", "zoom", 12]]
+    },
+    { "id": "poi-expression", "type": "symbol", "source": "osm", "source-layer": "poi",
+      "filter": ["all", [">", ["get", "rank"], 20], ["<", ["get", "name"], "m"]]
+    }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let style = read_style(&style_path).expect("read style");
+    let feature = |rank: mvt_reader::feature::Value, name: &str| mvt_reader::feature::Feature {
+        geometry: geo_types::Geometry::Point(geo_types::Point::new(0.0, 0.0)),
+        id: None,
+        properties: Some(
+            [
+                ("rank".to_string(), rank),
+                (
+                    "name".to_string(),
+                    mvt_reader::feature::Value::String(name.to_string()),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        ),
+    };
+    let check = |zoom: u8, feature: &mvt_reader::feature::Feature| {
+        let mut unknown = 0usize;
+        let result = style.should_keep_feature("poi", zoom, feature, &mut unknown);
+        assert_eq!(unknown, 0);
+        result
+    };
+    let keep = vt_optimizer::style::FilterResult::True;
+    let drop = vt_optimizer::style::FilterResult::False;
+
+    let low_rank = feature(mvt_reader::feature::Value::Int(5), "zoo");
+    assert_eq!(check(12, &low_rank), keep);
+    assert_eq!(check(11, &low_rank), drop);
+    let mid_rank = feature(mvt_reader::feature::Value::Double(5.5), "alpha");
+    assert_eq!(check(14, &mid_rank), drop);
+    let high_rank = feature(mvt_reader::feature::Value::UInt(21), "alpha");
+    assert_eq!(check(3, &high_rank), keep);
+    let late_name = feature(mvt_reader::feature::Value::UInt(21), "north");
+    assert_eq!(check(3, &late_name), drop);
+    // A string never orders against a number.
+    let string_rank = feature(mvt_reader::feature::Value::String("1".to_string()), "a");
+    assert_eq!(check(14, &string_rank), drop);
+}
+
+#[test]
+fn style_filter_supports_extended_expressions() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "in-literal", "type": "symbol", "source": "osm", "source-layer": "in",
+      "filter": ["in", ["downcase", ["get", "class"]], ["literal", ["park", "zoo"]]] },
+    { "id": "not-in", "type": "symbol", "source": "osm", "source-layer": "not-in",
+      "filter": ["!", ["in", ["get", "class"], ["literal", ["park"]]]] },
+    { "id": "substring", "type": "symbol", "source": "osm", "source-layer": "substring",
+      "filter": ["in", "ar", ["get", "class"]] },
+    { "id": "conversions", "type": "symbol", "source": "osm", "source-layer": "conversions",
+      "filter": ["all",
+        [">=", ["to-number", ["get", "rank"]], 2],
+        ["==", ["to-string", ["get", "level"]], "3"],
+        ["to-boolean", ["get", "name"]]
+      ] },
+    { "id": "strings", "type": "symbol", "source": "osm", "source-layer": "strings",
+      "filter": ["all",
+        ["==", ["concat", ["upcase", ["slice", ["get", "name"], 0, 2]], "-", ["id"]], "CE-7"],
+        ["==", ["index-of", "tra", ["get", "name"]], 3],
+        ["==", ["length", ["get", "name"]], 7],
+        ["==", ["get", "class", ["properties"]], "Park"]
+      ] },
+    { "id": "math", "type": "symbol", "source": "osm", "source-layer": "math",
+      "filter": ["all",
+        ["==", ["+", ["*", ["get", "level"], 2], ["-", 1], ["/", 6, 3]], 7],
+        ["==", ["%", ["^", 2, ["get", "level"]], 5], 3],
+        ["==", ["max", ["min", ["get", "level"], 10], 1], 3]
+      ] },
+    { "id": "step", "type": "symbol", "source": "osm", "source-layer": "step",
+      "filter": ["==", ["step", ["zoom"], "low", 10, "mid", 14, "high"], ["get", "band"]] }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let style = read_style(&style_path).expect("read style");
+    let feature = mvt_reader::feature::Feature {
+        geometry: geo_types::Geometry::Point(geo_types::Point::new(0.0, 0.0)),
+        id: Some(7),
+        properties: Some(
+            [
+                (
+                    "class".to_string(),
+                    mvt_reader::feature::Value::String("Park".to_string()),
+                ),
+                (
+                    "name".to_string(),
+                    mvt_reader::feature::Value::String("central".to_string()),
+                ),
+                (
+                    "rank".to_string(),
+                    mvt_reader::feature::Value::String("2.5".to_string()),
+                ),
+                ("level".to_string(), mvt_reader::feature::Value::Int(3)),
+                (
+                    "band".to_string(),
+                    mvt_reader::feature::Value::String("mid".to_string()),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        ),
+    };
+    let check = |layer: &str, zoom: u8| {
+        let mut unknown = 0usize;
+        let result = style.should_keep_feature(layer, zoom, &feature, &mut unknown);
+        assert_eq!(unknown, 0, "{layer} was not fully evaluated");
+        result
+    };
+    let keep = vt_optimizer::style::FilterResult::True;
+    let drop = vt_optimizer::style::FilterResult::False;
+
+    assert_eq!(check("in", 10), keep);
+    assert_eq!(check("not-in", 10), keep);
+    assert_eq!(check("substring", 10), keep);
+    assert_eq!(check("conversions", 10), keep);
+    assert_eq!(check("strings", 10), keep);
+    assert_eq!(check("math", 10), keep);
+    assert_eq!(check("step", 12), keep);
+    assert_eq!(check("step", 9), drop);
+    assert_eq!(check("step", 14), drop);
+}
+
+#[test]
+fn style_visibility_evaluates_zoom_expressions() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "fade-in", "type": "fill", "source": "osm", "source-layer": "landuse",
+      "paint": { "fill-opacity": ["interpolate", ["linear"], ["zoom"], 10, 0, 12, 1] } },
+    { "id": "fade-out", "type": "line", "source": "osm", "source-layer": "roads",
+      "paint": { "line-opacity": ["interpolate", ["exponential", 1.5], ["zoom"], 5, 1, 8, 0] } },
+    { "id": "stepped", "type": "symbol", "source": "osm", "source-layer": "poi",
+      "paint": { "text-opacity": ["step", ["zoom"], 0, 14, 1, 16, 0] } },
+    { "id": "legacy", "type": "circle", "source": "osm", "source-layer": "place",
+      "paint": { "circle-radius": { "stops": [[4, 0], [6, 0], [8, 4]] } } },
+    { "id": "legacy-interval", "type": "circle", "source": "osm", "source-layer": "housenumber",
+      "paint": { "circle-opacity": { "type": "interval", "stops": [[3, 1], [9, 0]] } } },
+    { "id": "half-step", "type": "line", "source": "osm", "source-layer": "aeroway",
+      "paint": { "line-opacity": ["step", ["zoom"], 0, 10.5, 1] } },
+    { "id": "data-driven", "type": "line", "source": "osm", "source-layer": "water",
+      "paint": { "line-width": ["interpolate", ["linear"], ["get", "width"], 0, 0, 10, 5] } }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let style = read_style(&style_path).expect("read style");
+    let visible = |layer: &str, zoom: u8| style.is_layer_visible_on_zoom(layer, zoom);
+
+    // z10 tiles are drawn up to z10.99, where the fade-in is already visible.
+    assert!(!visible("landuse", 9));
+    assert!(visible("landuse", 10));
+    assert!(visible("landuse", 11));
+    assert!(visible("landuse", 14));
+
+    assert!(visible("roads", 2));
+    assert!(visible("roads", 7));
+    assert!(!visible("roads", 8));
+    assert!(!visible("roads", 12));
+
+    assert!(!visible("poi", 13));
+    assert!(visible("poi", 14));
+    assert!(visible("poi", 15));
+    assert!(!visible("poi", 16));
+
+    assert!(!visible("place", 3));
+    assert!(!visible("place", 5));
+    assert!(visible("place", 6));
+    assert!(visible("place", 7));
+
+    assert!(visible("housenumber", 2));
+    assert!(visible("housenumber", 8));
+    assert!(!visible("housenumber", 9));
+
+    assert!(!visible("aeroway", 9));
+    assert!(visible("aeroway", 10));
+
+    assert!(visible("water", 0));
+}
+
+const TWO_SOURCE_STYLE: &str = r#"{
+  "version": 8,
+  "sources": {
+    "basemap": { "type": "vector", "url": "pmtiles://https://example.com/tiles/planet.pmtiles" },
+    "overlay": { "type": "vector", "tiles": ["https://example.com/hydro/{z}/{x}/{y}.pbf"] },
+    "hillshade": { "type": "raster", "url": "https://example.com/planet.json" }
+  },
+  "layers": [
+    { "id": "water", "type": "fill", "source": "basemap", "source-layer": "water",
+      "filter": ["==", ["get", "class"], "lake"] },
+    { "id": "overlay-water", "type": "fill", "source": "overlay", "source-layer": "water",
+      "minzoom": 8 }
+  ]
+}"#;
+
+#[test]
+fn style_source_limits_layers_to_that_source() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(&style_path, TWO_SOURCE_STYLE).expect("write style");
+
+    let river = mvt_reader::feature::Feature {
+        geometry: geo_types::Geometry::Point(geo_types::Point::new(0.0, 0.0)),
+        id: None,
+        properties: Some(
+            [(
+                "class".to_string(),
+                mvt_reader::feature::Value::String("river".to_string()),
+            )]
+            .into_iter()
+            .collect(),
+        ),
+    };
+    let mut unknown = 0usize;
+    let all = read_style(&style_path).expect("read style");
+    assert_eq!(
+        all.should_keep_feature("water", 10, &river, &mut unknown),
+        vt_optimizer::style::FilterResult::True
+    );
+
+    let basemap = read_style_with_source(&style_path, Some("basemap")).expect("basemap");
+    assert_eq!(
+        basemap.should_keep_feature("water", 10, &river, &mut unknown),
+        vt_optimizer::style::FilterResult::False
+    );
+    let overlay = read_style_with_source(&style_path, Some("overlay")).expect("overlay");
+    assert!(!overlay.is_layer_visible_on_zoom("water", 7));
+    assert!(overlay.is_layer_visible_on_zoom("water", 8));
+
+    let err = read_style_with_source(&style_path, Some("missing")).expect_err("unknown source");
+    assert!(err.to_string().contains("no source named missing"));
+}
+
+#[test]
+fn style_source_is_matched_from_input_name_and_metadata() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(&style_path, TWO_SOURCE_STYLE).expect("write style");
+    let no_metadata = BTreeMap::new();
+
+    let matched = |input: &str, metadata: &BTreeMap<String, String>| {
+        match_style_source(&style_path, Path::new(input), metadata).expect("match source")
+    };
+    assert_eq!(
+        matched("/data/planet.pmtiles", &no_metadata).as_deref(),
+        Some("basemap")
+    );
+    assert_eq!(
+        matched("hydro.mbtiles", &no_metadata).as_deref(),
+        Some("overlay")
+    );
+    assert_eq!(
+        matched("Overlay.mbtiles", &no_metadata).as_deref(),
+        Some("overlay")
+    );
+    let metadata: BTreeMap<String, String> = [("name".to_string(), "Planet".to_string())]
+        .into_iter()
+        .collect();
+    assert_eq!(
+        matched("export.mbtiles", &metadata).as_deref(),
+        Some("basemap")
+    );
+    assert_eq!(matched("export.mbtiles", &no_metadata), None);
+}
+
+#[test]
+fn style_collects_property_keys_per_layer_and_zoom() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let style_path = dir.path().join("style.json");
+    fs::write(
+        &style_path,
+        r#"{
+  "version": 8,
+  "sources": { "osm": { "type": "vector" } },
+  "layers": [
+    { "id": "poi", "type": "symbol", "source": "osm", "source-layer": "poi",
+      "filter": ["all", ["==", "class", "shop"], ["<=", ["get", "rank"], 5], ["!=", "$type", "Polygon"]],
+      "layout": { "text-field": "{name:latin}\n{name}", "icon-image": ["concat", ["get", "subclass"], "_11"] },
+      "paint": { "text-opacity": { "property": "importance", "stops": [[0, 0.5], [1, 1]] } } },
+    { "id": "poi-detail", "type": "symbol", "source": "osm", "source-layer": "poi", "minzoom": 14,
+      "layout": { "text-field": ["get", "housenumber", ["properties"]] } },
+    { "id": "roads", "type": "line", "source": "osm", "source-layer": "roads",
+      "layout": { "text-field": ["get", ["concat", "name:", ["get", "lang"]]] } }
+  ]
+}"#,
+    )
+    .expect("write style");
+
+    let style = read_style(&style_path).expect("read style");
+    let mut keys: Vec<&str> = style
+        .used_property_keys("poi", 10)
+        .expect("static keys")
+        .into_iter()
+        .collect();
+    keys.sort_unstable();
+    assert_eq!(
+        keys,
+        vec![
+            "class",
+            "importance",
+            "name",
+            "name:latin",
+            "rank",
+            "subclass"
+        ]
+    );
+    assert!(
+        style
+            .used_property_keys("poi", 14)
+            .expect("static keys")
+            .contains("housenumber")
+    );
+    // A key computed at runtime means every property may be read.
+    assert_eq!(style.used_property_keys("roads", 10), None);
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use vt_optimizer::format::TileFormat;
+use vt_optimizer::mbtiles::{MbtilesScheme, TileCoord};
+use vt_optimizer::pmtiles::{decode_tile_payload_pmtiles, encode_tile_payload_pmtiles};
+use vt_optimizer::tiles::{TileRecord, TileSource, copy_tiles, create_sink, open_source};
+
+/// The first tile is stored plain and the last gzip-compressed.
+fn create_map_images_mbtiles(path: &Path) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE map (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_id TEXT
+        );
+        CREATE TABLE images (tile_id TEXT, tile_data BLOB);
+        INSERT INTO metadata (name, value) VALUES ('format', 'pbf');
+        INSERT INTO map VALUES (1, 0, 0, 'a'), (1, 1, 0, 'a'), (2, 3, 1, 'b');
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO images (tile_id, tile_data) VALUES ('a', ?1), ('b', ?2)",
+        (
+            vec![1u8; 8],
+            encode_tile_payload_pmtiles(&[2u8; 8], 1).expect("gzip tile"),
+        ),
+    )
+    .expect("images");
+}
+
+fn collect_tiles(source: &mut dyn TileSource) -> Vec<TileRecord> {
+    let mut tiles = Vec::new();
+    source
+        .for_each_tile(&mut |tile| {
+            tiles.push(tile);
+            Ok(())
+        })
+        .expect("enumerate tiles");
+    tiles.sort_by_key(|tile| (tile.coord.zoom, tile.coord.x, tile.coord.y));
+    tiles
+}
+
+fn payload(tile: &TileRecord) -> (TileCoord, Vec<u8>) {
+    let data = decode_tile_payload_pmtiles(&tile.data, tile.compression).expect("decode tile");
+    (tile.coord, data)
+}
+
+#[test]
+fn tile_sources_agree_across_formats() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_map_images_mbtiles(&mbtiles);
+
+    let mut source =
+        open_source(&mbtiles, TileFormat::Mbtiles, MbtilesScheme::Tms).expect("open mbtiles");
+    let expected = collect_tiles(source.as_mut());
+    assert_eq!(
+        expected
+            .iter()
+            .map(|tile| (tile.coord.zoom, tile.coord.x, tile.coord.y))
+            .collect::<Vec<_>>(),
+        vec![(1, 0, 1), (1, 1, 1), (2, 3, 2)]
+    );
+    let mut sink = create_sink(
+        &pmtiles,
+        TileFormat::Pmtiles,
+        source.metadata().expect("metadata"),
+        MbtilesScheme::Tms,
+    )
+    .expect("create pmtiles");
+    assert_eq!(copy_tiles(source.as_mut(), sink.as_mut()).expect("copy"), 3);
+
+    let mut copied =
+        open_source(&pmtiles, TileFormat::Pmtiles, MbtilesScheme::Tms).expect("open pmtiles");
+    // The `pbf` format makes the archive gzip whatever the first tile was.
+    let tiles = collect_tiles(copied.as_mut());
+    assert!(tiles.iter().all(|tile| tile.compression == 1));
+    assert_eq!(
+        tiles.iter().map(payload).collect::<Vec<_>>(),
+        expected.iter().map(payload).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        copied.metadata().expect("metadata").get("format"),
+        Some(&"pbf".to_string())
+    );
+    for source in [&mut source, &mut copied] {
+        let tile = source
+            .read_tile(TileCoord {
+                zoom: 2,
+                x: 3,
+                y: 2,
+            })
+            .expect("read tile")
+            .expect("tile exists");
+        assert_eq!(payload(&tile).1, vec![2u8; 8]);
+        assert!(
+            source
+                .read_tile(TileCoord {
+                    zoom: 2,
+                    x: 0,
+                    y: 0,
+                })
+                .expect("read missing tile")
+                .is_none()
+        );
+    }
+}
+
+struct MemorySource(Vec<TileRecord>);
+
+impl TileSource for MemorySource {
+    fn metadata(&self) -> Result<BTreeMap<String, String>> {
+        Ok(BTreeMap::from([("name".to_string(), "memory".to_string())]))
+    }
+
+    fn for_each_tile(&mut self, visit: &mut dyn FnMut(TileRecord) -> Result<()>) -> Result<()> {
+        self.0.iter().cloned().try_for_each(visit)
+    }
+
+    fn read_tile(&mut self, coord: TileCoord) -> Result<Option<TileRecord>> {
+        Ok(self.0.iter().find(|tile| tile.coord == coord).cloned())
+    }
+}
+
+#[test]
+fn custom_sources_plug_into_format_sinks() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let output = dir.path().join("output.mbtiles");
+    let mut source = MemorySource(vec![TileRecord {
+        coord: TileCoord {
+            zoom: 3,
+            x: 1,
+            y: 2,
+        },
+        data: vec![7u8; 4],
+        compression: 0,
+    }]);
+    let mut sink = create_sink(
+        &output,
+        TileFormat::Mbtiles,
+        source.metadata().expect("metadata"),
+        MbtilesScheme::Tms,
+    )
+    .expect("create mbtiles");
+    copy_tiles(&mut source, sink.as_mut()).expect("copy");
+
+    let conn = rusqlite::Connection::open(&output).expect("open output");
+    let (row, name): (u32, String) = conn
+        .query_row(
+            "SELECT tile_row, (SELECT value FROM metadata WHERE name = 'name') FROM tiles",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("read tile");
+    assert_eq!((row, name), (5, "memory".to_string()));
+}
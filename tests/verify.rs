@@ -0,0 +1,499 @@
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+use mvt::{GeomEncoder, GeomType, Tile};
+use vt_optimizer::mbtiles::{
+    MbtilesScheme, VerifyIssueKind, verify_mbtiles, verify_mbtiles_with_scheme,
+};
+use vt_optimizer::output::verify_ndjson_lines;
+use vt_optimizer::pmtiles::{
+    Entry, HEADER_SIZE, Header, build_header, encode_directory, mbtiles_to_pmtiles,
+    tile_id_from_xyz, verify_pmtiles, write_header,
+};
+
+fn create_layer_tile() -> Vec<u8> {
+    let mut tile = Tile::new(4096);
+    let layer = tile.create_layer("roads");
+    let geom = GeomEncoder::new(GeomType::Point)
+        .point(1.0, 2.0)
+        .expect("point")
+        .encode()
+        .expect("encode");
+    let mut feature = layer.into_feature(geom);
+    feature.add_tag_string("class", "primary");
+    let layer = feature.into_layer();
+    tile.add_layer(layer).expect("add roads");
+    tile.to_bytes().expect("tile bytes")
+}
+
+fn create_tiles_mbtiles(path: &Path, tiles: &[(i64, i64, i64, Vec<u8>)], with_metadata: bool) {
+    let conn = rusqlite::Connection::open(path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        ",
+    )
+    .expect("schema");
+    if with_metadata {
+        conn.execute_batch("CREATE TABLE metadata (name TEXT, value TEXT);")
+            .expect("metadata schema");
+    }
+    for (z, x, y, data) in tiles.iter() {
+        conn.execute(
+            "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            (z, x, y, data),
+        )
+        .expect("tile insert");
+    }
+}
+
+#[test]
+fn verify_mbtiles_accepts_valid_archive() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("valid.mbtiles");
+    create_tiles_mbtiles(
+        &path,
+        &[
+            (0, 0, 0, create_layer_tile()),
+            (1, 1, 0, create_layer_tile()),
+        ],
+        true,
+    );
+
+    let report = verify_mbtiles(&path).expect("verify");
+    assert!(report.is_ok(), "unexpected issues: {:?}", report.issues);
+    assert_eq!(report.schema.as_deref(), Some("tiles"));
+    assert!(report.has_metadata);
+    assert_eq!(report.tile_count, 2);
+}
+
+#[test]
+fn verify_mbtiles_reports_tile_errors_with_coordinates() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("broken.mbtiles");
+    create_tiles_mbtiles(
+        &path,
+        &[
+            (0, 0, 0, create_layer_tile()),
+            (1, 0, 1, vec![0x1f, 0x8b, 0x08, 0x00]),
+            (2, 3, 1, vec![0xff; 8]),
+            (1, 2, 0, create_layer_tile()),
+        ],
+        false,
+    );
+
+    let report = verify_mbtiles(&path).expect("verify");
+    assert!(!report.is_ok());
+    assert!(!report.has_metadata);
+    assert_eq!(report.tile_count, 4);
+
+    let kinds = report
+        .issues
+        .iter()
+        .map(|issue| (issue.kind, issue.zoom, issue.x, issue.y))
+        .collect::<Vec<_>>();
+    // Issues carry XYZ coordinates; the out-of-range tile keeps its tile_row.
+    assert!(kinds.contains(&(VerifyIssueKind::Schema, None, None, None)));
+    assert!(kinds.contains(&(VerifyIssueKind::Decode, Some(1), Some(0), Some(0))));
+    assert!(kinds.contains(&(VerifyIssueKind::Mvt, Some(2), Some(3), Some(2))));
+    assert!(kinds.contains(&(VerifyIssueKind::Coordinate, Some(1), Some(2), Some(0))));
+    assert_eq!(report.issues.len(), 4);
+
+    let report = verify_mbtiles_with_scheme(&path, MbtilesScheme::Xyz).expect("verify xyz");
+    assert!(
+        report
+            .issues
+            .iter()
+            .any(|issue| (issue.kind, issue.zoom, issue.x, issue.y)
+                == (VerifyIssueKind::Mvt, Some(2), Some(3), Some(1)))
+    );
+}
+
+#[test]
+fn verify_mbtiles_reports_missing_images() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("map.mbtiles");
+    let conn = rusqlite::Connection::open(&path).expect("open");
+    conn.execute_batch(
+        "
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE map (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_id TEXT
+        );
+        CREATE TABLE images (
+            tile_id TEXT,
+            tile_data BLOB
+        );
+        INSERT INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (0, 0, 0, 'a');
+        INSERT INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (1, 0, 0, 'missing');
+        ",
+    )
+    .expect("schema");
+    conn.execute(
+        "INSERT INTO images (tile_id, tile_data) VALUES ('a', ?1)",
+        (create_layer_tile(),),
+    )
+    .expect("image insert");
+    drop(conn);
+
+    let report = verify_mbtiles(&path).expect("verify");
+    assert_eq!(report.schema.as_deref(), Some("map/images"));
+    assert_eq!(report.tile_count, 2);
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].kind, VerifyIssueKind::Decode);
+    assert_eq!(report.issues[0].zoom, Some(1));
+}
+
+#[test]
+fn verify_pmtiles_checks_tile_payloads() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mbtiles = dir.path().join("input.mbtiles");
+    let pmtiles = dir.path().join("output.pmtiles");
+    create_tiles_mbtiles(&mbtiles, &[(0, 0, 0, create_layer_tile())], true);
+    mbtiles_to_pmtiles(&mbtiles, &pmtiles).expect("convert");
+
+    let report = verify_pmtiles(&pmtiles).expect("verify");
+    assert!(report.is_ok(), "unexpected issues: {:?}", report.issues);
+    assert_eq!(report.tile_count, 1);
+
+    let len = std::fs::metadata(&pmtiles).expect("metadata").len();
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(&pmtiles)
+        .expect("open pmtiles");
+    file.seek(SeekFrom::Start(len - 4)).expect("seek");
+    file.write_all(&[0xff; 4]).expect("corrupt");
+    drop(file);
+
+    let report = verify_pmtiles(&pmtiles).expect("verify");
+    assert!(!report.is_ok());
+    assert_eq!(report.issues[0].kind, VerifyIssueKind::Mvt);
+    assert_eq!(
+        (
+            report.issues[0].zoom,
+            report.issues[0].x,
+            report.issues[0].y
+        ),
+        (Some(0), Some(0), Some(0))
+    );
+}
+
+#[test]
+fn verify_ndjson_emits_summary_and_issue_lines() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("broken.mbtiles");
+    create_tiles_mbtiles(&path, &[(0, 0, 0, vec![0xff; 8])], true);
+
+    let report = verify_mbtiles(&path).expect("verify");
+    let lines = verify_ndjson_lines(&report).expect("ndjson");
+    assert_eq!(lines.len(), 2);
+    let summary: serde_json::Value = serde_json::from_str(&lines[0]).expect("summary");
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["ok"], false);
+    let issue: serde_json::Value = serde_json::from_str(&lines[1]).expect("issue");
+    assert_eq!(issue["type"], "issue");
+    assert_eq!(issue["kind"], "mvt");
+    assert_eq!(issue["zoom"], 0);
+}
+
+struct PmtilesParts {
+    root: Vec<Entry>,
+    leaves: Vec<Entry>,
+    tiles: Vec<Vec<u8>>,
+}
+
+fn write_raw_pmtiles(path: &Path, parts: &PmtilesParts, adjust: impl FnOnce(&mut Header)) {
+    let root = encode_directory(&parts.root).expect("encode root");
+    let leaves = if parts.leaves.is_empty() {
+        Vec::new()
+    } else {
+        encode_directory(&parts.leaves).expect("encode leaves")
+    };
+    let data = parts.tiles.concat();
+    let addressed = parts
+        .root
+        .iter()
+        .chain(parts.leaves.iter())
+        .map(|entry| u64::from(entry.run_length))
+        .sum::<u64>();
+    let mut header = build_header(root.len() as u64, 0, data.len() as u64, addressed, 0, 0);
+    header.internal_compression = 0;
+    header.tile_compression = 0;
+    header.leaf_offset = HEADER_SIZE as u64 + root.len() as u64;
+    header.leaf_length = leaves.len() as u64;
+    header.data_offset = header.leaf_offset + header.leaf_length;
+    let tile_entries = parts
+        .root
+        .iter()
+        .chain(parts.leaves.iter())
+        .filter(|entry| entry.run_length > 0)
+        .count() as u64;
+    header.n_tile_entries = tile_entries;
+    header.n_tile_contents = parts.tiles.len() as u64;
+    adjust(&mut header);
+
+    let file = std::fs::File::create(path).expect("create pmtiles");
+    write_header(&file, &header).expect("write header");
+    let mut file = file;
+    file.seek(SeekFrom::Start(HEADER_SIZE as u64))
+        .expect("seek root");
+    file.write_all(&root).expect("write root");
+    file.write_all(&leaves).expect("write leaves");
+    file.write_all(&data).expect("write data");
+}
+
+fn tile_entry(tile_id: u64, offset: u64, length: usize, run_length: u32) -> Entry {
+    Entry {
+        tile_id,
+        offset,
+        length: length as u32,
+        run_length,
+    }
+}
+
+fn issue_messages(path: &Path, kind: VerifyIssueKind) -> Vec<String> {
+    let report = verify_pmtiles(path).expect("verify");
+    report
+        .issues
+        .into_iter()
+        .filter(|issue| issue.kind == kind)
+        .map(|issue| issue.message)
+        .collect()
+}
+
+#[test]
+fn verify_pmtiles_accepts_leaf_directories() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("leaves.pmtiles");
+    let tile = create_layer_tile();
+    let len = tile.len();
+    let leaves = vec![
+        tile_entry(tile_id_from_xyz(1, 0, 0), 0, len, 1),
+        tile_entry(tile_id_from_xyz(1, 0, 1), len as u64, len, 1),
+    ];
+    let leaf_len = encode_directory(&leaves).expect("encode").len();
+    let parts = PmtilesParts {
+        root: vec![
+            tile_entry(0, 0, len, 1),
+            tile_entry(leaves[0].tile_id, 0, leaf_len, 0),
+        ],
+        leaves,
+        tiles: vec![tile.clone(), tile.clone()],
+    };
+    write_raw_pmtiles(&path, &parts, |header| {
+        header.max_zoom = 1;
+        header.clustered = 1;
+        header.n_tile_contents = 2;
+    });
+
+    let report = verify_pmtiles(&path).expect("verify");
+    assert!(report.is_ok(), "unexpected issues: {:?}", report.issues);
+    assert_eq!(report.tile_count, 3);
+}
+
+#[test]
+fn verify_pmtiles_reads_self_referencing_leaf_once() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("cycle.pmtiles");
+    let tile = create_layer_tile();
+    let len = tile.len();
+    let leaf_tile_id = tile_id_from_xyz(1, 0, 0);
+    // The leaf's last entry points back at the leaf itself; re-encode until
+    // the varint-encoded length it declares matches its own size.
+    let mut leaf_len = 0;
+    let leaves = loop {
+        let leaves = vec![
+            tile_entry(leaf_tile_id, 0, len, 1),
+            tile_entry(leaf_tile_id + 1, 0, leaf_len, 0),
+        ];
+        let encoded = encode_directory(&leaves).expect("encode").len();
+        if encoded == leaf_len {
+            break leaves;
+        }
+        leaf_len = encoded;
+    };
+    let parts = PmtilesParts {
+        root: vec![
+            tile_entry(0, 0, len, 1),
+            tile_entry(leaf_tile_id, 0, leaf_len, 0),
+        ],
+        leaves,
+        tiles: vec![tile],
+    };
+    write_raw_pmtiles(&path, &parts, |header| {
+        header.max_zoom = 1;
+        header.n_tile_contents = 1;
+    });
+
+    let report = verify_pmtiles(&path).expect("verify");
+    assert_eq!(report.tile_count, 2);
+    let messages = issue_messages(&path, VerifyIssueKind::Directory);
+    assert!(
+        messages
+            .iter()
+            .any(|msg| msg.contains("referenced more than once")),
+        "{messages:?}"
+    );
+    assert!(
+        !messages.iter().any(|msg| msg.contains("nested deeper")),
+        "{messages:?}"
+    );
+}
+
+#[test]
+fn verify_pmtiles_reports_directory_ordering_and_overlap() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("order.pmtiles");
+    let tile = create_layer_tile();
+    let len = tile.len();
+    let parts = PmtilesParts {
+        root: vec![tile_entry(1, 0, len, 3), tile_entry(2, 0, len, 1)],
+        leaves: Vec::new(),
+        tiles: vec![tile],
+    };
+    write_raw_pmtiles(&path, &parts, |header| {
+        header.min_zoom = 1;
+        header.max_zoom = 1;
+    });
+
+    let messages = issue_messages(&path, VerifyIssueKind::Directory);
+    assert!(
+        messages.iter().any(|msg| msg.contains("overlaps")),
+        "{messages:?}"
+    );
+
+    let duplicated = dir.path().join("duplicated.pmtiles");
+    let tile = create_layer_tile();
+    let len = tile.len();
+    let parts = PmtilesParts {
+        root: vec![tile_entry(2, 0, len, 1), tile_entry(2, 0, len, 1)],
+        leaves: Vec::new(),
+        tiles: vec![tile],
+    };
+    write_raw_pmtiles(&duplicated, &parts, |header| {
+        header.min_zoom = 1;
+        header.max_zoom = 1;
+    });
+
+    let messages = issue_messages(&duplicated, VerifyIssueKind::Directory);
+    assert!(
+        messages
+            .iter()
+            .any(|msg| msg.contains("tile_id 2 does not increase")),
+        "{messages:?}"
+    );
+}
+
+#[test]
+fn verify_pmtiles_reports_offsets_outside_data_section() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("offsets.pmtiles");
+    let tile = create_layer_tile();
+    let len = tile.len();
+    let parts = PmtilesParts {
+        root: vec![tile_entry(0, 0, len, 1), tile_entry(1, len as u64, len, 1)],
+        leaves: Vec::new(),
+        tiles: vec![tile],
+    };
+    write_raw_pmtiles(&path, &parts, |header| {
+        header.max_zoom = 1;
+        header.n_tile_contents = 2;
+    });
+
+    let report = verify_pmtiles(&path).expect("verify");
+    let issue = report
+        .issues
+        .iter()
+        .find(|issue| issue.kind == VerifyIssueKind::Directory)
+        .expect("directory issue");
+    assert!(issue.message.contains("exceeds data_length"));
+    assert_eq!((issue.zoom, issue.x, issue.y), (Some(1), Some(0), Some(0)));
+
+    let overflow = dir.path().join("overflow.pmtiles");
+    let tile = create_layer_tile();
+    let len = tile.len();
+    let parts = PmtilesParts {
+        root: vec![tile_entry(0, u64::MAX - 1, len, 1)],
+        leaves: Vec::new(),
+        tiles: vec![tile],
+    };
+    write_raw_pmtiles(&overflow, &parts, |_| {});
+    let messages = issue_messages(&overflow, VerifyIssueKind::Directory);
+    assert!(
+        messages
+            .iter()
+            .any(|msg| msg.contains("exceeds data_length")),
+        "{messages:?}"
+    );
+
+    // A 4 GiB length is rejected before any buffer is allocated for it.
+    let oversized = dir.path().join("oversized.pmtiles");
+    let tile = create_layer_tile();
+    let parts = PmtilesParts {
+        root: vec![tile_entry(0, 0, u32::MAX as usize, 1)],
+        leaves: Vec::new(),
+        tiles: vec![tile],
+    };
+    write_raw_pmtiles(&oversized, &parts, |_| {});
+    let messages = issue_messages(&oversized, VerifyIssueKind::Directory);
+    assert!(
+        messages
+            .iter()
+            .any(|msg| msg.contains("exceeds data_length")),
+        "{messages:?}"
+    );
+}
+
+#[test]
+fn verify_pmtiles_reports_header_mismatches() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("header.pmtiles");
+    let tile_a = create_layer_tile();
+    let tile_b = create_layer_tile();
+    let len = tile_a.len();
+    let parts = PmtilesParts {
+        root: vec![tile_entry(0, len as u64, len, 1), tile_entry(1, 0, len, 2)],
+        leaves: Vec::new(),
+        tiles: vec![tile_a, tile_b],
+    };
+    write_raw_pmtiles(&path, &parts, |header| {
+        header.n_addressed_tiles = 5;
+        header.n_tile_entries = 3;
+        header.n_tile_contents = 1;
+        header.min_zoom = 0;
+        header.max_zoom = 4;
+        header.clustered = 1;
+    });
+
+    let messages = issue_messages(&path, VerifyIssueKind::Header);
+    assert!(
+        messages
+            .iter()
+            .any(|msg| msg.starts_with("n_addressed_tiles is 5 but 3"))
+    );
+    assert!(
+        messages
+            .iter()
+            .any(|msg| msg.starts_with("n_tile_entries is 3 but 2"))
+    );
+    assert!(
+        messages
+            .iter()
+            .any(|msg| msg.starts_with("n_tile_contents is 1 but 2"))
+    );
+    assert!(messages.iter().any(|msg| msg.contains("tiles span 0..=1")));
+    assert!(
+        messages
+            .iter()
+            .any(|msg| msg.starts_with("clustered flag is set"))
+    );
+}